@@ -0,0 +1,211 @@
+use crate::triangle::Triangle;
+use nalgebra::{Point3, Vector3};
+use std::collections::HashMap;
+
+/// An edge of a welded mesh and the faces on either side of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edge {
+    pub a: usize,
+    pub b: usize,
+    pub faces: Vec<usize>,
+}
+
+/// Triangle soup (as read from an STL file) welded into shared vertices,
+/// so that each edge knows which faces meet along it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adjacency {
+    pub vertices: Vec<Point3<f32>>,
+    pub faces: Vec<[usize; 3]>,
+    pub edges: Vec<Edge>,
+}
+
+impl Adjacency {
+    /// vertices closer than a millionth of the mesh's size are merged
+    pub fn new(triangles: &[Triangle]) -> Self {
+        let points = triangles
+            .iter()
+            .flat_map(|t| vec![t.p1, t.p2, t.p3])
+            .collect::<Vec<_>>();
+
+        let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        for point in points.iter() {
+            min = min.inf(point);
+            max = max.sup(point);
+        }
+
+        let extent = (max - min).max();
+        let tolerance = if extent.is_finite() && extent > 0.0 {
+            extent * 1e-6
+        } else {
+            1e-6
+        };
+
+        let mut vertices = vec![];
+        let mut welded: HashMap<[i64; 3], usize> = HashMap::new();
+
+        let mut weld = |point: Point3<f32>| {
+            let key = [
+                (point.x / tolerance).round() as i64,
+                (point.y / tolerance).round() as i64,
+                (point.z / tolerance).round() as i64,
+            ];
+
+            *welded.entry(key).or_insert_with(|| {
+                vertices.push(point);
+                vertices.len() - 1
+            })
+        };
+
+        let faces = points
+            .chunks(3)
+            .map(|corners| [weld(corners[0]), weld(corners[1]), weld(corners[2])])
+            .collect::<Vec<_>>();
+
+        let mut edge_indices: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edges: Vec<Edge> = vec![];
+
+        for (face, corners) in faces.iter().enumerate() {
+            for i in 0..3 {
+                let (a, b) = (corners[i], corners[(i + 1) % 3]);
+
+                if a == b {
+                    continue;
+                }
+
+                let key = (a.min(b), a.max(b));
+
+                let index = *edge_indices.entry(key).or_insert_with(|| {
+                    edges.push(Edge {
+                        a: key.0,
+                        b: key.1,
+                        faces: vec![],
+                    });
+                    edges.len() - 1
+                });
+
+                edges[index].faces.push(face);
+            }
+        }
+
+        Adjacency {
+            vertices,
+            faces,
+            edges,
+        }
+    }
+
+    /// `None` for degenerate faces
+    pub fn face_normal(&self, face: usize) -> Option<Vector3<f32>> {
+        let [a, b, c] = self.faces[face];
+        let (a, b, c) = (self.vertices[a], self.vertices[b], self.vertices[c]);
+
+        (b - a).cross(&(c - a)).try_normalize(f32::EPSILON)
+    }
+
+    /// edges with only one face: holes and the rims of open surfaces
+    pub fn boundary_edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(|edge| edge.faces.len() == 1)
+    }
+
+    /// edges where the faces meet at more than `angle` radians, plus edges
+    /// shared by more than two faces
+    pub fn crease_edges(&self, angle: f32) -> impl Iterator<Item = &Edge> {
+        let cos_angle = angle.cos();
+
+        self.edges
+            .iter()
+            .filter(move |edge| match edge.faces.as_slice() {
+                [f1, f2] => match (self.face_normal(*f1), self.face_normal(*f2)) {
+                    (Some(n1), Some(n2)) => n1.dot(&n2) < cos_angle,
+                    _ => false,
+                },
+                faces => faces.len() > 2,
+            })
+    }
+
+    /// edges between a face turned towards `eye` and one turned away
+    pub fn silhouette_edges(&self, eye: Point3<f32>) -> impl Iterator<Item = &Edge> {
+        self.edges
+            .iter()
+            .filter(move |edge| match edge.faces.as_slice() {
+                [f1, f2] => {
+                    let view = self.vertices[edge.a] - eye;
+
+                    match (self.face_normal(*f1), self.face_normal(*f2)) {
+                        (Some(n1), Some(n2)) => (n1.dot(&view) < 0.0) != (n2.dot(&view) < 0.0),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            })
+    }
+
+    pub fn endpoints(&self, edge: &Edge) -> (Point3<f32>, Point3<f32>) {
+        (self.vertices[edge.a], self.vertices[edge.b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a unit square in the xz plane, folded up along z = 0 by `fold` radians
+    fn folded_square(fold: f32) -> Vec<Triangle> {
+        let far = Point3::new(1.0, fold.sin(), -fold.cos());
+
+        vec![
+            Triangle::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 1.0),
+            ),
+            Triangle::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ),
+            Triangle::new(Point3::new(0.0, 0.0, 0.0), far, Point3::new(1.0, 0.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn welding_shares_vertices_between_triangles() {
+        let adjacency = Adjacency::new(&folded_square(0.0));
+        assert_eq!(adjacency.vertices.len(), 5);
+        assert_eq!(adjacency.faces.len(), 3);
+        assert_eq!(adjacency.edges.len(), 7);
+    }
+
+    #[test]
+    fn boundary_edges_have_one_face() {
+        let adjacency = Adjacency::new(&folded_square(0.0));
+        assert_eq!(adjacency.boundary_edges().count(), 5);
+    }
+
+    #[test]
+    fn creases_depend_on_the_dihedral_angle() {
+        let flat = Adjacency::new(&folded_square(0.0));
+        assert_eq!(flat.crease_edges(0.5).count(), 0);
+
+        let folded = Adjacency::new(&folded_square(1.0));
+        let creases = folded.crease_edges(0.5).collect::<Vec<_>>();
+        assert_eq!(creases.len(), 1);
+        assert_eq!(
+            folded.endpoints(creases[0]),
+            (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(folded.crease_edges(1.5).count(), 0);
+    }
+
+    #[test]
+    fn silhouettes_separate_front_and_back_faces() {
+        let adjacency = Adjacency::new(&folded_square(2.0));
+        let in_front = adjacency.silhouette_edges(Point3::new(0.5, 0.5, 5.0));
+        assert_eq!(in_front.count(), 0);
+
+        let behind = adjacency.silhouette_edges(Point3::new(0.5, 0.5, -5.0));
+        assert_eq!(behind.count(), 1);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::canvas::Canvas;
+use crate::exr::write_exr;
+use crate::gbuffer::{GBuffer, SurfaceSample};
+use crate::material::Color;
+use nalgebra::Vector3;
+use std::str::FromStr;
+
+/// An auxiliary render pass (arbitrary output variable).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aov {
+    /// distance from the camera along each ray
+    Depth,
+    /// world space, facing the camera
+    Normal,
+    /// world-space hit point
+    Position,
+    /// index of the object in the world
+    ObjectId,
+    /// index of the triangle within the mesh
+    PrimitiveId,
+    /// surface color before lighting
+    Albedo,
+    /// 1 where the light is blocked
+    Shadow,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Position,
+        Aov::ObjectId,
+        Aov::PrimitiveId,
+        Aov::Albedo,
+        Aov::Shadow,
+    ];
+
+    /// the EXR layer name and its channels
+    fn layer(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Aov::Depth => ("depth", &["Z"]),
+            Aov::Normal => ("normal", &["X", "Y", "Z"]),
+            Aov::Position => ("position", &["X", "Y", "Z"]),
+            Aov::ObjectId => ("object", &["id"]),
+            Aov::PrimitiveId => ("primitive", &["id"]),
+            Aov::Albedo => ("albedo", &["R", "G", "B"]),
+            Aov::Shadow => ("shadow", &["Y"]),
+        }
+    }
+
+    /// the pass's raw values at a pixel; misses are infinitely deep, have
+    /// an id of -1 and are zero otherwise
+    fn values(self, surface: Option<&SurfaceSample>) -> Vec<f32> {
+        let surface = match surface {
+            Some(surface) => surface,
+            None => {
+                return match self {
+                    Aov::Depth => vec![f32::INFINITY],
+                    Aov::ObjectId | Aov::PrimitiveId => vec![-1.0],
+                    Aov::Shadow => vec![0.0],
+                    _ => vec![0.0; 3],
+                }
+            }
+        };
+
+        match self {
+            Aov::Depth => vec![surface.depth],
+            Aov::Normal => surface.normal.iter().copied().collect(),
+            Aov::Position => surface.position.coords.iter().copied().collect(),
+            Aov::ObjectId => vec![surface.object as f32],
+            Aov::PrimitiveId => vec![surface.primitive.map(|p| p as f32).unwrap_or(-1.0)],
+            Aov::Albedo => surface.albedo.iter().copied().collect(),
+            Aov::Shadow => vec![if surface.shadowed { 1.0 } else { 0.0 }],
+        }
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "depth" => Ok(Aov::Depth),
+            "normal" => Ok(Aov::Normal),
+            "position" => Ok(Aov::Position),
+            "object" | "object-id" => Ok(Aov::ObjectId),
+            "primitive" | "primitive-id" | "triangle" => Ok(Aov::PrimitiveId),
+            "albedo" => Ok(Aov::Albedo),
+            "shadow" => Ok(Aov::Shadow),
+            _ => Err(format!("unknown render pass: {}", s)),
+        }
+    }
+}
+
+/// The beauty image and the auxiliary passes from a single render.
+pub struct Passes {
+    pub(crate) canvas: Canvas,
+    pub(crate) buffers: GBuffer,
+    /// names of the parts, by their object id
+    pub(crate) names: Vec<(usize, String)>,
+}
+
+impl Passes {
+    pub fn beauty(&self, format: image::ImageFormat) -> Result<Vec<u8>, String> {
+        self.canvas.to_image(format)
+    }
+
+    /// the name of the part seen at a pixel, if it was given one
+    pub fn name_at(&self, x: usize, y: usize) -> Option<&str> {
+        let object = self.buffers.surface_at(x, y)?.object;
+
+        self.names
+            .iter()
+            .find(|(id, _)| *id == object)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// an 8-bit preview of a pass: depth from white (near) to black (far),
+    /// normals as `n * 0.5 + 0.5`, positions scaled to the hits' bounds and
+    /// ids as arbitrary distinct colors
+    pub fn aov(&self, aov: Aov, format: image::ImageFormat) -> Result<Vec<u8>, String> {
+        let (width, height) = (self.buffers.width, self.buffers.height);
+        let surfaces = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| self.buffers.surface_at(x, y))
+            .collect::<Vec<_>>();
+
+        let range = |value: fn(&SurfaceSample) -> Vector3<f32>| {
+            surfaces.iter().fold(
+                (
+                    Vector3::repeat(f32::INFINITY),
+                    Vector3::repeat(f32::NEG_INFINITY),
+                ),
+                |(min, max), s| (min.inf(&value(s)), max.sup(&value(s))),
+            )
+        };
+
+        let (near, far) = range(|s| Vector3::repeat(s.depth));
+        let (low, high) = range(|s| s.position.coords);
+
+        let mut canvas = Canvas::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let surface = match self.buffers.surface_at(x, y) {
+                    Some(surface) => surface,
+                    None => continue,
+                };
+
+                let color = match aov {
+                    Aov::Depth => {
+                        let span = (far.x - near.x).max(f32::EPSILON);
+                        Vector3::repeat(1.0 - (surface.depth - near.x) / span)
+                    }
+                    Aov::Normal => surface.normal * 0.5 + Vector3::repeat(0.5),
+                    Aov::Position => (surface.position.coords - low)
+                        .component_div(&(high - low).map(|d| d.max(f32::EPSILON))),
+                    Aov::ObjectId => id_color(surface.object),
+                    Aov::PrimitiveId => surface
+                        .primitive
+                        .map(id_color)
+                        .unwrap_or_else(|| Vector3::repeat(1.0)),
+                    Aov::Albedo => surface.albedo,
+                    Aov::Shadow => Vector3::repeat(if surface.shadowed { 1.0 } else { 0.0 }),
+                };
+
+                canvas.write_pixel(x, y, color);
+            }
+        }
+
+        canvas.to_image(format)
+    }
+
+    /// every pass in linear float precision as one multi-layer EXR, with the
+    /// beauty image in the unnamed R, G, B and A channels
+    pub fn exr(&self) -> Vec<u8> {
+        let (width, height) = (self.buffers.width, self.buffers.height);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+
+        let mut channels = ["R", "G", "B"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let values = pixels
+                    .iter()
+                    .map(|&(x, y)| self.canvas.pixel_at(x, y)[i])
+                    .collect();
+                (name.to_string(), values)
+            })
+            .collect::<Vec<_>>();
+
+        channels.push((
+            "A".to_string(),
+            pixels
+                .iter()
+                .map(|&(x, y)| self.canvas.alpha_at(x, y))
+                .collect(),
+        ));
+
+        for aov in Aov::ALL.iter() {
+            let (layer, names) = aov.layer();
+            let values = pixels
+                .iter()
+                .map(|&(x, y)| aov.values(self.buffers.surface_at(x, y)))
+                .collect::<Vec<_>>();
+
+            for (i, name) in names.iter().enumerate() {
+                channels.push((
+                    format!("{}.{}", layer, name),
+                    values.iter().map(|v| v[i]).collect(),
+                ));
+            }
+        }
+
+        write_exr(width, height, &channels)
+    }
+}
+
+/// well spread colors for consecutive ids
+fn id_color(id: usize) -> Color {
+    let hash = (id as u32 + 1).wrapping_mul(0x9e37_79b9);
+
+    Vector3::new(
+        (hash >> 24) as f32 / 255.0,
+        ((hash >> 16) & 0xff) as f32 / 255.0,
+        ((hash >> 8) & 0xff) as f32 / 255.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes() -> Passes {
+        let mut buffers = GBuffer::new(2, 1);
+        buffers.write(
+            0,
+            0,
+            Some(SurfaceSample {
+                primitive: Some(7),
+                shadowed: true,
+                ..SurfaceSample::at_depth(3, 2.5)
+            }),
+        );
+
+        Passes {
+            canvas: Canvas::new(2, 1),
+            buffers,
+            names: vec![(3, "bracket".to_string())],
+        }
+    }
+
+    #[test]
+    fn pass_values_at_hits_and_misses() {
+        let passes = passes();
+        let hit = passes.buffers.surface_at(0, 0);
+        assert_eq!(Aov::Depth.values(hit), vec![2.5]);
+        assert_eq!(Aov::ObjectId.values(hit), vec![3.0]);
+        assert_eq!(Aov::PrimitiveId.values(hit), vec![7.0]);
+        assert_eq!(Aov::Shadow.values(hit), vec![1.0]);
+        assert_eq!(Aov::Position.values(hit), vec![0.0, 0.0, 2.5]);
+
+        let miss = passes.buffers.surface_at(1, 0);
+        assert_eq!(Aov::Depth.values(miss), vec![f32::INFINITY]);
+        assert_eq!(Aov::PrimitiveId.values(miss), vec![-1.0]);
+        assert_eq!(Aov::Normal.values(miss), vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn every_pass_becomes_an_exr_layer() {
+        let exr = passes().exr();
+        let text = String::from_utf8_lossy(&exr);
+
+        for name in &[
+            "A",
+            "depth.Z",
+            "normal.Y",
+            "object.id",
+            "albedo.B",
+            "shadow.Y",
+        ] {
+            assert!(text.contains(&format!("{}\0", name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn passes_can_be_saved_as_images() {
+        let passes = passes();
+
+        for aov in Aov::ALL.iter() {
+            let png = passes.aov(*aov, image::ImageFormat::Png).unwrap();
+            assert!(image::load_from_memory(&png).is_ok());
+        }
+    }
+
+    #[test]
+    fn passes_by_name() {
+        assert_eq!("depth".parse(), Ok(Aov::Depth));
+        assert_eq!("primitive-id".parse(), Ok(Aov::PrimitiveId));
+        assert!("beauty".parse::<Aov>().is_err());
+    }
+
+    #[test]
+    fn naming_the_part_under_a_pixel() {
+        let passes = passes();
+
+        assert_eq!(passes.name_at(0, 0), Some("bracket"));
+        assert_eq!(passes.name_at(1, 0), None);
+    }
+}
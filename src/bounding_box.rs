@@ -1,10 +1,11 @@
-use nalgebra::Point3;
+use crate::ray::Ray;
+use nalgebra::{Matrix4, Point3};
 
 pub trait BoundingBox {
     fn bounding_box(&self) -> AABB;
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct AABB {
     pub min: Point3<f32>,
     pub max: Point3<f32>,
@@ -31,6 +32,88 @@ impl AABB {
     //     AABB { min, max }
     // }
 
+    /// the smallest box holding every point
+    pub fn around(points: impl IntoIterator<Item = Point3<f32>>) -> Self {
+        let mut aabb = AABB {
+            min: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        };
+
+        for point in points {
+            aabb.min = aabb.min.inf(&point);
+            aabb.max = aabb.max.sup(&point);
+        }
+
+        aabb
+    }
+
+    /// the box around this one once moved by `transform`, unbounded if this
+    /// one is
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Self {
+        let finite = self
+            .min
+            .iter()
+            .chain(self.max.iter())
+            .all(|c| c.is_finite());
+
+        if !finite {
+            return AABB::default();
+        }
+
+        let corners = (0..8).map(|i| {
+            let corner = Point3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+
+            transform.transform_point(&corner)
+        });
+
+        AABB::around(corners)
+    }
+
+    /// the box around both
+    pub fn union(&self, other: &AABB) -> Self {
+        AABB {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    /// whether `ray` passes through the box, ahead of its origin or not
+    pub fn is_hit_by(&self, ray: &Ray) -> bool {
+        let mut near = f32::NEG_INFINITY;
+        let mut far = f32::INFINITY;
+
+        for axis in 0..3 {
+            if self.min[axis] > self.max[axis] {
+                return false;
+            }
+
+            let inverse = 1.0 / ray.direction[axis];
+            let t0 = (self.min[axis] - ray.origin[axis]) * inverse;
+            let t1 = (self.max[axis] - ray.origin[axis]) * inverse;
+
+            // NaNs, from a ray along a face of an unbounded box, are skipped
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        near <= far
+    }
+
+    /// zero for points inside the box
+    pub fn distance_squared_to(&self, point: Point3<f32>) -> f32 {
+        let below = self.min - point;
+        let above = point - self.max;
+
+        below
+            .sup(&above)
+            .sup(&nalgebra::Vector3::zeros())
+            .norm_squared()
+    }
+
     pub fn merge_mut(&mut self, other: AABB) {
         if other.min < self.min {
             self.min = other.min;
@@ -0,0 +1,266 @@
+use crate::noise::perlin;
+use crate::shape::Shape;
+use crate::texture::Texture;
+use nalgebra::{Matrix4, Point2, Point3, Projective3, Transform, Vector3};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// A tangent-space normal map: red is along the surface tangent (+u), green
+/// along the bitangent (+v) and blue along the normal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalMap {
+    pub texture: Arc<Texture>,
+    /// 0.0 leaves the surface flat, 1.0 applies the map as authored
+    pub strength: f32,
+}
+
+impl NormalMap {
+    pub fn new(texture: Arc<Texture>) -> Self {
+        NormalMap {
+            texture,
+            strength: 1.0,
+        }
+    }
+
+    /// needs texture coordinates and a tangent frame; surfaces without them are left alone
+    pub fn perturb(
+        &self,
+        object: &dyn Shape,
+        point: Point3<f32>,
+        uv: Option<Point2<f32>>,
+        normal: Vector3<f32>,
+    ) -> Vector3<f32> {
+        let (uv, (tangent, bitangent)) = match (uv, object.tangent_at(point)) {
+            (Some(uv), Some(frame)) => (uv, frame),
+            _ => return normal,
+        };
+
+        let texel = self.texture.sample(uv) * 2.0 - Vector3::new(1.0, 1.0, 1.0);
+        let x = texel.x * self.strength;
+        let y = texel.y * self.strength;
+        let z = texel.z;
+
+        // the frame's normal follows the uv winding, whichever way `normal` faces
+        let flipped = tangent.cross(&bitangent).dot(&normal) < 0.0;
+        let frame_normal = if flipped { -normal } else { normal };
+
+        let tangent =
+            match (tangent - frame_normal * frame_normal.dot(&tangent)).try_normalize(1e-8) {
+                Some(t) => t,
+                None => return normal,
+            };
+
+        let bitangent = frame_normal.cross(&tangent);
+
+        match (tangent * x + bitangent * y + frame_normal * z).try_normalize(1e-8) {
+            Some(perturbed) if flipped => -perturbed,
+            Some(perturbed) => perturbed,
+            None => normal,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BumpKind {
+    /// a few octaves of Perlin noise, for cast or sand-blasted finishes
+    Noise,
+    /// ridges crossing diagonally in the xz plane, forming a diamond knurl
+    Knurl,
+    /// fine striations running along x
+    Brushed,
+}
+
+/// A procedural height field that tilts normals by its gradient. It is
+/// evaluated in object space after `transform`, so scaling the transform
+/// changes the feature size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bump {
+    pub kind: BumpKind,
+    pub strength: f32,
+    pub transform: Matrix4<f32>,
+}
+
+impl Bump {
+    pub fn new(kind: BumpKind, strength: f32) -> Self {
+        Bump {
+            kind,
+            strength,
+            transform: Matrix4::identity(),
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4<f32>) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn height_at(&self, point: Point3<f32>) -> f32 {
+        match self.kind {
+            BumpKind::Noise => (0..4)
+                .map(|octave| {
+                    let frequency = 2.0_f32.powi(octave);
+                    perlin(point * frequency) / frequency
+                })
+                .sum(),
+            BumpKind::Knurl => {
+                let ridge = |t: f32| (t * PI).sin().abs();
+                ridge(point.x + point.z) + ridge(point.x - point.z)
+            }
+            BumpKind::Brushed => (0..3)
+                .map(|octave| {
+                    let frequency = 2.0_f32.powi(octave);
+                    let stretched = Point3::new(point.x * 0.05, point.y * 20.0, point.z * 20.0);
+                    perlin(stretched * frequency) / frequency
+                })
+                .sum(),
+        }
+    }
+
+    pub fn perturb(
+        &self,
+        object: &dyn Shape,
+        world_point: Point3<f32>,
+        normal: Vector3<f32>,
+    ) -> Vector3<f32> {
+        let object_transform: Projective3<f32> =
+            Transform::from_matrix_unchecked(object.transform());
+        let bump_transform: Projective3<f32> = Transform::from_matrix_unchecked(self.transform);
+        let point = bump_transform
+            .inverse_transform_point(&object_transform.inverse_transform_point(&world_point));
+
+        let gradient = self.gradient_at(point);
+
+        // gradients are covectors, so they go back to world space like normals do
+        let to_world = (object.transform() * self.transform).transpose();
+        let to_world: Projective3<f32> = Transform::from_matrix_unchecked(to_world);
+        let gradient = to_world.inverse_transform_vector(&gradient);
+
+        let tangential = gradient - normal * normal.dot(&gradient);
+
+        (normal - tangential * self.strength)
+            .try_normalize(1e-8)
+            .unwrap_or(normal)
+    }
+
+    fn gradient_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let h = 0.001;
+        let dx = Vector3::new(h, 0.0, 0.0);
+        let dy = Vector3::new(0.0, h, 0.0);
+        let dz = Vector3::new(0.0, 0.0, h);
+
+        Vector3::new(
+            self.height_at(point + dx) - self.height_at(point - dx),
+            self.height_at(point + dy) - self.height_at(point - dy),
+            self.height_at(point + dz) - self.height_at(point - dz),
+        ) / (2.0 * h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane::Plane;
+    use crate::triangle::Triangle;
+
+    fn approx_eq(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+        (a - b).norm() < 0.0001
+    }
+
+    fn uv_triangle() -> Triangle {
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        )
+        .with_uvs(
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(0.0, 1.0),
+        )
+    }
+
+    fn flat_map(texel: Vector3<f32>) -> NormalMap {
+        NormalMap::new(Arc::new(Texture::new(1, 1, vec![texel]).unwrap()))
+    }
+
+    #[test]
+    fn a_flat_normal_map_leaves_the_normal_alone() {
+        let triangle = uv_triangle();
+        let normal = triangle.normal;
+        let map = flat_map(Vector3::new(0.5, 0.5, 1.0));
+        let uv = Some(Point2::new(0.25, 0.25));
+        let result = map.perturb(&triangle, Point3::new(0.25, 0.25, 0.0), uv, normal);
+        assert!(approx_eq(result, normal));
+    }
+
+    #[test]
+    fn a_normal_map_tilts_the_normal_towards_the_tangent() {
+        let triangle = uv_triangle();
+        let map = flat_map(Vector3::new(1.0, 0.5, 0.5));
+        let uv = Some(Point2::new(0.25, 0.25));
+        let outward = Vector3::new(0.0, 0.0, 1.0);
+        let result = map.perturb(&triangle, Point3::new(0.25, 0.25, 0.0), uv, outward);
+        assert!(approx_eq(result, Vector3::new(1.0, 0.0, 0.0)));
+
+        let inward = map.perturb(&triangle, Point3::new(0.25, 0.25, 0.0), uv, -outward);
+        assert!(approx_eq(inward, -result));
+    }
+
+    #[test]
+    fn a_normal_map_needs_texture_coordinates() {
+        let triangle = uv_triangle();
+        let map = flat_map(Vector3::new(1.0, 0.5, 0.5));
+        let result = map.perturb(
+            &triangle,
+            Point3::new(0.25, 0.25, 0.0),
+            None,
+            triangle.normal,
+        );
+        assert_eq!(result, triangle.normal);
+    }
+
+    #[test]
+    fn a_bump_with_no_strength_leaves_the_normal_alone() {
+        let plane = Plane::new();
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let bump = Bump::new(BumpKind::Knurl, 0.0);
+        let result = bump.perturb(&plane, Point3::new(0.3, 0.0, 0.1), normal);
+        assert_eq!(result, normal);
+    }
+
+    #[test]
+    fn bumps_produce_unit_normals() {
+        let plane = Plane::new();
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+
+        for kind in &[BumpKind::Noise, BumpKind::Knurl, BumpKind::Brushed] {
+            let bump = Bump::new(*kind, 0.5);
+            for i in 0..20 {
+                let point = Point3::new(i as f32 * 0.137, 0.0, i as f32 * 0.071);
+                let result = bump.perturb(&plane, point, normal);
+                assert!((result.norm() - 1.0).abs() < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn a_knurl_tilts_normals_across_its_ridges() {
+        let plane = Plane::new();
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let bump = Bump::new(BumpKind::Knurl, 0.5);
+        let result = bump.perturb(&plane, Point3::new(0.25, 0.0, 0.0), normal);
+        assert!(result.x.abs() > 0.1);
+        assert!(result.y > 0.0);
+    }
+
+    #[test]
+    fn scaling_a_bump_changes_its_feature_size() {
+        let small = Bump::new(BumpKind::Knurl, 1.0);
+        let large = small.with_transform(Matrix4::new_scaling(2.0));
+        let plane = Plane::new();
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let a = small.perturb(&plane, Point3::new(0.25, 0.0, 0.0), normal);
+        let b = large.perturb(&plane, Point3::new(0.5, 0.0, 0.0), normal);
+        assert!(a.x.abs() > b.x.abs());
+    }
+}
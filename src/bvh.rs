@@ -0,0 +1,517 @@
+use crate::bounding_box::AABB;
+use crate::ray::Ray;
+use crate::triangle::Triangle;
+use nalgebra::{Point2, Point3, Vector3};
+
+const LEAF_SIZE: usize = 4;
+
+/// A bounding volume hierarchy over a mesh's triangles, for finding the
+/// nearest point on the surface and tracing rays against it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bvh {
+    triangles: Vec<[Point3<f32>; 3]>,
+    /// the texture coordinates of each triangle, if it has them
+    uvs: Vec<Option<[Point2<f32>; 3]>>,
+    /// where each triangle was in the slice the hierarchy was built from
+    indices: Vec<usize>,
+    tree: Tree,
+}
+
+/// Boxes split at the median along their longest axis until the leaves are
+/// small; leaves are ranges of the items the tree was built over.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Tree {
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Leaf {
+        bounds: AABB,
+        start: usize,
+        end: usize,
+    },
+    Branch {
+        bounds: AABB,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &AABB {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+impl Tree {
+    /// builds the tree over `items`, reordering them so each leaf is a range
+    pub(crate) fn new<T>(items: &mut [T], bounds: impl Fn(&T) -> AABB) -> Self {
+        let mut tree = Tree { nodes: vec![] };
+
+        if !items.is_empty() {
+            tree.build(items, 0, items.len(), &bounds);
+        }
+
+        tree
+    }
+
+    /// splits `items[start..end]` at the median of their centers along the
+    /// longest axis, returning the node's index
+    fn build<T>(
+        &mut self,
+        items: &mut [T],
+        start: usize,
+        end: usize,
+        bounds: &impl Fn(&T) -> AABB,
+    ) -> usize {
+        let around = items[start..end]
+            .iter()
+            .map(bounds)
+            .fold(AABB::around(vec![]), |a, b| a.union(&b));
+        let index = self.nodes.len();
+
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf {
+                bounds: around,
+                start,
+                end,
+            });
+            return index;
+        }
+
+        let center = |item: &T| {
+            let bounds = bounds(item);
+            Point3::from((bounds.min.coords + bounds.max.coords) / 2.0)
+        };
+        let centers = AABB::around(items[start..end].iter().map(center));
+        let axis = (centers.max - centers.min).imax();
+
+        let middle = (start + end) / 2;
+        items[start..end].select_nth_unstable_by(middle - start, |a, b| {
+            center(a)[axis]
+                .partial_cmp(&center(b)[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        // reserve the slot so children come after their parent
+        self.nodes.push(Node::Leaf {
+            bounds: around,
+            start,
+            end,
+        });
+
+        let left = self.build(items, start, middle, bounds);
+        let right = self.build(items, middle, end, bounds);
+
+        self.nodes[index] = Node::Branch {
+            bounds: around,
+            left,
+            right,
+        };
+
+        index
+    }
+
+    /// the box around everything, empty for an empty tree
+    pub(crate) fn bounds(&self) -> AABB {
+        self.nodes
+            .first()
+            .map_or_else(|| AABB::around(vec![]), |node| *node.bounds())
+    }
+
+    /// the ranges of items in the leaves whose boxes `ray` passes through
+    pub(crate) fn along(&self, ray: &Ray) -> Vec<(usize, usize)> {
+        let mut ranges = vec![];
+        let mut stack = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            if !node.bounds().is_hit_by(ray) {
+                continue;
+            }
+
+            match *node {
+                Node::Leaf { start, end, .. } => ranges.push((start, end)),
+                Node::Branch { left, right, .. } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+
+        ranges
+    }
+}
+
+/// The point on a mesh nearest to a query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Closest {
+    pub point: Point3<f32>,
+    pub distance: f32,
+    /// outward (counter-clockwise) normal of that triangle
+    pub normal: Vector3<f32>,
+}
+
+/// Where a ray crosses a mesh: the distance along it and the barycentric
+/// coordinates on the triangle, as `Intersection` has them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    pub t: f32,
+    pub u: f32,
+    pub v: f32,
+    /// which of the hierarchy's triangles was crossed, for `Bvh::triangle`
+    pub triangle: usize,
+}
+
+impl Bvh {
+    pub fn new(triangles: &[Triangle]) -> Self {
+        let mut indices = (0..triangles.len()).collect::<Vec<_>>();
+        let tree = Tree::new(&mut indices, |&i| {
+            let t = &triangles[i];
+            AABB::around(vec![t.p1, t.p2, t.p3])
+        });
+
+        Bvh {
+            triangles: indices
+                .iter()
+                .map(|&i| [triangles[i].p1, triangles[i].p2, triangles[i].p3])
+                .collect(),
+            uvs: indices.iter().map(|&i| triangles[i].uvs).collect(),
+            indices,
+            tree,
+        }
+    }
+
+    pub fn bounds(&self) -> AABB {
+        self.tree.bounds()
+    }
+
+    pub fn triangles(&self) -> &[[Point3<f32>; 3]] {
+        &self.triangles
+    }
+
+    /// the `triangle`th one of the hierarchy's, as a crossing names it
+    pub fn triangle(&self, triangle: usize) -> Triangle {
+        let [p1, p2, p3] = self.triangles[triangle];
+        let t = Triangle::new(p1, p2, p3);
+
+        match self.uvs[triangle] {
+            Some([uv1, uv2, uv3]) => t.with_uvs(uv1, uv2, uv3),
+            None => t,
+        }
+    }
+
+    /// the outward (counter-clockwise) normal of the `triangle`th one
+    pub fn normal(&self, triangle: usize) -> Vector3<f32> {
+        outward(&self.triangles[triangle])
+    }
+
+    /// where the `triangle`th one was among those the hierarchy was built
+    /// from
+    pub fn source_index(&self, triangle: usize) -> usize {
+        self.indices[triangle]
+    }
+
+    /// every triangle `ray` crosses, in no particular order
+    pub fn intersect(&self, ray: &Ray) -> Vec<Crossing> {
+        self.tree
+            .along(ray)
+            .into_iter()
+            .flat_map(|(start, end)| start..end)
+            .filter_map(|i| crossing(ray, &self.triangles[i], i))
+            .collect()
+    }
+
+    /// the nearest point on any triangle, or `None` for an empty mesh
+    pub fn closest_point(&self, query: Point3<f32>) -> Option<Closest> {
+        let nodes = &self.tree.nodes;
+        let mut best: Option<(f32, Point3<f32>, usize)> = None;
+        let mut stack = if nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(index) = stack.pop() {
+            let node = &nodes[index];
+            let bound = node.bounds().distance_squared_to(query);
+
+            if best.is_some_and(|(d, _, _)| bound >= d) {
+                continue;
+            }
+
+            match *node {
+                Node::Leaf { start, end, .. } => {
+                    for (i, triangle) in self.triangles[start..end].iter().enumerate() {
+                        let point = closest_on_triangle(query, triangle);
+                        let d = (point - query).norm_squared();
+
+                        if best.is_none_or(|(best, _, _)| d < best) {
+                            best = Some((d, point, start + i));
+                        }
+                    }
+                }
+                Node::Branch { left, right, .. } => {
+                    // visit the nearer child first so it can prune the other
+                    let (near, far) = if nodes[left].bounds().distance_squared_to(query)
+                        <= nodes[right].bounds().distance_squared_to(query)
+                    {
+                        (left, right)
+                    } else {
+                        (right, left)
+                    };
+
+                    stack.push(far);
+                    stack.push(near);
+                }
+            }
+        }
+
+        best.map(|(d, point, triangle)| Closest {
+            point,
+            distance: d.sqrt(),
+            normal: outward(&self.triangles[triangle]),
+        })
+    }
+
+    /// distance to the surface, negative on the side the normals point away from
+    pub fn signed_distance(&self, query: Point3<f32>) -> Option<f32> {
+        self.closest_point(query).map(|closest| {
+            if (query - closest.point).dot(&closest.normal) < 0.0 {
+                -closest.distance
+            } else {
+                closest.distance
+            }
+        })
+    }
+}
+
+/// zero for degenerate triangles, as `Triangle::new` gives them
+fn outward([a, b, c]: &[Point3<f32>; 3]) -> Vector3<f32> {
+    (b - a)
+        .cross(&(c - a))
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vector3::zeros)
+}
+
+/// Möller–Trumbore, as `Triangle::local_intersect` does it
+fn crossing(ray: &Ray, [p1, p2, p3]: &[Point3<f32>; 3], triangle: usize) -> Option<Crossing> {
+    let e1 = p2 - p1;
+    let e2 = p3 - p1;
+    let dir_cross_e2 = ray.direction.cross(&e2);
+    let det = e1.dot(&dir_cross_e2);
+
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(&e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some(Crossing {
+        t: f * e2.dot(&origin_cross_e1),
+        u,
+        v,
+        triangle,
+    })
+}
+
+/// from Ericson, Real-Time Collision Detection, 5.1.5
+fn closest_on_triangle(p: Point3<f32>, [a, b, c]: &[Point3<f32>; 3]) -> Point3<f32> {
+    let (a, b, c) = (*a, *b, *c);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    fn corners() -> [Point3<f32>; 3] {
+        [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ]
+    }
+
+    #[test]
+    fn the_closest_point_on_a_triangle() {
+        let t = corners();
+
+        for (query, expected) in &[
+            ((0.2, 0.2, 3.0), (0.2, 0.2, 0.0)),
+            ((-1.0, -1.0, 0.0), (0.0, 0.0, 0.0)),
+            ((0.5, -2.0, 1.0), (0.5, 0.0, 0.0)),
+            ((1.0, 1.0, 0.0), (0.5, 0.5, 0.0)),
+        ] {
+            let closest = closest_on_triangle(Point3::new(query.0, query.1, query.2), &t);
+            let expected = Point3::new(expected.0, expected.1, expected.2);
+            assert_close((closest - expected).norm(), 0.0);
+        }
+    }
+
+    // a strip of unit squares along x, two triangles each, facing +z
+    fn strip(length: usize) -> Vec<Triangle> {
+        (0..length)
+            .flat_map(|i| {
+                let x = i as f32;
+                vec![
+                    Triangle::new(
+                        Point3::new(x, 0.0, 0.0),
+                        Point3::new(x + 1.0, 0.0, 0.0),
+                        Point3::new(x + 1.0, 1.0, 0.0),
+                    ),
+                    Triangle::new(
+                        Point3::new(x, 0.0, 0.0),
+                        Point3::new(x + 1.0, 1.0, 0.0),
+                        Point3::new(x, 1.0, 0.0),
+                    ),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_hierarchy_finds_the_same_point_as_a_brute_force_search() {
+        let triangles = strip(50);
+        let bvh = Bvh::new(&triangles);
+
+        for query in &[
+            Point3::new(17.3, 0.4, 2.0),
+            Point3::new(-3.0, 0.5, 0.0),
+            Point3::new(60.0, 2.0, -1.0),
+            Point3::new(25.5, -0.5, 0.1),
+        ] {
+            let brute = triangles
+                .iter()
+                .map(|t| (closest_on_triangle(*query, &[t.p1, t.p2, t.p3]) - query).norm())
+                .fold(f32::INFINITY, f32::min);
+
+            assert_close(bvh.closest_point(*query).unwrap().distance, brute);
+        }
+    }
+
+    #[test]
+    fn rays_cross_the_same_triangles_as_a_brute_force_search() {
+        let triangles = strip(50);
+        let bvh = Bvh::new(&triangles);
+
+        for ray in &[
+            Ray::new(Point3::new(17.3, 0.4, 2.0), Vector3::new(0.0, 0.0, -1.0)),
+            Ray::new(Point3::new(-1.0, 0.5, -1.0), Vector3::new(10.0, 0.0, 1.0)),
+            Ray::new(Point3::new(60.0, 0.5, 1.0), Vector3::new(0.0, 0.0, 1.0)),
+        ] {
+            let brute = triangles
+                .iter()
+                .filter_map(|t| crossing(ray, &[t.p1, t.p2, t.p3], 0))
+                .count();
+
+            assert_eq!(bvh.intersect(ray).len(), brute);
+        }
+
+        let hits = bvh.intersect(&Ray::new(
+            Point3::new(17.3, 0.4, 2.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ));
+        assert_eq!(hits.len(), 1);
+        assert_close(hits[0].t, 2.0);
+
+        // the crossing names the triangle as it was given
+        assert_eq!(bvh.source_index(hits[0].triangle), 35);
+        assert_eq!(bvh.normal(hits[0].triangle), Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn distances_are_signed_by_the_outward_normal() {
+        let bvh = Bvh::new(&strip(3));
+        assert_close(
+            bvh.signed_distance(Point3::new(1.5, 0.5, 0.25)).unwrap(),
+            0.25,
+        );
+        assert_close(
+            bvh.signed_distance(Point3::new(1.5, 0.5, -0.5)).unwrap(),
+            -0.5,
+        );
+    }
+
+    #[test]
+    fn degenerate_triangles_have_no_normal_rather_than_nan() {
+        let line = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+        );
+        let bvh = Bvh::new(&[line]);
+
+        assert_eq!(bvh.normal(0), Vector3::zeros());
+        assert_close(
+            bvh.signed_distance(Point3::new(1.0, 0.5, 0.0)).unwrap(),
+            0.5,
+        );
+    }
+
+    #[test]
+    fn an_empty_mesh_has_no_closest_point() {
+        assert_eq!(Bvh::new(&[]).closest_point(Point3::origin()), None);
+    }
+}
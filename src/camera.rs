@@ -1,4 +1,5 @@
 use crate::canvas::Canvas;
+use crate::gbuffer::GBuffer;
 use crate::ray::Ray;
 use crate::world::World;
 use nalgebra::{Matrix4, Point3, Projective3, Vector3};
@@ -58,8 +59,39 @@ impl Camera {
         Ray::new(origin, direction)
     }
 
-    pub fn render(&self, world: World) -> Canvas {
+    /// the camera's position in world space
+    pub fn eye(&self) -> Point3<f32> {
+        let projective_inverse: Projective3<f32> =
+            Projective3::from_matrix_unchecked(self.transform).inverse();
+
+        projective_inverse * Point3::new(0.0, 0.0, 0.0)
+    }
+
+    /// where `point` lands on the canvas, as fractional pixel coordinates
+    /// (the pixel is their floor), and its distance from the camera;
+    /// `None` for points behind the camera
+    pub fn project(&self, point: Point3<f32>) -> Option<(f32, f32, f32)> {
+        let transform: Projective3<f32> = Projective3::from_matrix_unchecked(self.transform);
+        let camera_point = transform * point;
+
+        if camera_point.z > -f32::EPSILON {
+            return None;
+        }
+
+        let world_x = camera_point.x / -camera_point.z;
+        let world_y = camera_point.y / -camera_point.z;
+
+        Some((
+            (self.half_width - world_x) / self.pixel_size,
+            (self.half_height - world_y) / self.pixel_size,
+            (point - self.eye()).magnitude(),
+        ))
+    }
+
+    /// renders the image along with what each pixel's ray hit
+    pub fn render_with_buffers(&self, world: &World) -> (Canvas, GBuffer) {
         let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut buffers = GBuffer::new(self.hsize, self.vsize);
 
         let mut pixels = Vec::with_capacity(self.hsize * self.vsize);
 
@@ -77,8 +109,8 @@ impl Camera {
                 (x, y, ray)
             })
             .map(|(x, y, ray)| {
-                let color = world.color_at(ray);
-                (x, y, color)
+                let trace = world.trace(ray);
+                (x, y, trace)
             })
             .collect::<Vec<_>>();
 
@@ -90,19 +122,69 @@ impl Camera {
                 (x, y, ray)
             })
             .map(|(x, y, ray)| {
-                let color = world.color_at(ray);
-                (x, y, color)
+                let trace = world.trace(ray);
+                (x, y, trace)
             })
             .collect::<Vec<_>>();
 
-        for (x, y, color) in xycs {
-            image.write_pixel(x, y, color)
+        for (x, y, trace) in xycs {
+            image.write_pixel(x, y, trace.color);
+            image.write_alpha(x, y, trace.alpha);
+            buffers.write(x, y, trace.surface);
         }
 
-        image
+        (image, buffers)
     }
 
+    /// the book's view transform, with the eye at `from` looking towards
+    /// `to`; the command line and scene files both place the camera with it
     pub fn view_transforms(from: Point3<f32>, to: Point3<f32>, up: Vector3<f32>) -> Matrix4<f32> {
-        Matrix4::face_towards(&from, &to, &up)
+        Matrix4::look_at_rh(&from, &to, &up)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn the_pixel_size_for_a_horizontal_canvas() {
+        let c = Camera::new(200, 125, FRAC_PI_2);
+        assert!((c.pixel_size - 0.01).abs() < 0.0001);
+    }
+
+    #[test]
+    fn projecting_a_point_finds_the_pixel_its_ray_came_from() {
+        let mut c = Camera::new(201, 101, FRAC_PI_2);
+        c.transform = Camera::view_transforms(
+            Point3::new(1.0, 3.0, -6.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        for &(px, py) in &[(0, 0), (100, 50), (37, 88)] {
+            let ray = c.ray_for_pixel(px, py);
+            let (x, y, distance) = c.project(ray.position(7.0)).unwrap();
+            assert_eq!((x as usize, y as usize), (px, py));
+            assert!((distance - 7.0).abs() < 0.001);
+        }
+
+        assert_eq!(
+            c.project(c.eye() - c.ray_for_pixel(100, 50).direction),
+            None
+        );
+    }
+
+    #[test]
+    fn the_view_transform_puts_the_eye_at_from_looking_at_to() {
+        let mut c = Camera::new(201, 101, FRAC_PI_2);
+        let from = Point3::new(1.0, 3.0, -6.0);
+        let to = Point3::new(0.0, 1.0, 0.0);
+        c.transform = Camera::view_transforms(from, to, Vector3::new(0.0, 1.0, 0.0));
+
+        assert!((c.eye() - from).magnitude() < 0.0001);
+        let forward = c.ray_for_pixel(100, 50).direction;
+        assert!((forward - (to - from).normalize()).magnitude() < 0.0001);
     }
 }
@@ -8,6 +8,8 @@ type Pixel = Vector3<f32>;
 #[derive(Clone, Debug)]
 pub struct Canvas {
     grid: Vec<Vec<Pixel>>,
+    /// coverage per pixel; PNGs keep it, other formats are composited over black
+    alpha: Vec<Vec<f32>>,
 }
 
 impl Canvas {
@@ -26,14 +28,17 @@ impl Canvas {
             rows.push(row.clone());
         }
 
-        Canvas { grid: rows }
+        Canvas {
+            grid: rows,
+            alpha: vec![vec![1.0; width]; height],
+        }
     }
 
-    fn width(&self) -> usize {
+    pub(crate) fn width(&self) -> usize {
         self.grid[0].len()
     }
 
-    fn height(&self) -> usize {
+    pub(crate) fn height(&self) -> usize {
         self.grid.len()
     }
 
@@ -41,10 +46,44 @@ impl Canvas {
         self.grid[y][x] = pixel;
     }
 
-    fn pixel_at(&self, x: usize, y: usize) -> Pixel {
+    pub(crate) fn write_alpha(&mut self, x: usize, y: usize, alpha: f32) {
+        self.alpha[y][x] = alpha;
+    }
+
+    pub(crate) fn pixel_at(&self, x: usize, y: usize) -> Pixel {
         self.grid[y][x]
     }
 
+    pub(crate) fn alpha_at(&self, x: usize, y: usize) -> f32 {
+        self.alpha[y][x]
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.alpha.iter().flatten().all(|&a| a >= 1.0)
+    }
+
+    fn to_rgba_image(&self) -> image::RgbaImage {
+        let mut img = image::RgbaImage::new(
+            self.width().try_into().unwrap(),
+            self.height().try_into().unwrap(),
+        );
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let pixel = self.pixel_at(x, y);
+                let color = image::Rgba([
+                    scale(clamp(pixel.x)).try_into().unwrap(),
+                    scale(clamp(pixel.y)).try_into().unwrap(),
+                    scale(clamp(pixel.z)).try_into().unwrap(),
+                    scale(clamp(self.alpha_at(x, y))).try_into().unwrap(),
+                ]);
+
+                img.put_pixel(x.try_into().unwrap(), y.try_into().unwrap(), color);
+            }
+        }
+
+        img
+    }
+
     pub(crate) fn to_image(&self, format: image::ImageFormat) -> Result<Vec<u8>, String> {
         let mut buf = Vec::new();
         let mut img = image::RgbImage::new(
@@ -53,7 +92,7 @@ impl Canvas {
         );
         for y in 0..self.height() {
             for x in 0..self.width() {
-                let pixel = self.pixel_at(x, y);
+                let pixel = self.pixel_at(x, y) * self.alpha_at(x, y);
                 let color = image::Rgb([
                     scale(clamp(pixel.x)).try_into().unwrap(),
                     scale(clamp(pixel.y)).try_into().unwrap(),
@@ -65,6 +104,16 @@ impl Canvas {
         }
 
         match format {
+            image::ImageFormat::Png if !self.is_opaque() => {
+                let img = self.to_rgba_image();
+                let (x, y) = img.dimensions();
+
+                PngEncoder::new(&mut buf)
+                    .encode(&img.into_raw(), x, y, image::ColorType::Rgba8)
+                    .map_err(|e| format!("{}", e))?;
+
+                Ok(buf)
+            }
             image::ImageFormat::Png => {
                 let (x, y) = img.dimensions();
 
@@ -139,4 +188,23 @@ mod tests {
         c.write_pixel(2, 3, red);
         assert_eq!(c.pixel_at(2, 3), red);
     }
+
+    #[test]
+    fn a_canvas_is_opaque_until_alpha_is_written() {
+        let mut c = Canvas::new(10, 20);
+        assert_eq!(c.alpha_at(2, 3), 1.0);
+        assert!(c.is_opaque());
+        c.write_alpha(2, 3, 0.25);
+        assert_eq!(c.alpha_at(2, 3), 0.25);
+        assert!(!c.is_opaque());
+    }
+
+    #[test]
+    fn transparent_canvases_become_rgba_pngs() {
+        let mut c = Canvas::new(2, 2);
+        c.write_alpha(0, 0, 0.0);
+        let png = c.to_image(image::ImageFormat::Png).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!(decoded.color(), image::ColorType::Rgba8);
+    }
 }
@@ -0,0 +1,134 @@
+use crate::material::Color;
+use crate::ray::Ray;
+use nalgebra::{Point3, Vector3};
+
+/// Cuts away everything on the side of the plane its normal points to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipPlane {
+    pub point: Point3<f32>,
+    pub normal: Vector3<f32>,
+}
+
+impl ClipPlane {
+    pub fn new(point: Point3<f32>, normal: Vector3<f32>) -> Self {
+        ClipPlane {
+            point,
+            normal: normal.normalize(),
+        }
+    }
+
+    pub(crate) fn clips(&self, point: Point3<f32>) -> bool {
+        (point - self.point).dot(&self.normal) > 0.0
+    }
+
+    /// where the ray crosses from the cut away side to the kept side
+    pub(crate) fn entry(&self, ray: &Ray) -> Option<f32> {
+        let denominator = ray.direction.dot(&self.normal);
+
+        if denominator >= 0.0 {
+            return None;
+        }
+
+        Some((self.point - ray.origin).dot(&self.normal) / denominator)
+    }
+}
+
+/// How the faces cut open by clipping planes are filled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cap {
+    pub color: Color,
+    pub hatch: Option<Hatch>,
+}
+
+impl Default for Cap {
+    fn default() -> Self {
+        Cap {
+            color: Vector3::new(0.85, 0.25, 0.2),
+            hatch: None,
+        }
+    }
+}
+
+/// Diagonal lines across a cap, as on a section drawing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hatch {
+    pub color: Color,
+    /// between lines, in world units
+    pub spacing: f32,
+    /// fraction of the spacing covered by each line
+    pub width: f32,
+}
+
+impl Default for Hatch {
+    fn default() -> Self {
+        Hatch {
+            color: Vector3::new(0.2, 0.05, 0.05),
+            spacing: 1.0,
+            width: 0.25,
+        }
+    }
+}
+
+impl Cap {
+    pub(crate) fn color_at(&self, point: Point3<f32>, plane: &ClipPlane) -> Color {
+        let hatch = match &self.hatch {
+            Some(hatch) => hatch,
+            None => return self.color,
+        };
+
+        let helper = if plane.normal.x.abs() > 0.9 {
+            Vector3::y()
+        } else {
+            Vector3::x()
+        };
+        let u = plane.normal.cross(&helper).normalize();
+        let v = plane.normal.cross(&u);
+
+        let offset = point - plane.point;
+        let position = (offset.dot(&u) + offset.dot(&v)) / hatch.spacing;
+
+        if position.rem_euclid(1.0) < hatch.width {
+            hatch.color
+        } else {
+            self.color
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_plane_clips_the_side_its_normal_points_to() {
+        let plane = ClipPlane::new(Point3::origin(), Vector3::new(0.0, 0.0, 2.0));
+        assert!(plane.clips(Point3::new(0.0, 0.0, 1.0)));
+        assert!(!plane.clips(Point3::new(5.0, 5.0, -1.0)));
+    }
+
+    #[test]
+    fn rays_enter_the_kept_side_once() {
+        let plane = ClipPlane::new(Point3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, 1.0));
+        let inward = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let outward = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(plane.entry(&inward), Some(4.0));
+        assert_eq!(plane.entry(&outward), None);
+    }
+
+    #[test]
+    fn hatching_alternates_across_the_cap() {
+        let plane = ClipPlane::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
+        let cap = Cap {
+            hatch: Some(Hatch::default()),
+            ..Default::default()
+        };
+
+        let colors = (0..8)
+            .map(|i| cap.color_at(Point3::new(i as f32 * 0.125, 0.0, 0.0), &plane))
+            .collect::<Vec<_>>();
+
+        assert!(colors.contains(&cap.color));
+        assert!(colors.contains(&Hatch::default().color));
+    }
+}
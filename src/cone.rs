@@ -0,0 +1,210 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use crate::{
+    bounding_box::{BoundingBox, AABB},
+    cylinder::cap_hits,
+    intersection::Intersection,
+    material::Material,
+    ray::Ray,
+    shape::{normal_to_world, world_to_object, Shape},
+};
+
+/// how close to a cap a point must be to take the cap's normal
+const EPSILON: f32 = 0.0001;
+
+/// The double-napped cone x² + z² = y², its tips meeting at the origin, cut
+/// off at `minimum` and `maximum` (both excluded) and, if `closed`, capped
+/// there.
+#[derive(Clone, Debug)]
+pub struct Cone {
+    pub minimum: f32,
+    pub maximum: f32,
+    pub closed: bool,
+    pub transform: Matrix4<f32>,
+    pub material: Material,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            minimum: -f32::INFINITY,
+            maximum: f32::INFINITY,
+            closed: false,
+            transform: Matrix4::identity(),
+            material: Material::default(),
+        }
+    }
+}
+
+impl BoundingBox for Cone {
+    fn bounding_box(&self) -> AABB {
+        let radius = self.minimum.abs().max(self.maximum.abs());
+
+        AABB {
+            min: Point3::new(-radius, self.minimum, -radius),
+            max: Point3::new(radius, self.maximum, radius),
+        }
+    }
+}
+
+impl Shape for Cone {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let (o, d) = (ray.origin, ray.direction);
+        let a = d.x.powi(2) - d.y.powi(2) + d.z.powi(2);
+        let b = 2.0 * (o.x * d.x - o.y * d.y + o.z * d.z);
+        let c = o.x.powi(2) - o.y.powi(2) + o.z.powi(2);
+
+        let ts = if a.abs() < f32::EPSILON {
+            // parallel to one of the halves, so it crosses the other once
+            if b.abs() < f32::EPSILON {
+                vec![]
+            } else {
+                vec![-c / (2.0 * b)]
+            }
+        } else {
+            let discriminant = b.powi(2) - 4.0 * a * c;
+
+            // rays grazing the surface can come out a little below zero
+            if discriminant < -EPSILON {
+                vec![]
+            } else {
+                let disc_sqrt = discriminant.max(0.0).sqrt();
+                let t0 = (-b - disc_sqrt) / (2.0 * a);
+                let t1 = (-b + disc_sqrt) / (2.0 * a);
+                vec![t0.min(t1), t0.max(t1)]
+            }
+        };
+
+        let mut xs = ts
+            .into_iter()
+            .filter(|t| {
+                let y = o.y + t * d.y;
+                self.minimum < y && y < self.maximum
+            })
+            .map(|t| Intersection::new(t, self))
+            .collect::<Vec<_>>();
+
+        if self.closed {
+            xs.extend(cap_hits(self, &ray, (self.minimum, self.maximum), f32::abs));
+        }
+
+        xs
+    }
+
+    fn local_normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let distance = point.x.powi(2) + point.z.powi(2);
+
+        if distance < point.y.powi(2) && point.y >= self.maximum - EPSILON {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else if distance < point.y.powi(2) && point.y <= self.minimum + EPSILON {
+            Vector3::new(0.0, -1.0, 0.0)
+        } else {
+            let y = distance.sqrt();
+            let y = if point.y > 0.0 { -y } else { y };
+
+            Vector3::new(point.x, y, point.z)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray() {
+        let shape = Cone::default();
+
+        for (origin, direction, t0, t1) in [
+            (
+                Point3::new(0.0, 0.0, -5.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Point3::new(0.0, 0.0, -5.0),
+                Vector3::new(1.0, 1.0, 1.0),
+                8.66025,
+                8.66025,
+            ),
+            (
+                Point3::new(1.0, 1.0, -5.0),
+                Vector3::new(-0.5, -1.0, 1.0),
+                4.55006,
+                49.44994,
+            ),
+        ] {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = shape.local_intersect(r);
+
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < 1e-3, "{} != {}", xs[0].t, t0);
+            assert!((xs[1].t - t1).abs() < 1e-3, "{} != {}", xs[1].t, t1);
+        }
+    }
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
+        let shape = Cone::default();
+        let r = Ray::new(
+            Point3::new(0.0, 0.0, -1.0),
+            Vector3::new(0.0, 1.0, 1.0).normalize(),
+        );
+        let xs = shape.local_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 0.35355).abs() < 1e-4);
+    }
+
+    #[test]
+    fn intersecting_a_cones_end_caps() {
+        let shape = Cone {
+            minimum: -0.5,
+            maximum: 0.5,
+            closed: true,
+            ..Cone::default()
+        };
+
+        for (origin, direction, count) in [
+            (Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0), 0),
+            (Point3::new(0.0, 0.0, -0.25), Vector3::new(0.0, 1.0, 1.0), 2),
+            (Point3::new(0.0, 0.0, -0.25), Vector3::new(0.0, 1.0, 0.0), 4),
+        ] {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(shape.local_intersect(r).len(), count);
+        }
+    }
+
+    #[test]
+    fn computing_the_normal_vector_on_a_cone() {
+        let shape = Cone::default();
+
+        assert_eq!(
+            shape.local_normal_at(Point3::new(1.0, 1.0, 1.0)),
+            Vector3::new(1.0, -(2.0f32.sqrt()), 1.0)
+        );
+        assert_eq!(
+            shape.local_normal_at(Point3::new(-1.0, -1.0, 0.0)),
+            Vector3::new(-1.0, 1.0, 0.0)
+        );
+    }
+}
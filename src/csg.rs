@@ -0,0 +1,268 @@
+use crate::bounding_box::{BoundingBox, AABB};
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use nalgebra::{Matrix4, Point3, Projective3, Vector3};
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// everything in either shape
+    Union,
+    /// only what's in both
+    Intersection,
+    /// the first shape with the second cut out of it
+    Difference,
+}
+
+impl CsgOperation {
+    /// whether a hit on one side is on the combined surface, given where
+    /// the ray is relative to each side at that point
+    fn allows(self, on_left: bool, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => (on_left && !in_right) || (!on_left && !in_left),
+            CsgOperation::Intersection => (on_left && in_right) || (!on_left && in_left),
+            CsgOperation::Difference => (on_left && !in_right) || (!on_left && in_left),
+        }
+    }
+}
+
+/// Two shapes combined by a boolean operation, from The Ray Tracer
+/// Challenge's bonus chapter.
+///
+/// Either side may be any shape, including a closed mesh or another `Csg`;
+/// a ray is taken to enter or leave a side at each of its hits on it. Like a
+/// group's children, the sides carry the node's transform.
+pub struct Csg {
+    operation: CsgOperation,
+    left: Box<dyn Shape>,
+    right: Box<dyn Shape>,
+    material: Material,
+    transform: Matrix4<f32>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        Csg {
+            operation,
+            left,
+            right,
+            material: Material::default(),
+            transform: Matrix4::identity(),
+        }
+    }
+
+    /// moves the node and both its sides
+    pub fn with_transform(mut self, transform: Matrix4<f32>) -> Self {
+        self.transform_by(&transform);
+        self
+    }
+
+    /// the hits on the combined surface from `hits` sorted by `t`, each
+    /// marked with whether it's on the left side
+    fn filter<'a>(&self, hits: Vec<(Intersection<'a>, bool)>) -> Vec<Intersection<'a>> {
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut kept = vec![];
+
+        for (hit, on_left) in hits {
+            if self.operation.allows(on_left, in_left, in_right) {
+                kept.push(hit);
+            }
+
+            if on_left {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+        }
+
+        kept
+    }
+}
+
+impl BoundingBox for Csg {
+    /// in the node's own space, like other shapes' bounds
+    fn bounding_box(&self) -> AABB {
+        let world = |side: &dyn Shape| side.bounding_box().transformed(&side.transform());
+        let inverse = Projective3::from_matrix_unchecked(self.transform)
+            .inverse()
+            .to_homogeneous();
+
+        match self.operation {
+            CsgOperation::Difference => world(self.left.as_ref()),
+            _ => world(self.left.as_ref()).union(&world(self.right.as_ref())),
+        }
+        .transformed(&inverse)
+    }
+}
+
+impl Shape for Csg {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+        self.left.transform_by(parent);
+        self.right.transform_by(parent);
+    }
+
+    fn normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    /// the sides already carry the node's transform, so they're handed the
+    /// world-space ray
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut hits = self
+            .left
+            .intersect(ray)
+            .into_iter()
+            .map(|hit| (hit, true))
+            .chain(
+                self.right
+                    .intersect(ray)
+                    .into_iter()
+                    .map(|hit| (hit, false)),
+            )
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|(a, _), (b, _)| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal));
+
+        self.filter(hits)
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        self.intersect(&ray.transform(self.transform))
+    }
+
+    fn local_normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        let mut emitters = self.left.emitters();
+        emitters.extend(self.right.emitters());
+        emitters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::sphere::Sphere;
+
+    fn sphere_at(z: f32) -> Box<dyn Shape> {
+        Box::new(Sphere {
+            transform: Matrix4::new_translation(&Vector3::new(0.0, 0.0, z)),
+            ..Sphere::new()
+        })
+    }
+
+    #[test]
+    fn evaluating_the_rule_for_a_csg_operation() {
+        use CsgOperation::*;
+
+        // (operation, on left, in left, in right, allowed), as tabled in the book
+        for &(operation, on_left, in_left, in_right, expected) in &[
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false),
+        ] {
+            assert_eq!(
+                operation.allows(on_left, in_left, in_right),
+                expected,
+                "{:?} {} {} {}",
+                operation,
+                on_left,
+                in_left,
+                in_right
+            );
+        }
+    }
+
+    #[test]
+    fn a_ray_hits_a_csg_object() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        // two unit spheres overlapping from z = -0.5 to 0.5
+        for (operation, expected) in &[
+            (CsgOperation::Union, vec![3.5, 6.5]),
+            (CsgOperation::Intersection, vec![4.5, 5.5]),
+            (CsgOperation::Difference, vec![3.5, 4.5]),
+        ] {
+            let csg = Csg::new(*operation, sphere_at(-0.5), sphere_at(0.5));
+            let ts = csg.intersect(&ray).iter().map(|i| i.t).collect::<Vec<_>>();
+
+            assert_eq!(ts.len(), expected.len(), "{:?}", operation);
+            for (t, expected) in ts.iter().zip(expected) {
+                assert!((t - expected).abs() < 1e-4, "{:?}: {:?}", operation, ts);
+            }
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_csg_object() {
+        let csg = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new(Cube::default()),
+        );
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(csg.intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn a_cube_minus_a_sphere_keeps_the_cut_surface() {
+        let sphere = Sphere {
+            transform: Matrix4::new_scaling(1.3),
+            ..Sphere::new()
+        };
+        let csg = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Cube::default()),
+            Box::new(sphere),
+        )
+        .with_transform(Matrix4::new_translation(&Vector3::new(0.0, 0.0, 1.0)));
+
+        // through a corner, which the sphere doesn't reach
+        let corner = Ray::new(Point3::new(0.95, 0.95, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = csg.intersect(&corner);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].t - 5.0).abs() < 1e-4);
+
+        // through the middle, which the sphere hollows out completely
+        let middle = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(csg.intersect(&middle).is_empty());
+    }
+}
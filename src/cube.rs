@@ -1,16 +1,19 @@
-use nalgebra::{Point3, Vector3};
+use nalgebra::{Matrix4, Point3, Vector3};
 
 use crate::{
     bounding_box::{BoundingBox, AABB},
     intersection::Intersection,
+    material::Material,
     ray::Ray,
-    shape::Shape,
+    shape::{normal_to_world, world_to_object, Shape},
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Cube {
     pub min: Point3<f32>,
     pub max: Point3<f32>,
+    pub transform: Matrix4<f32>,
+    pub material: Material,
 }
 
 impl Default for Cube {
@@ -18,6 +21,8 @@ impl Default for Cube {
         Self {
             min: Point3::new(-1.0, -1.0, -1.0),
             max: Point3::new(1.0, 1.0, 1.0),
+            transform: Matrix4::identity(),
+            material: Material::default(),
         }
     }
 }
@@ -27,6 +32,7 @@ impl From<AABB> for Cube {
         Cube {
             min: aabb.min,
             max: aabb.max,
+            ..Default::default()
         }
     }
 }
@@ -41,16 +47,22 @@ impl BoundingBox for Cube {
 }
 
 impl Shape for Cube {
-    fn material(&self) -> crate::material::Material {
-        todo!()
+    fn material(&self) -> &Material {
+        &self.material
     }
 
-    fn transform(&self) -> nalgebra::Matrix4<f32> {
-        todo!()
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
     }
 
-    fn normal_at(&self, _point: Point3<f32>) -> nalgebra::Vector3<f32> {
-        todo!()
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
     }
 
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
@@ -0,0 +1,266 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use crate::{
+    bounding_box::{BoundingBox, AABB},
+    intersection::Intersection,
+    material::Material,
+    ray::Ray,
+    shape::{normal_to_world, world_to_object, Shape},
+};
+
+/// how close to a cap a point must be to take the cap's normal
+const EPSILON: f32 = 0.0001;
+
+/// The unit-radius cylinder around the y axis, cut off at `minimum` and
+/// `maximum` (both excluded) and, if `closed`, capped there.
+#[derive(Clone, Debug)]
+pub struct Cylinder {
+    pub minimum: f32,
+    pub maximum: f32,
+    pub closed: bool,
+    pub transform: Matrix4<f32>,
+    pub material: Material,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self {
+            minimum: -f32::INFINITY,
+            maximum: f32::INFINITY,
+            closed: false,
+            transform: Matrix4::identity(),
+            material: Material::default(),
+        }
+    }
+}
+
+impl BoundingBox for Cylinder {
+    fn bounding_box(&self) -> AABB {
+        AABB {
+            min: Point3::new(-1.0, self.minimum, -1.0),
+            max: Point3::new(1.0, self.maximum, 1.0),
+        }
+    }
+}
+
+impl Shape for Cylinder {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = vec![];
+        let a = ray.direction.x.powi(2) + ray.direction.z.powi(2);
+
+        // rays parallel to the axis can only hit the caps
+        if a.abs() >= f32::EPSILON {
+            let b = 2.0 * (ray.origin.x * ray.direction.x + ray.origin.z * ray.direction.z);
+            let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - 1.0;
+            let discriminant = b.powi(2) - 4.0 * a * c;
+
+            if discriminant < 0.0 {
+                return vec![];
+            }
+
+            let disc_sqrt = discriminant.sqrt();
+            let t0 = (-b - disc_sqrt) / (2.0 * a);
+            let t1 = (-b + disc_sqrt) / (2.0 * a);
+
+            for t in [t0.min(t1), t0.max(t1)].iter() {
+                let y = ray.origin.y + t * ray.direction.y;
+
+                if self.minimum < y && y < self.maximum {
+                    xs.push(Intersection::new(*t, self));
+                }
+            }
+        }
+
+        if self.closed {
+            xs.extend(cap_hits(self, &ray, (self.minimum, self.maximum), |_| 1.0));
+        }
+
+        xs
+    }
+
+    fn local_normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let distance = point.x.powi(2) + point.z.powi(2);
+
+        if distance < 1.0 && point.y >= self.maximum - EPSILON {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else if distance < 1.0 && point.y <= self.minimum + EPSILON {
+            Vector3::new(0.0, -1.0, 0.0)
+        } else {
+            Vector3::new(point.x, 0.0, point.z)
+        }
+    }
+}
+
+/// hits on the caps at `minimum` and `maximum`, each a disk whose radius
+/// is given by the cap's height
+pub(crate) fn cap_hits<'a, S: Shape>(
+    shape: &'a S,
+    ray: &Ray,
+    (minimum, maximum): (f32, f32),
+    radius: impl Fn(f32) -> f32,
+) -> Vec<Intersection<'a>> {
+    if ray.direction.y.abs() < f32::EPSILON {
+        return vec![];
+    }
+
+    [minimum, maximum]
+        .iter()
+        .filter(|y| y.is_finite())
+        .map(|y| (y - ray.origin.y) / ray.direction.y)
+        .filter(|&t| {
+            let point = ray.position(t);
+            let r = radius(point.y);
+
+            point.x.powi(2) + point.z.powi(2) <= r * r + EPSILON
+        })
+        .map(|t| Intersection::new(t, shape))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn truncated(closed: bool) -> Cylinder {
+        Cylinder {
+            minimum: 1.0,
+            maximum: 2.0,
+            closed,
+            ..Cylinder::default()
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_cylinder() {
+        let cyl = Cylinder::default();
+
+        for (origin, direction) in [
+            (Point3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+            (Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+            (Point3::new(0.0, 0.0, -5.0), Vector3::new(1.0, 1.0, 1.0)),
+        ] {
+            let r = Ray::new(origin, direction.normalize());
+            assert!(cyl.local_intersect(r).is_empty());
+        }
+    }
+
+    #[test]
+    fn a_ray_strikes_a_cylinder() {
+        let cyl = Cylinder::default();
+
+        for (origin, direction, t0, t1) in [
+            (
+                Point3::new(1.0, 0.0, -5.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Point3::new(0.0, 0.0, -5.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point3::new(0.5, 0.0, -5.0),
+                Vector3::new(0.1, 1.0, 1.0),
+                6.80798,
+                7.08872,
+            ),
+        ] {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.local_intersect(r);
+
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < 1e-4);
+            assert!((xs[1].t - t1).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn normal_vector_on_a_cylinder() {
+        let cyl = Cylinder::default();
+
+        assert_eq!(
+            cyl.local_normal_at(Point3::new(1.0, 0.0, 0.0)),
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            cyl.local_normal_at(Point3::new(0.0, -2.0, 1.0)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn intersecting_a_constrained_cylinder() {
+        let cyl = truncated(false);
+
+        for (origin, direction, count) in [
+            (Point3::new(0.0, 1.5, 0.0), Vector3::new(0.1, 1.0, 0.0), 0),
+            (Point3::new(0.0, 3.0, -5.0), Vector3::new(0.0, 0.0, 1.0), 0),
+            (Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0), 0),
+            (Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0), 0),
+            (Point3::new(0.0, 1.0, -5.0), Vector3::new(0.0, 0.0, 1.0), 0),
+            (Point3::new(0.0, 1.5, -2.0), Vector3::new(0.0, 0.0, 1.0), 2),
+        ] {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(cyl.local_intersect(r).len(), count);
+        }
+    }
+
+    #[test]
+    fn intersecting_the_caps_of_a_closed_cylinder() {
+        let cyl = truncated(true);
+
+        for (origin, direction, count) in [
+            (Point3::new(0.0, 3.0, 0.0), Vector3::new(0.0, -1.0, 0.0), 2),
+            (Point3::new(0.0, 3.0, -2.0), Vector3::new(0.0, -1.0, 2.0), 2),
+            (Point3::new(0.0, 4.0, -2.0), Vector3::new(0.0, -1.0, 1.0), 2),
+            (Point3::new(0.0, 0.0, -2.0), Vector3::new(0.0, 1.0, 2.0), 2),
+            (Point3::new(0.0, -1.0, -2.0), Vector3::new(0.0, 1.0, 1.0), 2),
+        ] {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(cyl.local_intersect(r).len(), count);
+        }
+    }
+
+    #[test]
+    fn the_normal_on_a_cylinders_end_caps() {
+        let cyl = truncated(true);
+
+        for (point, normal) in [
+            (Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Point3::new(0.5, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Point3::new(0.0, 2.0, 0.5), Vector3::new(0.0, 1.0, 0.0)),
+            (Point3::new(0.5, 2.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+        ] {
+            assert_eq!(cyl.local_normal_at(point), normal);
+        }
+    }
+
+    #[test]
+    fn a_truncated_cylinders_bounds() {
+        let bounds = truncated(false).bounding_box();
+
+        assert_eq!(bounds.min, Point3::new(-1.0, 1.0, -1.0));
+        assert_eq!(bounds.max, Point3::new(1.0, 2.0, 1.0));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::bounding_box::{BoundingBox, AABB};
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point2, Point3, Vector3};
+use std::f32::consts::PI;
+
+/// how many triangles an emissive disk is sampled as
+const EMITTER_SEGMENTS: usize = 32;
+
+/// The disk of radius 1 in the xz plane facing +y, with a hole of
+/// `inner_radius` for a washer.
+///
+/// Hits carry texture coordinates: u goes once around from +x towards +z,
+/// v out from the inner edge to the rim.
+#[derive(Clone, Debug)]
+pub struct Disk {
+    pub inner_radius: f32,
+    pub transform: Matrix4<f32>,
+    pub material: Material,
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            inner_radius: 0.0,
+            transform: Matrix4::identity(),
+            material: Material::default(),
+        }
+    }
+}
+
+impl BoundingBox for Disk {
+    fn bounding_box(&self) -> AABB {
+        AABB {
+            min: Point3::new(-1.0, 0.0, -1.0),
+            max: Point3::new(1.0, 0.0, 1.0),
+        }
+    }
+}
+
+impl Shape for Disk {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        if ray.direction.y.abs() < 0.00001 {
+            return vec![];
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+        let point = ray.position(t);
+        let radius = (point.x.powi(2) + point.z.powi(2)).sqrt();
+
+        if radius < self.inner_radius || radius > 1.0 {
+            return vec![];
+        }
+
+        let u = point.z.atan2(point.x) / (2.0 * PI);
+        let u = if u < 0.0 { u + 1.0 } else { u };
+        let v = (radius - self.inner_radius) / (1.0 - self.inner_radius);
+
+        vec![Intersection::with_uv(t, self, u, v)]
+    }
+
+    fn local_normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+
+    fn uv_at(&self, hit: &Intersection) -> Option<Point2<f32>> {
+        Some(Point2::new(hit.u, hit.v))
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        if !self.material.is_emissive() {
+            return vec![];
+        }
+
+        let at = |i: usize, radius: f32| {
+            let angle = i as f32 * 2.0 * PI / EMITTER_SEGMENTS as f32;
+            self.transform.transform_point(&Point3::new(
+                radius * angle.cos(),
+                0.0,
+                radius * angle.sin(),
+            ))
+        };
+
+        // each segment of the ring as two triangles; the inner ones are
+        // degenerate, and dropped, for a full disk
+        (0..EMITTER_SEGMENTS)
+            .flat_map(|i| {
+                let (inner, outer) = (self.inner_radius, 1.0);
+                vec![
+                    Emitter::triangle(
+                        at(i, inner),
+                        at(i, outer),
+                        at(i + 1, outer),
+                        self.material.emission(),
+                    ),
+                    Emitter::triangle(
+                        at(i, inner),
+                        at(i + 1, outer),
+                        at(i + 1, inner),
+                        self.material.emission(),
+                    ),
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ray_hits_a_washer_between_its_edges() {
+        let washer = Disk {
+            inner_radius: 0.5,
+            ..Disk::default()
+        };
+        let down = Vector3::new(0.0, -1.0, 0.0);
+
+        let ring = washer.local_intersect(Ray::new(Point3::new(0.0, 2.0, 0.75), down));
+        assert_eq!(ring.len(), 1);
+        assert_eq!(ring[0].t, 2.0);
+        assert!((ring[0].u - 0.25).abs() < 1e-5);
+        assert!((ring[0].v - 0.5).abs() < 1e-5);
+
+        assert!(washer
+            .local_intersect(Ray::new(Point3::new(0.0, 2.0, 0.25), down))
+            .is_empty());
+        assert!(washer
+            .local_intersect(Ray::new(Point3::new(1.5, 2.0, 0.0), down))
+            .is_empty());
+    }
+
+    #[test]
+    fn an_emissive_disk_covers_nearly_its_area() {
+        let disk = Disk {
+            material: Material {
+                emissive: Vector3::new(1.0, 1.0, 1.0),
+                ..Material::default()
+            },
+            ..Disk::default()
+        };
+
+        let area = disk.emitters().iter().map(|e| e.area).sum::<f32>();
+        assert!((area - PI).abs() < 0.03, "{}", area);
+    }
+}
@@ -1,88 +1,311 @@
 use crate::bounding_box::{BoundingBox, AABB};
-use crate::cube::Cube;
-use nalgebra::{Matrix4, Vector3};
+use crate::bvh::Tree;
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use nalgebra::{Matrix4, Point3, Projective3, Vector3};
 
 #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::{material::Material, shape::Shape};
 
-pub struct DynGroup<S: Shape + Sized> {
-    shapes: Vec<S>,
+/// A node of the scene graph: any mix of shapes, meshes and other groups,
+/// moved together by the group's transform.
+///
+/// The group's transform is passed down to every descendant, so each
+/// shape's own `transform` takes it from object to world space through the
+/// whole chain of parents, and its normals come out in world space. Rays
+/// find the children they may hit through a hierarchy over their bounds.
+pub struct DynGroup {
+    children: Vec<Box<dyn Shape>>,
+    /// world-to-object transforms of the children, so rays needn't invert
+    /// them for every hit
+    inverses: Vec<Matrix4<f32>>,
+    /// in world space
     bounding_box: AABB,
+    /// over the indices of the bounded children
+    tree: Tree,
+    bounded: Vec<usize>,
+    /// children with no bounds, e.g. planes, which every ray is tried on
+    unbounded: Vec<usize>,
     material: Material,
     transform: Matrix4<f32>,
 }
 
-impl<S: Shape + Sized> DynGroup<S> {
-    pub fn new(shapes: Vec<S>) -> Self {
-        let transform = Matrix4::identity();
-        let material = Material::default();
+impl DynGroup {
+    pub fn new(children: Vec<Box<dyn Shape>>) -> Self {
+        let mut group = DynGroup {
+            children,
+            inverses: vec![],
+            bounding_box: AABB::default(),
+            tree: Tree::new::<usize>(&mut [], |_| AABB::default()),
+            bounded: vec![],
+            unbounded: vec![],
+            material: Material::default(),
+            transform: Matrix4::identity(),
+        };
 
-        let mut aabb = AABB::default();
+        group.update();
+        group
+    }
 
-        for shape in shapes.iter() {
-            aabb.merge_mut(shape.bounding_box());
-        }
+    /// moves the group and everything in it
+    pub fn with_transform(mut self, transform: Matrix4<f32>) -> Self {
+        self.transform_by(&transform);
+        self
+    }
 
-        DynGroup {
-            shapes,
-            bounding_box: aabb,
-            material,
-            transform,
-        }
+    /// refreshes what's cached about the children after they move
+    fn update(&mut self) {
+        self.inverses = self
+            .children
+            .iter()
+            .map(|child| inverse(&child.transform()))
+            .collect();
+
+        let bounds = self
+            .children
+            .iter()
+            .map(|child| child.bounding_box().transformed(&child.transform()))
+            .collect::<Vec<_>>();
+
+        let (mut bounded, unbounded): (Vec<usize>, Vec<usize>) =
+            (0..bounds.len()).partition(|&i| is_finite(&bounds[i]));
+
+        self.tree = Tree::new(&mut bounded, |&i| bounds[i]);
+        self.bounded = bounded;
+        self.unbounded = unbounded;
+
+        self.bounding_box = if self.unbounded.is_empty() {
+            self.tree.bounds()
+        } else {
+            AABB::default()
+        };
+    }
+
+    /// the children `ray`, in world space, may hit
+    fn along(&self, ray: &Ray) -> Vec<usize> {
+        self.tree
+            .along(ray)
+            .into_iter()
+            .flat_map(|(start, end)| self.bounded[start..end].iter().copied())
+            .chain(self.unbounded.iter().copied())
+            .collect()
     }
 }
 
-impl<S: Shape + Sized> BoundingBox for DynGroup<S> {
+fn is_finite(bounds: &AABB) -> bool {
+    bounds
+        .min
+        .iter()
+        .chain(bounds.max.iter())
+        .all(|c| c.is_finite())
+}
+
+fn inverse(transform: &Matrix4<f32>) -> Matrix4<f32> {
+    Projective3::from_matrix_unchecked(*transform)
+        .inverse()
+        .to_homogeneous()
+}
+
+impl BoundingBox for DynGroup {
+    /// in the group's own space, like other shapes' bounds
     fn bounding_box(&self) -> AABB {
-        self.bounding_box
+        self.bounding_box.transformed(&inverse(&self.transform))
     }
 }
 
-impl<S: Shape + Sized> Shape for DynGroup<S> {
-    fn material(&self) -> crate::material::Material {
-        self.material
+impl Shape for DynGroup {
+    fn material(&self) -> &Material {
+        &self.material
     }
 
-    fn transform(&self) -> nalgebra::Matrix4<f32> {
+    fn transform(&self) -> Matrix4<f32> {
         self.transform
     }
 
-    fn normal_at(&self, _point: nalgebra::Point3<f32>) -> nalgebra::Vector3<f32> {
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+
+        for child in self.children.iter_mut() {
+            child.transform_by(parent);
+        }
+
+        self.update();
+    }
+
+    fn normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
         Vector3::new(0.0, 0.0, 0.0)
     }
 
-    fn local_intersect(&self, ray: crate::ray::Ray) -> Vec<crate::intersection::Intersection> {
-        let cube: Cube = self.bounding_box.into();
+    /// the children already carry the group's transform, so they're handed
+    /// the world-space ray
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let candidates = self.along(ray);
+        let hit = |&i: &usize| self.children[i].local_intersect(ray.transform(self.inverses[i]));
 
         #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
-        if !cube.local_intersect(ray).is_empty() {
-            let intersections = self
-                .shapes
-                .par_iter()
-                .map(|shape| shape.local_intersect(ray))
-                .collect::<Vec<Vec<_>>>();
-
-            intersections.into_iter().flatten().collect::<Vec<_>>()
-        } else {
-            vec![]
-        }
+        let intersections = candidates.par_iter().map(hit).collect::<Vec<Vec<_>>>();
         #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
-        if !cube.local_intersect(ray).is_empty() {
-            let intersections = self
-                .shapes
-                .iter()
-                .map(|shape| shape.local_intersect(ray))
-                .collect::<Vec<Vec<_>>>();
-
-            intersections.into_iter().flatten().collect::<Vec<_>>()
-        } else {
-            vec![]
-        }
+        let intersections = candidates.iter().map(hit).collect::<Vec<Vec<_>>>();
+
+        intersections.into_iter().flatten().collect()
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        self.intersect(&ray.transform(self.transform))
     }
 
-    fn local_normal_at(&self, _point: nalgebra::Point3<f32>) -> nalgebra::Vector3<f32> {
+    fn local_normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
         Vector3::new(0.0, 0.0, 0.0)
     }
+
+    fn primitive_index(&self, hit: &Intersection) -> Option<usize> {
+        let address = hit.object as *const dyn Shape as *const ();
+
+        self.children
+            .iter()
+            .position(|child| child.as_ref() as *const dyn Shape as *const () == address)
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        self.children
+            .iter()
+            .flat_map(|child| child.emitters())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::Group;
+    use crate::sphere::Sphere;
+    use crate::triangle::Triangle;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn sphere_at(x: f32, y: f32, z: f32) -> Box<dyn Shape> {
+        Box::new(Sphere {
+            transform: Matrix4::new_translation(&Vector3::new(x, y, z)),
+            ..Sphere::new()
+        })
+    }
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).norm() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_an_empty_group() {
+        let g = DynGroup::new(vec![]);
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(g.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn intersecting_a_transformed_group() {
+        let g =
+            DynGroup::new(vec![sphere_at(5.0, 0.0, 0.0)]).with_transform(Matrix4::new_scaling(2.0));
+        let r = Ray::new(Point3::new(10.0, 0.0, -10.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(g.intersect(&r).len(), 2);
+    }
+
+    #[test]
+    fn converting_a_point_from_world_to_object_space() {
+        let g2 =
+            DynGroup::new(vec![sphere_at(5.0, 0.0, 0.0)]).with_transform(Matrix4::new_scaling(2.0));
+        let g1 = DynGroup::new(vec![Box::new(g2)])
+            .with_transform(Matrix4::from_euler_angles(0.0, FRAC_PI_2, 0.0));
+
+        let r = Ray::new(Point3::new(0.0, 0.0, -20.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = g1.intersect(&r);
+        let sphere = hits.first().unwrap().object;
+
+        // the sphere's transform runs through both groups
+        let point = inverse(&sphere.transform()).transform_point(&Point3::new(-2.0, 0.0, -10.0));
+        assert_close(point.coords, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_child_object() {
+        let g2 = DynGroup::new(vec![sphere_at(5.0, 0.0, 0.0)]).with_transform(
+            Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 2.0, 3.0)),
+        );
+        let g1 = DynGroup::new(vec![Box::new(g2)])
+            .with_transform(Matrix4::from_euler_angles(0.0, FRAC_PI_2, 0.0));
+
+        // a ray towards the point the book picks, so the hit is the sphere itself
+        let point = Point3::new(1.7321, 1.1547, -5.5774);
+        let r = Ray::new(
+            point + Vector3::new(0.0, 0.0, -0.001),
+            Vector3::new(0.0, 0.0, 1.0),
+        );
+        let hits = g1.intersect(&r);
+        let sphere = hits.first().unwrap().object;
+
+        assert_close(
+            sphere.normal_at(point),
+            Vector3::new(0.2857, 0.4286, -0.8571),
+        );
+    }
+
+    #[test]
+    fn groups_hold_any_kind_of_shape() {
+        let triangle = Triangle::new(
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        );
+        let g = DynGroup::new(vec![
+            sphere_at(0.0, 0.0, 5.0),
+            Box::new(triangle),
+            sphere_at(0.0, 0.0, 10.0),
+        ])
+        .with_transform(Matrix4::new_translation(&Vector3::new(0.0, 0.0, 1.0)));
+
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let mut ts = g.intersect(&r).iter().map(|i| i.t).collect::<Vec<_>>();
+        ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // the triangle moved with the group, as did the spheres
+        assert_eq!(ts.len(), 5);
+        assert!((ts[0] - 6.0).abs() < 1e-4);
+
+        let hit = g
+            .intersect(&r)
+            .into_iter()
+            .find(|i| (i.t - 6.0).abs() < 1e-4)
+            .unwrap();
+        assert_eq!(g.primitive_index(&hit), Some(1));
+    }
+
+    #[test]
+    fn grouped_meshes_are_indexed_by_their_bounds() {
+        let triangle = Triangle::new(
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        );
+        let mesh = Group::new(vec![triangle.clone(), triangle.clone()]);
+        let g = DynGroup::new(vec![Box::new(mesh), Box::new(triangle)]);
+
+        assert_eq!(g.bounded.len(), 2);
+        assert!(g.unbounded.is_empty());
+        assert!(is_finite(&g.bounding_box()));
+    }
+
+    #[test]
+    fn rays_that_miss_a_groups_bounds_skip_its_children() {
+        let g = DynGroup::new(vec![sphere_at(0.0, 0.0, 0.0)])
+            .with_transform(Matrix4::new_translation(&Vector3::new(10.0, 0.0, 0.0)));
+
+        let near = Ray::new(Point3::new(10.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let far = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(g.along(&near), vec![0]);
+        assert!(g.along(&far).is_empty());
+    }
 }
@@ -0,0 +1,224 @@
+use crate::material::Color;
+use nalgebra::{Point3, Vector3};
+
+/// An emissive triangle in world space that can be sampled as a light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub p1: Point3<f32>,
+    pub e1: Vector3<f32>,
+    pub e2: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    pub area: f32,
+    pub radiance: Color,
+}
+
+impl Emitter {
+    /// `None` for degenerate triangles, which can't emit anything
+    pub fn triangle(
+        p1: Point3<f32>,
+        p2: Point3<f32>,
+        p3: Point3<f32>,
+        radiance: Color,
+    ) -> Option<Self> {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        let cross = e1.cross(&e2);
+        let area = cross.magnitude() / 2.0;
+        let normal = cross.try_normalize(f32::EPSILON)?;
+
+        Some(Emitter {
+            p1,
+            e1,
+            e2,
+            normal,
+            area,
+            radiance,
+        })
+    }
+
+    fn power(&self) -> f32 {
+        self.area * (self.radiance.x + self.radiance.y + self.radiance.z)
+    }
+
+    /// uniformly distributed over the triangle for (u1, u2) uniform in the unit square
+    pub fn point_at(&self, u1: f32, u2: f32) -> Point3<f32> {
+        let su = u1.sqrt();
+
+        self.p1 + self.e1 * (su * (1.0 - u2)) + self.e2 * (su * u2)
+    }
+}
+
+/// A light sample: the point on the emitter, its normal, and the radiance
+/// already divided by the probability density of picking that point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EmitterSample {
+    pub point: Point3<f32>,
+    pub normal: Vector3<f32>,
+    pub weighted_radiance: Color,
+}
+
+/// All emissive triangles in a world, picked in proportion to their power.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Emitters {
+    emitters: Vec<Emitter>,
+    cdf: Vec<f32>,
+}
+
+impl Emitters {
+    pub fn new(emitters: Vec<Emitter>) -> Self {
+        let emitters = emitters
+            .into_iter()
+            .filter(|e| e.power() > 0.0)
+            .collect::<Vec<_>>();
+
+        let mut total = 0.0;
+        let mut cdf = Vec::with_capacity(emitters.len());
+
+        for emitter in emitters.iter() {
+            total += emitter.power();
+            cdf.push(total);
+        }
+
+        for c in cdf.iter_mut() {
+            *c /= total;
+        }
+
+        Emitters { emitters, cdf }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+
+    /// `u0` picks the emitter, `u1` and `u2` the point on it
+    pub fn sample(&self, u0: f32, u1: f32, u2: f32) -> Option<EmitterSample> {
+        if self.emitters.is_empty() {
+            return None;
+        }
+
+        let index = self
+            .cdf
+            .iter()
+            .position(|&c| u0 < c)
+            .unwrap_or(self.emitters.len() - 1);
+
+        let emitter = &self.emitters[index];
+        let previous = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        let pick_probability = self.cdf[index] - previous;
+
+        Some(EmitterSample {
+            point: emitter.point_at(u1, u2),
+            normal: emitter.normal,
+            weighted_radiance: emitter.radiance * (emitter.area / pick_probability),
+        })
+    }
+}
+
+/// `count` stratified sample triples, rotated by a per-point offset so that
+/// neighbouring pixels trade banding for fine noise
+pub fn stratified_samples(count: usize, point: Point3<f32>) -> Vec<(f32, f32, f32)> {
+    let (r0, r1, r2) = hash_point(point);
+    let side = (count as f32).sqrt().ceil() as usize;
+
+    (0..count)
+        .map(|i| {
+            let u0 = ((i as f32 + 0.5) / count as f32 + r0).fract();
+            let u1 = (((i % side) as f32 + 0.5) / side as f32 + r1).fract();
+            let u2 = (((i / side % side) as f32 + 0.5) / side as f32 + r2).fract();
+            (u0, u1, u2)
+        })
+        .collect()
+}
+
+fn hash_point(point: Point3<f32>) -> (f32, f32, f32) {
+    let mut h = 0x9e37_79b9
+        ^ point.x.to_bits().wrapping_mul(73_856_093)
+        ^ point.y.to_bits().wrapping_mul(19_349_663)
+        ^ point.z.to_bits().wrapping_mul(83_492_791);
+
+    let mut next = || {
+        // xorshift32
+        h ^= h << 13;
+        h ^= h >> 17;
+        h ^= h << 5;
+        (h >> 8) as f32 / (1 << 24) as f32
+    };
+
+    (next(), next(), next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_triangle(radiance: Color) -> Emitter {
+        Emitter::triangle(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            radiance,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn degenerate_triangles_do_not_emit() {
+        let emitter = Emitter::triangle(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        assert!(emitter.is_none());
+    }
+
+    #[test]
+    fn sampled_points_lie_on_the_triangle() {
+        let emitter = unit_triangle(Vector3::new(1.0, 1.0, 1.0));
+
+        for (_, u1, u2) in stratified_samples(64, Point3::new(0.3, 0.2, 0.1)) {
+            let p = emitter.point_at(u1, u2);
+            assert_eq!(p.y, 0.0);
+            assert!(p.x >= 0.0 && p.z >= 0.0 && p.x + p.z <= 1.0 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn brighter_emitters_are_picked_more_often() {
+        let white = unit_triangle(Vector3::new(1.0, 1.0, 1.0));
+        let red = unit_triangle(Vector3::new(9.0, 0.0, 0.0));
+        let emitters = Emitters::new(vec![white, red]);
+
+        let picks_red = (0..100)
+            .map(|i| emitters.sample(i as f32 / 100.0, 0.5, 0.5).unwrap())
+            .filter(|sample| sample.weighted_radiance.y == 0.0)
+            .count();
+
+        assert_eq!(picks_red, 75);
+    }
+
+    #[test]
+    fn the_weighted_radiance_is_unbiased() {
+        let emitters = Emitters::new(vec![
+            unit_triangle(Vector3::new(1.0, 1.0, 1.0)),
+            unit_triangle(Vector3::new(3.0, 3.0, 3.0)),
+        ]);
+
+        let samples = stratified_samples(400, Point3::new(0.0, 0.0, 0.0));
+        let total = samples
+            .iter()
+            .map(|(u0, u1, u2)| emitters.sample(*u0, *u1, *u2).unwrap().weighted_radiance.x)
+            .sum::<f32>()
+            / samples.len() as f32;
+
+        // sum of radiance * area over both triangles
+        assert!((total - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn black_emitters_are_dropped() {
+        let emitters = Emitters::new(vec![unit_triangle(Vector3::new(0.0, 0.0, 0.0))]);
+        assert!(emitters.is_empty());
+        assert!(emitters.sample(0.5, 0.5, 0.5).is_none());
+    }
+}
@@ -34,8 +34,8 @@ impl<'a> Shape<'a> for ExampleShape<'a> {
         self.parent = Some(group);
     }
 
-    fn material(&self) -> Material {
-        self.material
+    fn material(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix4<f32> {
@@ -0,0 +1,127 @@
+/// Writes a minimal OpenEXR file: a single part of uncompressed scanlines
+/// with 32-bit float channels. `channels` are named layers of
+/// `width * height` row-major values, e.g. `("depth.Z", ...)`, in any order.
+pub fn write_exr(width: usize, height: usize, channels: &[(String, Vec<f32>)]) -> Vec<u8> {
+    // readers expect the channel list in alphabetical order
+    let mut channels = channels.iter().collect::<Vec<_>>();
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut out = vec![];
+
+    out.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    out.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut chlist = vec![];
+
+    for (name, _) in channels.iter() {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+        chlist.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // xSampling
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // ySampling
+    }
+
+    chlist.push(0);
+
+    let mut window = vec![];
+
+    for value in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+
+    attribute(&mut out, "channels", "chlist", &chlist);
+    attribute(&mut out, "compression", "compression", &[0]);
+    attribute(&mut out, "dataWindow", "box2i", &window);
+    attribute(&mut out, "displayWindow", "box2i", &window);
+    attribute(&mut out, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut out, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut out,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    out.push(0);
+
+    // one scanline per chunk: the y coordinate, the size, then each channel's row
+    let row_size = channels.len() * width * 4;
+    let chunk_size = 8 + row_size;
+    let table_end = out.len() + height * 8;
+
+    for y in 0..height {
+        out.extend_from_slice(&((table_end + y * chunk_size) as u64).to_le_bytes());
+    }
+
+    for y in 0..height {
+        out.extend_from_slice(&(y as i32).to_le_bytes());
+        out.extend_from_slice(&(row_size as i32).to_le_bytes());
+
+        for (_, values) in channels.iter() {
+            for value in &values[y * width..(y + 1) * width] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    out
+}
+
+fn attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    #[test]
+    fn an_exr_starts_with_the_magic_number() {
+        let exr = write_exr(1, 1, &[("Y".to_string(), vec![0.5])]);
+        assert_eq!(&exr[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn channels_are_listed_alphabetically() {
+        let exr = write_exr(
+            1,
+            1,
+            &[("Z".to_string(), vec![1.0]), ("A".to_string(), vec![2.0])],
+        );
+        assert!(find(&exr, b"A\0").unwrap() < find(&exr, b"Z\0").unwrap());
+    }
+
+    #[test]
+    fn scanlines_follow_the_offset_table() {
+        let red = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let exr = write_exr(3, 2, &[("R".to_string(), red)]);
+
+        let header_end = find(&exr, b"screenWindowWidth\0float\0").unwrap() + 32;
+        assert_eq!(exr[header_end], 0);
+
+        let table = header_end + 1;
+        let second = u64::from_le_bytes(exr[table + 8..table + 16].try_into().unwrap()) as usize;
+        assert_eq!(second, exr.len() - 20);
+        assert_eq!(
+            i32::from_le_bytes(exr[second..second + 4].try_into().unwrap()),
+            1
+        );
+        assert_eq!(
+            f32::from_le_bytes(exr[second + 8..second + 12].try_into().unwrap()),
+            4.0
+        );
+    }
+}
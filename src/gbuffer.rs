@@ -0,0 +1,152 @@
+use crate::material::Color;
+use nalgebra::{Point3, Vector3};
+
+/// What a camera ray hit, besides its color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceSample {
+    /// index into `World::objects`
+    pub object: usize,
+    /// the object's child that was hit, e.g. a mesh triangle
+    pub primitive: Option<usize>,
+    /// distance from the camera along the ray
+    pub depth: f32,
+    pub position: Point3<f32>,
+    /// in world space, facing the camera
+    pub normal: Vector3<f32>,
+    /// distance to the nearest edge of the primitive, see `Shape::edge_distance`
+    pub edge_distance: Option<f32>,
+    /// surface color before lighting
+    pub albedo: Color,
+    /// whether every point light is blocked
+    pub shadowed: bool,
+}
+
+#[cfg(test)]
+impl SurfaceSample {
+    pub(crate) fn at_depth(object: usize, depth: f32) -> Self {
+        SurfaceSample {
+            object,
+            primitive: None,
+            depth,
+            position: Point3::new(0.0, 0.0, depth),
+            normal: Vector3::new(0.0, 0.0, -1.0),
+            edge_distance: None,
+            albedo: Vector3::new(1.0, 1.0, 1.0),
+            shadowed: false,
+        }
+    }
+}
+
+/// Per-pixel geometry from a render, for outlines and other post-processing.
+/// Pixels are `None` where the ray missed or landed on a shadow catcher.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GBuffer {
+    pub width: usize,
+    pub height: usize,
+    surfaces: Vec<Option<SurfaceSample>>,
+}
+
+impl GBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        GBuffer {
+            width,
+            height,
+            surfaces: vec![None; width * height],
+        }
+    }
+
+    pub(crate) fn write(&mut self, x: usize, y: usize, surface: Option<SurfaceSample>) {
+        self.surfaces[y * self.width + x] = surface;
+    }
+
+    pub fn surface_at(&self, x: usize, y: usize) -> Option<&SurfaceSample> {
+        self.surfaces[y * self.width + x].as_ref()
+    }
+
+    /// infinite where nothing was hit
+    pub fn depth_at(&self, x: usize, y: usize) -> f32 {
+        self.surface_at(x, y)
+            .map(|s| s.depth)
+            .unwrap_or(f32::INFINITY)
+    }
+
+    /// a row-major mask of the pixels where one object ends and another (or
+    /// the background) begins, or where the depth jumps by more than
+    /// `depth_threshold` times the nearer depth
+    pub fn silhouettes(&self, depth_threshold: f32) -> Vec<bool> {
+        let mut mask = vec![false; self.width * self.height];
+
+        let differ = |a: Option<&SurfaceSample>, b: Option<&SurfaceSample>| match (a, b) {
+            (Some(a), Some(b)) => {
+                a.object != b.object
+                    || (a.depth - b.depth).abs() > a.depth.min(b.depth) * depth_threshold
+            }
+            (None, None) => false,
+            _ => true,
+        };
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let here = self.surface_at(x, y);
+
+                // mark the nearer of the two pixels, so lines hug the foreground
+                let mut mark = |nx: usize, ny: usize| {
+                    let there = self.surface_at(nx, ny);
+
+                    if differ(here, there) {
+                        if self.depth_at(x, y) <= self.depth_at(nx, ny) {
+                            mask[y * self.width + x] = true;
+                        } else {
+                            mask[ny * self.width + nx] = true;
+                        }
+                    }
+                };
+
+                if x + 1 < self.width {
+                    mark(x + 1, y);
+                }
+
+                if y + 1 < self.height {
+                    mark(x, y + 1);
+                }
+            }
+        }
+
+        mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface(object: usize, depth: f32) -> Option<SurfaceSample> {
+        Some(SurfaceSample::at_depth(object, depth))
+    }
+
+    #[test]
+    fn an_empty_buffer_has_no_silhouettes() {
+        let buffer = GBuffer::new(4, 3);
+        assert_eq!(buffer.depth_at(2, 1), f32::INFINITY);
+        assert!(buffer.silhouettes(0.1).iter().all(|&m| !m));
+    }
+
+    #[test]
+    fn silhouettes_follow_the_nearer_object() {
+        let mut buffer = GBuffer::new(3, 1);
+        buffer.write(0, 0, surface(1, 5.0));
+        buffer.write(1, 0, surface(1, 5.1));
+        buffer.write(2, 0, surface(0, 9.0));
+        assert_eq!(buffer.silhouettes(0.1), vec![false, true, false]);
+    }
+
+    #[test]
+    fn silhouettes_at_depth_jumps_within_an_object() {
+        let mut buffer = GBuffer::new(2, 2);
+        buffer.write(0, 0, surface(0, 2.0));
+        buffer.write(1, 0, surface(0, 2.0));
+        buffer.write(0, 1, surface(0, 4.0));
+        buffer.write(1, 1, surface(0, 4.0));
+        assert_eq!(buffer.silhouettes(0.1), vec![true, true, false, false]);
+    }
+}
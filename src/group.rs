@@ -1,14 +1,20 @@
 use crate::bounding_box::{BoundingBox, AABB};
-use crate::cube::Cube;
-use nalgebra::{Matrix4, Vector3};
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use nalgebra::{Matrix4, Projective3, Vector3};
 
 #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::{material::Material, shape::Shape};
 
+/// Shapes of one kind moved together by the group's transform, which, as
+/// in a `DynGroup`, is passed down so each shape's own transform takes it
+/// to world space.
 pub struct Group<S: Shape> {
     shapes: Vec<S>,
+    /// in world space
     bounding_box: AABB,
     material: Material,
     transform: Matrix4<f32>,
@@ -19,70 +25,144 @@ impl<S: Shape> Group<S> {
         let transform = Matrix4::identity();
         let material = Material::default();
 
-        let mut aabb = AABB::default();
-
-        for shape in shapes.iter() {
-            aabb.merge_mut(shape.bounding_box());
-        }
-
         Group {
+            bounding_box: bounds(&shapes),
             shapes,
-            bounding_box: aabb,
             material,
             transform,
         }
     }
 }
 
+/// the world-space box around every shape
+fn bounds<S: Shape>(shapes: &[S]) -> AABB {
+    shapes
+        .iter()
+        .map(|shape| shape.bounding_box().transformed(&shape.transform()))
+        .fold(AABB::around(vec![]), |aabb, bounds| aabb.union(&bounds))
+}
+
 impl<S: Shape> BoundingBox for Group<S> {
+    /// in the group's own space, like other shapes' bounds
     fn bounding_box(&self) -> AABB {
-        self.bounding_box
+        let inverse = Projective3::from_matrix_unchecked(self.transform)
+            .inverse()
+            .to_homogeneous();
+
+        self.bounding_box.transformed(&inverse)
     }
 }
 
 impl<S: Shape> Shape for Group<S> {
-    fn material(&self) -> crate::material::Material {
-        self.material
+    fn material(&self) -> &crate::material::Material {
+        &self.material
     }
 
     fn transform(&self) -> nalgebra::Matrix4<f32> {
         self.transform
     }
 
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+
+        for shape in self.shapes.iter_mut() {
+            shape.transform_by(parent);
+        }
+
+        self.bounding_box = bounds(&self.shapes);
+    }
+
     fn normal_at(&self, _point: nalgebra::Point3<f32>) -> nalgebra::Vector3<f32> {
         Vector3::new(0.0, 0.0, 0.0)
     }
 
-    fn local_intersect(&self, ray: crate::ray::Ray) -> Vec<crate::intersection::Intersection> {
-        let cube: Cube = self.bounding_box.into();
+    /// the shapes already carry the group's transform, so they're handed
+    /// the world-space ray
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if !self.bounding_box.is_hit_by(ray) {
+            return vec![];
+        }
 
         #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
-        if !cube.local_intersect(ray).is_empty() {
-            let intersections = self
-                .shapes
-                .par_iter()
-                .map(|shape| shape.local_intersect(ray))
-                .collect::<Vec<Vec<_>>>();
-
-            intersections.into_iter().flatten().collect::<Vec<_>>()
-        } else {
-            vec![]
-        }
+        let intersections = self
+            .shapes
+            .par_iter()
+            .map(|shape| shape.intersect(ray))
+            .collect::<Vec<Vec<_>>>();
         #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
-        if cube.local_intersect(ray).len() > 0 {
-            let intersections = self
-                .shapes
-                .iter()
-                .map(|shape| shape.local_intersect(ray))
-                .collect::<Vec<Vec<_>>>();
-
-            intersections.into_iter().flatten().collect::<Vec<_>>()
-        } else {
-            vec![]
-        }
+        let intersections = self
+            .shapes
+            .iter()
+            .map(|shape| shape.intersect(ray))
+            .collect::<Vec<Vec<_>>>();
+
+        intersections.into_iter().flatten().collect()
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        self.intersect(&ray.transform(self.transform))
     }
 
     fn local_normal_at(&self, _point: nalgebra::Point3<f32>) -> nalgebra::Vector3<f32> {
         Vector3::new(0.0, 0.0, 0.0)
     }
+
+    fn primitive_index(&self, hit: &Intersection) -> Option<usize> {
+        // children hand out references into `shapes`, so the address says which one was hit
+        let size = std::mem::size_of::<S>();
+        let start = self.shapes.as_ptr() as usize;
+        let address = hit.object as *const dyn Shape as *const () as usize;
+
+        if size == 0 || address < start {
+            return None;
+        }
+
+        let index = (address - start) / size;
+
+        if index < self.shapes.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        self.shapes
+            .iter()
+            .flat_map(|shape| shape.emitters())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use nalgebra::Point3;
+
+    #[test]
+    fn a_transformed_group_moves_its_transformed_children() {
+        let sphere = Sphere {
+            transform: Matrix4::new_translation(&Vector3::new(5.0, 0.0, 0.0)),
+            ..Sphere::new()
+        };
+        let mut g = Group::new(vec![sphere]);
+        g.transform_by(&Matrix4::new_scaling(2.0));
+
+        let through = |x: f32| {
+            let r = Ray::new(Point3::new(x, 0.0, -10.0), Vector3::new(0.0, 0.0, 1.0));
+            g.intersect(&r).iter().map(|i| i.t).collect::<Vec<_>>()
+        };
+
+        // the sphere now sits at x = 10 with radius 2
+        let mut ts = through(10.0);
+        ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ts.len(), 2);
+        assert!((ts[0] - 8.0).abs() < 1e-4 && (ts[1] - 12.0).abs() < 1e-4);
+        assert!(through(5.0).is_empty());
+
+        let bounds = g.bounding_box();
+        assert!((bounds.min - Point3::new(4.0, -1.0, -1.0)).norm() < 1e-4);
+        assert!((bounds.max - Point3::new(6.0, 1.0, 1.0)).norm() < 1e-4);
+    }
 }
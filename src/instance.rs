@@ -0,0 +1,248 @@
+use crate::bounding_box::{BoundingBox, AABB};
+use crate::bvh::Bvh;
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point2, Point3, Projective3, Vector3};
+use std::sync::Arc;
+
+/// One placement of a mesh that may be placed many times over: the triangles
+/// and their hierarchy are shared, only the transform and material are the
+/// instance's own.
+pub struct Instance {
+    mesh: Arc<Bvh>,
+    material: Material,
+    transform: Matrix4<f32>,
+    inverse: Matrix4<f32>,
+}
+
+impl Instance {
+    pub fn new(mesh: Arc<Bvh>) -> Self {
+        Instance {
+            mesh,
+            material: Material::default(),
+            transform: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4<f32>) -> Self {
+        self.transform_by(&transform);
+        self
+    }
+}
+
+impl BoundingBox for Instance {
+    fn bounding_box(&self) -> AABB {
+        self.mesh.bounds()
+    }
+}
+
+impl Shape for Instance {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+        self.inverse = Projective3::from_matrix_unchecked(self.transform)
+            .inverse()
+            .to_homogeneous();
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.local_intersect(ray.transform(self.inverse))
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        self.mesh
+            .intersect(&ray)
+            .into_iter()
+            .map(|crossing| {
+                Intersection::with_uv(crossing.t, self, crossing.u, crossing.v)
+                    .with_primitive(crossing.triangle)
+            })
+            .collect()
+    }
+
+    /// the outward normal of the triangle nearest the point; hits say which
+    /// triangle they're on, so shading looks it up through `normal_at_hit`
+    fn local_normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        self.mesh
+            .closest_point(point)
+            .map_or_else(Vector3::zeros, |closest| closest.normal)
+    }
+
+    fn normal_at_hit(&self, hit: &Intersection, _point: Point3<f32>) -> Vector3<f32> {
+        normal_to_world(&self.transform, self.mesh.normal(hit.primitive))
+    }
+
+    fn outward_normal_at_hit(&self, hit: &Intersection, point: Point3<f32>) -> Vector3<f32> {
+        self.normal_at_hit(hit, point)
+    }
+
+    fn uv_at(&self, hit: &Intersection) -> Option<Point2<f32>> {
+        self.mesh.triangle(hit.primitive).uv_at(hit)
+    }
+
+    fn edge_distance(&self, hit: &Intersection) -> Option<f32> {
+        let mut triangle = self.mesh.triangle(hit.primitive);
+        triangle.transform = self.transform;
+
+        triangle.edge_distance(hit)
+    }
+
+    /// the triangle's place in the mesh as it was loaded, as a group of the
+    /// same triangles would number it
+    fn primitive_index(&self, hit: &Intersection) -> Option<usize> {
+        Some(self.mesh.source_index(hit.primitive))
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        if !self.material.is_emissive() {
+            return vec![];
+        }
+
+        self.mesh
+            .triangles()
+            .iter()
+            .filter_map(|[p1, p2, p3]| {
+                Emitter::triangle(
+                    self.transform.transform_point(p1),
+                    self.transform.transform_point(p2),
+                    self.transform.transform_point(p3),
+                    self.material.emission(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::Group;
+    use crate::triangle::Triangle;
+
+    fn square() -> Arc<Bvh> {
+        let corners = [
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(-1.0, 1.0, 0.0),
+        ];
+
+        Arc::new(Bvh::new(&[
+            Triangle::new(corners[0], corners[1], corners[2]),
+            Triangle::new(corners[0], corners[2], corners[3]),
+        ]))
+    }
+
+    #[test]
+    fn instances_share_their_mesh() {
+        let mesh = square();
+        let a = Instance::new(mesh.clone());
+        let b = Instance::new(mesh.clone())
+            .with_transform(Matrix4::new_translation(&Vector3::new(5.0, 0.0, 0.0)));
+
+        assert_eq!(Arc::strong_count(&mesh), 3);
+        assert!(Arc::ptr_eq(&a.mesh, &b.mesh));
+    }
+
+    #[test]
+    fn intersecting_a_moved_instance() {
+        let instance = Instance::new(square())
+            .with_transform(Matrix4::new_translation(&Vector3::new(5.0, 0.0, 2.0)));
+
+        let hit = Ray::new(Point3::new(5.5, 0.2, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let miss = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        let hits = instance.intersect(&hit);
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].t - 7.0).abs() < 1e-5);
+        assert!(instance.intersect(&miss).is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_a_turned_instance() {
+        let instance = Instance::new(square());
+        let turned = Instance::new(square()).with_transform(Matrix4::from_euler_angles(
+            std::f32::consts::FRAC_PI_2,
+            0.0,
+            0.0,
+        ));
+
+        assert_eq!(
+            instance.normal_at(Point3::new(0.5, 0.2, 0.0)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+        assert!(
+            (turned.normal_at(Point3::new(0.5, 0.0, 0.2)) - Vector3::new(0.0, -1.0, 0.0)).norm()
+                < 1e-5
+        );
+    }
+
+    #[test]
+    fn hits_on_an_instance_know_their_triangle() {
+        let corners = [
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+        ];
+        let triangle = Triangle::new(corners[0], corners[1], corners[2]).with_uvs(
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+        );
+        let instance = Instance::new(Arc::new(Bvh::new(std::slice::from_ref(&triangle))))
+            .with_transform(Matrix4::new_scaling(2.0));
+        let mut group = Group::new(vec![triangle.clone()]);
+        group.transform_by(&Matrix4::new_scaling(2.0));
+        let mut moved = triangle;
+        moved.transform_by(&Matrix4::new_scaling(2.0));
+
+        let ray = Ray::new(Point3::new(1.0, -1.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = &instance.intersect(&ray)[0];
+        let alone = &moved.intersect(&ray)[0];
+
+        assert_eq!(instance.primitive_index(hit), Some(0));
+        assert_eq!(group.primitive_index(&group.intersect(&ray)[0]), Some(0));
+        assert_eq!(instance.uv_at(hit), moved.uv_at(alone));
+        assert_eq!(instance.edge_distance(hit), moved.edge_distance(alone));
+        assert_eq!(
+            instance.normal_at_hit(hit, ray.position(hit.t)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn an_instance_overrides_the_material() {
+        let material = Material {
+            specular: 0.3,
+            ..Material::default()
+        };
+        let instance = Instance::new(square()).with_material(material);
+
+        let ray = Ray::new(Point3::new(0.0, 0.2, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = instance.intersect(&ray);
+
+        assert_eq!(hits[0].object.material().specular, 0.3);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::ray::Ray;
 use crate::shape::Shape;
-use nalgebra::{Point3, Vector3};
+use nalgebra::{Point2, Point3, Vector3};
 use std::cmp::Ordering;
 
 pub struct PreparedComputations<'p> {
@@ -10,17 +10,47 @@ pub struct PreparedComputations<'p> {
     pub eyev: Vector3<f32>,
     pub normalv: Vector3<f32>,
     pub inside: bool,
+    /// pointing out of the surface, before any flip towards the eye
+    pub outward_normalv: Vector3<f32>,
     pub over_point: Point3<f32>,
+    pub uv: Option<Point2<f32>>,
 }
 
 pub struct Intersection<'a> {
     pub t: f32,
     pub object: &'a dyn Shape,
+    /// barycentric coordinates of the hit, for shapes that have them (triangles)
+    pub u: f32,
+    pub v: f32,
+    /// which of the shape's primitives was hit, for shapes that say (mesh
+    /// instances)
+    pub primitive: usize,
 }
 
 impl<'a> Intersection<'a> {
     pub fn new<T: 'a + Shape>(t: f32, object: &'a T) -> Self {
-        Intersection { t, object }
+        Intersection {
+            t,
+            object,
+            u: 0.0,
+            v: 0.0,
+            primitive: 0,
+        }
+    }
+
+    pub fn with_uv<T: 'a + Shape>(t: f32, object: &'a T, u: f32, v: f32) -> Self {
+        Intersection {
+            t,
+            object,
+            u,
+            v,
+            primitive: 0,
+        }
+    }
+
+    pub fn with_primitive(mut self, primitive: usize) -> Self {
+        self.primitive = primitive;
+        self
     }
 
     pub fn hit(intersections: Vec<Intersection>) -> Option<Intersection> {
@@ -39,15 +69,25 @@ impl<'a> Intersection<'a> {
         }
     }
 
-    pub fn prepare_computations(&self, ray: &Ray) -> PreparedComputations {
+    pub fn prepare_computations(&self, ray: &Ray) -> PreparedComputations<'a> {
         let point = ray.position(self.t);
         let eyev = ray.direction * -1.0;
-        let normalv = self.object.normal_at(point);
+        let uv = self.object.uv_at(self);
+        let normalv = self.object.normal_at_hit(self, point);
+        let normalv = self
+            .object
+            .material()
+            .perturb_normal(self.object, point, uv, normalv);
+
+        // decided by the unperturbed outward normal, so that a mesh's back
+        // faces count as inside whichever way its triangles define `normal`
+        let outward_normalv = self.object.outward_normal_at_hit(self, point);
+        let inside = outward_normalv.dot(&eyev) < 0.0;
 
-        let (inside, normalv) = if normalv.dot(&eyev) < 0.0 {
-            (true, normalv * -1.0)
+        let normalv = if normalv.dot(&eyev) < 0.0 {
+            normalv * -1.0
         } else {
-            (false, normalv)
+            normalv
         };
 
         let over_point = point + normalv * 0.00001;
@@ -59,7 +99,9 @@ impl<'a> Intersection<'a> {
             eyev,
             normalv,
             inside,
+            outward_normalv,
             over_point,
+            uv,
         }
     }
 }
@@ -0,0 +1,458 @@
+use std::fmt::Write;
+
+/// how deeply arrays and objects may nest, so malformed input runs out of
+/// patience before the parser runs out of stack
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value and the line it starts on, for error messages.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Json {
+    pub(crate) line: usize,
+    pub(crate) value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// members in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    pub(crate) fn as_f32(&self) -> Result<f32, String> {
+        match self.value {
+            Value::Number(n) => Ok(n as f32),
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Result<usize, String> {
+        match self.value {
+            Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+            _ => Err(self.error("expected a whole number")),
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Result<bool, String> {
+        match self.value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(self.error("expected true or false")),
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Result<&str, String> {
+        match &self.value {
+            Value::String(s) => Ok(s),
+            _ => Err(self.error("expected a string")),
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Result<&[Json], String> {
+        match &self.value {
+            Value::Array(items) => Ok(items),
+            _ => Err(self.error("expected a list")),
+        }
+    }
+
+    pub(crate) fn as_object(&self) -> Result<&[(String, Json)], String> {
+        match &self.value {
+            Value::Object(members) => Ok(members),
+            _ => Err(self.error("expected an object")),
+        }
+    }
+
+    /// an array of exactly `N` numbers, e.g. a point or a color
+    pub(crate) fn as_numbers<const N: usize>(&self) -> Result<[f32; N], String> {
+        let items = self.as_array()?;
+
+        if items.len() != N {
+            return Err(self.error(&format!("expected {} numbers", N)));
+        }
+
+        let mut numbers = [0.0; N];
+
+        for (number, item) in numbers.iter_mut().zip(items) {
+            *number = item.as_f32()?;
+        }
+
+        Ok(numbers)
+    }
+}
+
+pub(crate) fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        position: 0,
+        line: 1,
+        depth: 0,
+    };
+
+    let json = parser.value()?;
+    parser.skip_whitespace();
+
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("unexpected text after the end"));
+    }
+
+    Ok(json)
+}
+
+/// `s` as a JSON string literal
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    /// arrays and objects open around the current value
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+
+        if c == '\n' {
+            self.line += 1;
+        }
+
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(&format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.error(&format!("expected '{}', found the end", expected))),
+        }
+    }
+
+    /// skips spaces and `//` comments, which plain JSON lacks but hand
+    /// written scenes want
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.next();
+            } else if c == '/' && self.chars.get(self.position + 1) == Some(&'/') {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.next();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        let line = self.line;
+
+        if self.depth >= MAX_DEPTH && matches!(self.peek(), Some('{') | Some('[')) {
+            return Err(self.error("nested too deeply"));
+        }
+
+        let value = match self.peek() {
+            Some('{') => {
+                self.depth += 1;
+                let object = self.object()?;
+                self.depth -= 1;
+                object
+            }
+            Some('[') => {
+                self.depth += 1;
+                let array = self.array()?;
+                self.depth -= 1;
+                array
+            }
+            Some('"') => Value::String(self.string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+            Some(c) if c.is_alphabetic() => match self.word().as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                word => return Err(self.error(&format!("unexpected '{}'", word))),
+            },
+            Some(c) => return Err(self.error(&format!("unexpected '{}'", c))),
+            None => return Err(self.error("unexpected end of file")),
+        };
+
+        Ok(Json { line, value })
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut members: Vec<(String, Json)> = vec![];
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() == Some('}') && members.is_empty() {
+                self.next();
+                return Ok(Value::Object(members));
+            }
+
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a quoted name"));
+            }
+
+            let name = self.string()?;
+
+            if members.iter().any(|(existing, _)| *existing == name) {
+                return Err(self.error(&format!("\"{}\" is given twice", name)));
+            }
+
+            self.expect(':')?;
+            members.push((name, self.value()?));
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() == Some(']') && items.is_empty() {
+                self.next();
+                return Ok(Value::Array(items));
+            }
+
+            items.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => s.push(self.unicode_escape()?),
+                    Some(c) if c == '"' || c == '\\' || c == '/' => s.push(c),
+                    _ => return Err(self.error("bad escape in string")),
+                },
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// the character after a `\u`, joining UTF-16 surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+
+            let low = self.hex4()?;
+
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        std::char::from_u32(code).ok_or_else(|| self.error("bad \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("bad \\u escape"))?;
+            self.next();
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            self.next();
+        }
+
+        let text = self.chars[start..self.position].iter().collect::<String>();
+
+        text.parse()
+            .map(Value::Number)
+            .map_err(|_| self.error(&format!("bad number '{}'", text)))
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.position;
+
+        while self.peek().is_some_and(char::is_alphanumeric) {
+            self.next();
+        }
+
+        self.chars[start..self.position].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_nested_values() {
+        let json = parse("{\"a\": [1, -2.5e1, true, null], \"b\": {\"c\": \"d\\n\"}}").unwrap();
+        let members = json.as_object().unwrap();
+
+        assert_eq!(members[0].0, "a");
+        let a = members[0].1.as_array().unwrap();
+        assert_eq!(a[1].as_f32(), Ok(-25.0));
+        assert_eq!(a[2].as_bool(), Ok(true));
+        assert_eq!(a[3].value, Value::Null);
+
+        let b = members[1].1.as_object().unwrap();
+        assert_eq!(b[0].1.as_str(), Ok("d\n"));
+    }
+
+    #[test]
+    fn values_know_their_line() {
+        let json = parse("{\n  // a comment\n  \"a\": [\n    1,\n    2\n  ]\n}").unwrap();
+        let a = &json.as_object().unwrap()[0].1;
+
+        assert_eq!(a.line, 3);
+        assert_eq!(a.as_array().unwrap()[1].line, 5);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            parse("{\n  \"a\": 1\n  \"b\": 2\n}"),
+            Err("line 3: expected ',' or '}'".to_string())
+        );
+        assert_eq!(
+            parse("[1,\n2,\nfalsey]"),
+            Err("line 3: unexpected 'falsey'".to_string())
+        );
+    }
+
+    #[test]
+    fn escapes_and_unicode_in_strings() {
+        assert_eq!(
+            parse(r#""tab\t \"q\" \/ \u00e9 é \ud83d\ude00""#)
+                .unwrap()
+                .as_str(),
+            Ok("tab\t \"q\" / é é 😀")
+        );
+
+        for bad in &[
+            r#""\x""#,
+            r#""\u12""#,
+            r#""\u12g4""#,
+            r#""\ud83d""#,
+            r#""\ud83d\u0041""#,
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        for (text, error) in &[
+            ("", "line 1: unexpected end of file"),
+            ("[1, 2,]", "line 1: unexpected ']'"),
+            ("{\"a\": 1,}", "line 1: expected a quoted name"),
+            ("{\"a\" 1}", "line 1: expected ':', found '1'"),
+            ("{\"a\": 1, \"a\": 2}", "line 1: \"a\" is given twice"),
+            ("[1] [2]", "line 1: unexpected text after the end"),
+            ("\"open\nstring\"", "line 2: unterminated string"),
+            ("[1.2.3]", "line 1: bad number '1.2.3'"),
+            ("{\"a\": [}", "line 1: unexpected '}'"),
+        ] {
+            assert_eq!(parse(text), Err(error.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(100_000)),
+            Err("line 1: nested too deeply".to_string())
+        );
+    }
+
+    #[test]
+    fn quoting_round_trips() {
+        let s = "say \"hi\"\\\n";
+        let json = parse(&quote(s)).unwrap();
+
+        assert_eq!(json.as_str(), Ok(s));
+    }
+}
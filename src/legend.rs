@@ -0,0 +1,164 @@
+use crate::canvas::Canvas;
+use crate::material::Color;
+use nalgebra::Vector3;
+
+/// A key for an analysis coloring: a column of swatches with labels, drawn
+/// in the bottom left corner of the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Legend {
+    pub entries: Vec<(Color, String)>,
+}
+
+impl Legend {
+    pub(crate) fn draw(&self, canvas: &mut Canvas) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        // glyphs are 3x5 font pixels; scale them up for larger images
+        let scale = (canvas.height() / 200).max(1);
+        let glyph = 4 * scale;
+        let line = 7 * scale;
+        let margin = 2 * scale;
+
+        let longest = self
+            .entries
+            .iter()
+            .map(|(_, label)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let width = margin * 3 + line + longest * glyph;
+        let height = margin * 2 + self.entries.len() * line;
+
+        let left = margin;
+        let top = match canvas.height().checked_sub(height + margin) {
+            Some(top) => top,
+            None => return,
+        };
+
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let black = Vector3::new(0.0, 0.0, 0.0);
+
+        fill(canvas, left, top, width, height, white);
+
+        for (i, (color, label)) in self.entries.iter().enumerate() {
+            let y = top + margin + i * line;
+
+            fill(canvas, left + margin, y, 5 * scale, 5 * scale, *color);
+
+            for (j, c) in label.chars().enumerate() {
+                let x = left + margin * 2 + line + j * glyph;
+
+                for (row, bits) in glyph_rows(c).iter().enumerate() {
+                    for column in 0..3 {
+                        if bits & (0b100 >> column) != 0 {
+                            fill(
+                                canvas,
+                                x + column * scale,
+                                y + row * scale,
+                                scale,
+                                scale,
+                                black,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// paints an opaque rectangle, clipped to the canvas
+fn fill(canvas: &mut Canvas, x: usize, y: usize, width: usize, height: usize, color: Color) {
+    for py in y..(y + height).min(canvas.height()) {
+        for px in x..(x + width).min(canvas.width()) {
+            canvas.write_pixel(px, py, color);
+            canvas.write_alpha(px, py, 1.0);
+        }
+    }
+}
+
+/// the rows of a 3x5 bitmap font, most significant bit on the left;
+/// letters are all capitals and anything unknown is a blank
+fn glyph_rows(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '±' => [0b010, 0b111, 0b010, 0b000, 0b111],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '°' => [0b010, 0b101, 0b010, 0b000, 0b000],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        _ => [0; 5],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_legend_is_drawn_in_the_bottom_left_corner() {
+        let red = Vector3::new(1.0, 0.0, 0.0);
+        let legend = Legend {
+            entries: vec![(red, ">45°".to_string())],
+        };
+
+        let mut canvas = Canvas::new(100, 100);
+        legend.draw(&mut canvas);
+
+        // the swatch sits one margin inside the panel
+        assert_eq!(canvas.pixel_at(4, 100 - 2 - 2 - 7 + 1), red);
+        assert_eq!(canvas.pixel_at(2, 100 - 3), Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.pixel_at(50, 10), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn a_legend_too_tall_for_the_image_is_left_out() {
+        let legend = Legend {
+            entries: vec![(Vector3::new(1.0, 0.0, 0.0), "1".to_string()); 10],
+        };
+
+        let mut canvas = Canvas::new(20, 20);
+        legend.draw(&mut canvas);
+        assert_eq!(canvas.pixel_at(4, 10), Vector3::new(0.0, 0.0, 0.0));
+    }
+}
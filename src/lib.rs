@@ -1,28 +1,88 @@
+mod adjacency;
+mod aov;
 mod bounding_box;
+mod bump;
+mod bvh;
 mod camera;
 mod canvas;
+mod clip;
+mod cone;
+mod csg;
 mod cube;
+mod cylinder;
+mod disk;
 mod dyn_group;
+mod emitter;
+mod exr;
+mod gbuffer;
 mod group;
+mod instance;
 mod intersection;
+mod json;
+mod legend;
 mod light;
 mod material;
+mod noise;
 mod normal;
+mod obj;
+mod outline;
+mod part;
+mod pattern;
+mod pbr;
 mod plane;
+mod ply;
+mod ramp;
 mod ray;
+mod rectangle;
+mod scene_file;
 mod shape;
 mod sphere;
+mod stats;
+mod svg;
+mod texture;
+mod torus;
 mod triangle;
+mod validate;
+mod visualize;
+mod wireframe;
 mod world;
+mod yaml;
+mod yaml_scene;
 
+use adjacency::Adjacency;
+pub use aov::{Aov, Passes};
+pub use bump::{Bump, BumpKind, NormalMap};
+use bvh::Bvh;
 use camera::Camera;
+pub use clip::{Cap, ClipPlane, Hatch};
+pub use csg::CsgOperation;
 use group::Group;
-use material::Material;
+pub use legend::Legend;
+pub use material::{Material, ShadowCatcher};
 use nalgebra::{Point3, Vector3};
+pub use outline::Outline;
+pub use part::Part;
+pub use pattern::{Pattern, PatternKind};
+pub use pbr::{Pbr, Preset};
+use plane::Plane;
+pub use ramp::ColorRamp;
+pub use scene_file::{
+    CameraSpec, LightSpec, MaterialSpec, ObjectSpec, RenderSettings, SceneFile, ShapeSpec,
+    TransformStep,
+};
 use shape::Shape;
+pub use stats::MeshStats;
+use std::io::BufRead;
+use std::sync::Arc;
+pub use svg::{Stroke, SvgStyle};
+pub use texture::{Filter, Texture, UvMapping, Wrap};
 use triangle::Triangle;
+pub use validate::ValidationReport;
+pub use visualize::{LayerColors, Visualization};
+pub use wireframe::Wireframe;
 use world::World;
 
+#[derive(Clone)]
 pub struct Options {
     pub width_pixels: usize,
     pub height_pixels: usize,
@@ -31,6 +91,38 @@ pub struct Options {
     pub up: Vector3<f32>,
     pub fov_radians: f32,
     pub material_color: Vector3<f32>,
+    /// shade the mesh with a physically based preset instead of `material_color`
+    pub material_preset: Option<Preset>,
+    /// pattern or image texture for the mesh, overriding its color
+    pub material_pattern: Option<Pattern>,
+    /// tangent-space normal map for meshes with texture coordinates
+    pub material_normal_map: Option<NormalMap>,
+    /// procedural surface finish, e.g. knurling or brushing
+    pub material_bump: Option<Bump>,
+    /// makes the mesh a light source, e.g. an LED panel
+    pub material_emissive: Vector3<f32>,
+    pub emission_strength: f32,
+    /// shadow rays per pixel spent sampling emissive meshes
+    pub light_samples: usize,
+    /// pattern for the floor plane, e.g. a checker to show perspective and scale
+    pub floor_pattern: Option<Pattern>,
+    /// replaces the floor with a shadow catcher touching the bottom of the mesh
+    pub shadow_catcher: bool,
+    pub background_color: Vector3<f32>,
+    /// writes PNGs with an alpha channel where the background would be
+    pub transparent_background: bool,
+    /// draws silhouettes, creases and open edges as lines over the image
+    pub outline: Option<Outline>,
+    /// draws the mesh's triangle edges, over the shading or on their own
+    pub wireframe: Option<Wireframe>,
+    /// cut the mesh open to show its insides; the floor stays whole
+    pub clip_planes: Vec<ClipPlane>,
+    /// fills the faces cut by `clip_planes`
+    pub cap: Cap,
+    /// colors the mesh by an analysis, e.g. overhangs, wall thickness or print layers
+    pub visualization: Option<Visualization>,
+    /// draws the visualization's key in the bottom left corner
+    pub visualization_legend: bool,
     pub image_format: image::ImageFormat,
 }
 
@@ -39,11 +131,28 @@ impl Default for Options {
         Self {
             width_pixels: 400,
             height_pixels: 400,
-            from: Point3::new(0.0, -2.5, -10.0),
-            to: Point3::new(0.0, -5.0, 0.0),
+            from: Point3::new(0.0, 4.85, 9.1),
+            to: Point3::new(0.0, 2.35, -0.9),
             up: Vector3::new(0.0, 1.0, 0.0),
             fov_radians: std::f32::consts::FRAC_PI_2,
             material_color: Vector3::new(0.0196, 0.65, 0.874),
+            material_preset: None,
+            material_pattern: None,
+            material_normal_map: None,
+            material_bump: None,
+            material_emissive: Vector3::new(0.0, 0.0, 0.0),
+            emission_strength: 1.0,
+            light_samples: 16,
+            floor_pattern: None,
+            shadow_catcher: false,
+            background_color: Vector3::new(0.0, 0.0, 0.0),
+            transparent_background: false,
+            outline: None,
+            wireframe: None,
+            clip_planes: vec![],
+            cap: Cap::default(),
+            visualization: None,
+            visualization_legend: true,
             image_format: image::ImageFormat::Png,
         }
     }
@@ -54,26 +163,268 @@ pub struct Scene {
 }
 
 pub fn render(mesh: &nom_stl::Mesh, options: &Options) -> Result<Vec<u8>, String> {
-    let mut material = Material::new();
+    render_triangles(stl_triangles(mesh), options)
+}
+
+/// draws the visible silhouettes, creases and open edges of an STL mesh as
+/// an SVG document, using the camera and scene set up by `options`
+pub fn render_svg(
+    mesh: &nom_stl::Mesh,
+    options: &Options,
+    style: &SvgStyle,
+) -> Result<String, String> {
+    let triangles = stl_triangles(mesh);
+    let adjacency = Adjacency::new(&triangles);
+    let (world, camera) = scene(vec![triangles], options);
+
+    Ok(svg::export_svg(&camera, &world, &adjacency, style))
+}
+
+/// renders `scan` colored by its signed distance to `reference`: within
+/// `tolerance` of it in green, and from blue `range` inside to red `range`
+/// outside beyond that
+pub fn render_deviation(
+    scan: &nom_stl::Mesh,
+    reference: &nom_stl::Mesh,
+    tolerance: f32,
+    range: f32,
+    options: &Options,
+) -> Result<Vec<u8>, String> {
+    let reference = Bvh::new(&stl_triangles(reference));
+
+    let options = Options {
+        visualization: Some(Visualization::deviation(reference, tolerance, range)),
+        ..options.clone()
+    };
+
+    render(scan, &options)
+}
+
+/// renders a scene file, loading its meshes from paths relative to `base`
+pub fn render_scene(
+    scene: &SceneFile,
+    base: &std::path::Path,
+    format: image::ImageFormat,
+) -> Result<Vec<u8>, String> {
+    let (world, camera) = scene.build(base)?;
+    let (canvas, _) = camera.render_with_buffers(&world);
+
+    canvas.to_image(format)
+}
+
+/// renders a scene in The Ray Tracer Challenge's YAML format, loading OBJ
+/// files from paths relative to `base`
+pub fn render_yaml_scene(
+    yaml: &str,
+    base: &std::path::Path,
+    format: image::ImageFormat,
+) -> Result<Vec<u8>, String> {
+    let (world, camera) = yaml_scene::load(yaml, base)?;
+    let (canvas, _) = camera.render_with_buffers(&world);
+
+    canvas.to_image(format)
+}
+
+/// volume, surface area, extents, center of mass and principal axes of an STL mesh
+pub fn measure(mesh: &nom_stl::Mesh) -> MeshStats {
+    MeshStats::new(&stl_triangles(mesh))
+}
+
+/// checks an STL mesh for holes, non-manifold edges, flipped normals,
+/// degenerate triangles and self-intersections
+pub fn validate(mesh: &nom_stl::Mesh) -> ValidationReport {
+    ValidationReport::new(&stl_triangles(mesh), &stl_normals(mesh))
+}
+
+/// renders an STL mesh with the triangles `validate` finds fault with
+/// painted by their most serious problem, and a legend
+pub fn render_validation(mesh: &nom_stl::Mesh, options: &Options) -> Result<Vec<u8>, String> {
+    let report = validate(mesh);
+
+    let (world, camera) = scene_with(vec![stl_triangles(mesh)], options, |_, index, material| {
+        if let Some(color) = report.highlight(index) {
+            material.set_color(color);
+        }
+    });
+
+    let (mut canvas, _) = camera.render_with_buffers(&world);
+    report.legend().draw(&mut canvas);
 
-    material.color = options.material_color;
+    canvas.to_image(options.image_format)
+}
+
+fn stl_normals(mesh: &nom_stl::Mesh) -> Vec<Vector3<f32>> {
+    mesh.triangles()
+        .iter()
+        .map(|triangle| Vector3::from(triangle.normal()))
+        .collect()
+}
 
-    let triangles = mesh
-        .triangles()
+fn stl_triangles(mesh: &nom_stl::Mesh) -> Vec<Triangle> {
+    mesh.triangles()
         .iter()
         .map(|triangle| {
             let [v1i, v2i, v3i] = triangle.vertices();
 
-            let mut triangle =
-                Triangle::new(Point3::from(v1i), Point3::from(v2i), Point3::from(v3i));
+            Triangle::new(Point3::from(v1i), Point3::from(v2i), Point3::from(v3i))
+        })
+        .collect::<Vec<_>>()
+}
 
-            triangle.material = material;
+/// renders a Wavefront OBJ mesh, keeping its texture coordinates
+pub fn render_obj<R: BufRead>(reader: R, options: &Options) -> Result<Vec<u8>, String> {
+    let triangles = obj::parse_obj(reader)?;
 
-            triangle
-        })
+    render_triangles(triangles, options)
+}
+
+/// renders an ASCII or binary PLY mesh, keeping its texture coordinates
+pub fn render_ply<R: BufRead>(reader: R, options: &Options) -> Result<Vec<u8>, String> {
+    let triangles = ply::parse_ply(reader)?;
+
+    render_triangles(triangles, options)
+}
+
+/// renders an STL mesh once, keeping the depth, normal, ID, position,
+/// albedo and shadow passes alongside the image
+pub fn render_passes(mesh: &nom_stl::Mesh, options: &Options) -> Passes {
+    render_triangle_passes(vec![stl_triangles(mesh)], &[], options, |_, _, _| ())
+}
+
+/// renders several meshes together, each with its own material and
+/// placement, under the camera, lights and floor set up by `options`
+pub fn render_parts(parts: &[Part], options: &Options) -> Result<Vec<u8>, String> {
+    render_part_passes(parts, options).beauty(options.image_format)
+}
+
+/// `render_parts`, keeping the passes and which part covers each pixel
+pub fn render_part_passes(parts: &[Part], options: &Options) -> Passes {
+    let meshes = parts.iter().map(Part::placed_triangles).collect();
+    let names = parts
+        .iter()
+        .map(|part| part.name.clone())
         .collect::<Vec<_>>();
 
-    let mut world = World::default();
+    render_triangle_passes(meshes, &names, options, |mesh, _, material| {
+        let part = &parts[mesh];
+
+        // the options' analysis still applies unless the part brings its own
+        *material = Material {
+            visualization: part
+                .material
+                .visualization
+                .clone()
+                .or_else(|| material.visualization.take()),
+            ..part.material.clone()
+        };
+    })
+}
+
+fn render_triangles(triangles: Vec<Triangle>, options: &Options) -> Result<Vec<u8>, String> {
+    render_triangle_passes(vec![triangles], &[], options, |_, _, _| ()).beauty(options.image_format)
+}
+
+/// renders each of `meshes` as its own object, named by `names` in order
+fn render_triangle_passes(
+    meshes: Vec<Vec<Triangle>>,
+    names: &[String],
+    options: &Options,
+    paint: impl Fn(usize, usize, &mut Material),
+) -> Passes {
+    let mesh = options.outline.map(|_| Adjacency::new(&meshes.concat()));
+    let legend = options
+        .visualization
+        .clone()
+        .filter(|_| options.visualization_legend)
+        .map(|visualization| fit_to_mesh(visualization, &vertices(&meshes)).legend());
+    let (world, camera) = scene_with(meshes, options, paint);
+
+    // the meshes are the last objects in the world
+    let first = world.objects.len() - names.len().min(world.objects.len());
+    let names = names
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, name)| (first + i, name))
+        .collect();
+
+    let (mut canvas, buffers) = camera.render_with_buffers(&world);
+
+    if let Some(wireframe) = &options.wireframe {
+        wireframe.draw(&mut canvas, &buffers, &camera);
+    }
+
+    if let Some(outline) = &options.outline {
+        outline.draw(&mut canvas, &buffers, &camera, mesh.as_ref());
+    }
+
+    if let Some(legend) = legend {
+        legend.draw(&mut canvas);
+    }
+
+    Passes {
+        canvas,
+        buffers,
+        names,
+    }
+}
+
+/// the world around the meshes, lit and shaded as `options` describe, and the camera looking at them
+fn scene(meshes: Vec<Vec<Triangle>>, options: &Options) -> (World, Camera) {
+    scene_with(meshes, options, |_, _, _| ())
+}
+
+/// `scene`, letting `paint` adjust each triangle's material by the index of
+/// its mesh and its index within the mesh
+fn scene_with(
+    mut meshes: Vec<Vec<Triangle>>,
+    options: &Options,
+    paint: impl Fn(usize, usize, &mut Material),
+) -> (World, Camera) {
+    let points = vertices(&meshes);
+
+    let mut material = if let Some(preset) = options.material_preset {
+        Material::from_preset(preset)
+    } else {
+        let mut material = Material::new();
+        material.color = options.material_color;
+        material
+    };
+
+    material.pattern = options.material_pattern.clone().map(Arc::new);
+    material.normal_map = options.material_normal_map.clone();
+    material.bump = options.material_bump;
+    material.emissive = options.material_emissive;
+    material.emission_strength = options.emission_strength;
+    material.visualization = options
+        .visualization
+        .clone()
+        .map(|visualization| fit_to_mesh(visualization, &points));
+
+    for (mesh, triangles) in meshes.iter_mut().enumerate() {
+        for (index, triangle) in triangles.iter_mut().enumerate() {
+            triangle.material = material.clone();
+            paint(mesh, index, &mut triangle.material);
+        }
+    }
+
+    let mut world = if options.shadow_catcher {
+        let mut ground = Plane::under(points.iter().copied(), options.up);
+
+        ground.material.shadow_catcher = Some(ShadowCatcher {
+            occlusion_distance: occlusion_distance(&points),
+            occlusion_samples: options.light_samples,
+            ..Default::default()
+        });
+
+        World::with_floor(ground)
+    } else if let Some(pattern) = &options.floor_pattern {
+        let mut floor = Plane::new();
+        floor.material.pattern = Some(Arc::new(pattern.clone()));
+        World::with_floor(floor)
+    } else {
+        World::default()
+    };
 
     let mut camera = Camera::new(
         options.width_pixels,
@@ -85,11 +436,86 @@ pub fn render(mesh: &nom_stl::Mesh, options: &Options) -> Result<Vec<u8>, String
 
     camera.transform = view_transforms;
 
-    let group = Group::new(triangles);
+    for triangles in meshes {
+        world.objects.push(Box::new(Group::new(triangles)));
+    }
 
-    world.objects.push(Box::new(group));
+    world.light_samples = options.light_samples;
+    world.background = options.background_color;
+    world.transparent_background = options.transparent_background;
+    world.clip_planes = options.clip_planes.clone();
+    world.cap = options.cap;
+    world.collect_emitters();
 
-    let canvas = camera.render(world);
+    (world, camera)
+}
 
-    canvas.to_image(options.image_format)
+/// every corner of every triangle
+fn vertices(meshes: &[Vec<Triangle>]) -> Vec<Point3<f32>> {
+    meshes
+        .iter()
+        .flatten()
+        .flat_map(|t| vec![t.p1, t.p2, t.p3])
+        .collect()
+}
+
+/// rests overhang analysis on the bottom of the mesh and spans layer
+/// previews from its bottom to its top, unless told otherwise
+fn fit_to_mesh(visualization: Visualization, points: &[Point3<f32>]) -> Visualization {
+    match visualization {
+        Visualization::Overhang {
+            build_direction,
+            threshold,
+            bed_height: None,
+        } => Visualization::Overhang {
+            build_direction,
+            threshold,
+            bed_height: heights_along(points, build_direction).map(|(bottom, _)| bottom),
+        },
+        Visualization::Layers {
+            build_direction,
+            layer_height,
+            colors,
+            heights: None,
+        } => Visualization::Layers {
+            build_direction,
+            layer_height,
+            colors,
+            heights: heights_along(points, build_direction),
+        },
+        visualization => visualization,
+    }
+}
+
+/// the lowest and highest points of the mesh along `direction`
+fn heights_along(points: &[Point3<f32>], direction: Vector3<f32>) -> Option<(f32, f32)> {
+    let up = direction.normalize();
+
+    let (bottom, top) = points
+        .iter()
+        .map(|p| p.coords.dot(&up))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(bottom, top), h| {
+            (bottom.min(h), top.max(h))
+        });
+
+    Some((bottom, top)).filter(|(bottom, top)| bottom.is_finite() && top.is_finite())
+}
+
+/// a fifth of the mesh's largest extent, so contact shadows scale with the model
+fn occlusion_distance(points: &[Point3<f32>]) -> f32 {
+    let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+    for point in points {
+        min = min.inf(point);
+        max = max.sup(point);
+    }
+
+    let extent = (max - min).max();
+
+    if extent.is_finite() && extent > 0.0 {
+        extent * 0.2
+    } else {
+        1.0
+    }
 }
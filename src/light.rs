@@ -32,6 +32,17 @@ impl Light {
         normalv: Vector3<f32>,
         in_shadow: bool,
     ) -> Vector3<f32> {
+        if let Some(pbr) = material.pbr {
+            let ambient = pbr.base_color.component_mul(&light.intensity) * material.ambient;
+
+            return if in_shadow {
+                ambient
+            } else {
+                let lightv = (light.position - point).normalize();
+                ambient + pbr.brdf_cos(lightv, eyev, normalv, light.intensity)
+            };
+        }
+
         let effective_color = material.color.component_mul(&light.intensity);
         let lightv = (light.position - point).normalize();
         let ambient = effective_color * material.ambient;
@@ -102,6 +113,30 @@ mod tests {
         assert_eq!(result, Vector3::new(1.0, 1.0, 1.0))
     }
 
+    #[test]
+    fn pbr_lighting_with_the_surface_in_shadow() {
+        let m = Material::from_preset(crate::pbr::Preset::AbsPlastic);
+        let position = Point3::new(0.0, 0.0, 0.0);
+        let eyev = Vector3::new(0.0, 0.0, -1.0);
+        let normalv = Vector3::new(0.0, 0.0, -1.0);
+        let light = Light::point_light(Point3::new(0.0, 0.0, -10.0), Vector3::new(1.0, 1.0, 1.0));
+        let expected = m.color * m.ambient;
+        let result = Light::lighting(m, light, position, eyev, normalv, true);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn pbr_lighting_with_the_eye_between_the_light_and_the_surface() {
+        let m = Material::from_preset(crate::pbr::Preset::AbsPlastic);
+        let position = Point3::new(0.0, 0.0, 0.0);
+        let eyev = Vector3::new(0.0, 0.0, -1.0);
+        let normalv = Vector3::new(0.0, 0.0, -1.0);
+        let light = Light::point_light(Point3::new(0.0, 0.0, -10.0), Vector3::new(1.0, 1.0, 1.0));
+        let lit = Light::lighting(m.clone(), light, position, eyev, normalv, false);
+        let shadowed = Light::lighting(m, light, position, eyev, normalv, true);
+        assert!(lit.x > shadowed.x && lit.y > shadowed.y && lit.z > shadowed.z);
+    }
+
     #[test]
     fn lighting_with_the_eye_opposite_surface_light_offset_45_degrees() {}
 
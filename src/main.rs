@@ -1,30 +1,153 @@
-use racy::Options;
+use racy::{Options, Part};
 
 use std::fs::File;
 use std::io::prelude::*;
 use std::{error::Error, io::BufReader};
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    // --output path names the rendered image
+    let output = match args.iter().position(|arg| arg == "--output") {
+        Some(i) if i + 1 < args.len() => args.drain(i..i + 2).nth(1).unwrap(),
+        Some(_) => return Err("--output needs a path".into()),
+        None => "prism_power.png".to_string(),
+    };
+
+    // --stats prints the mesh's measurements as JSON instead of rendering
+    // it, so takes only one
+    let stats = args.iter().any(|arg| arg == "--stats");
+
+    // a .json path is a whole scene, see `racy::SceneFile`
+    if let Some(path) = args.iter().find(|arg| arg.ends_with(".json")) {
+        let scene = racy::SceneFile::parse(&std::fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let base = std::path::Path::new(path)
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new("."));
+
+        let rendered = racy::render_scene(&scene, base, image::ImageFormat::Png)?;
+        File::create(&output)?.write_all(&rendered)?;
+
+        return Ok(());
+    }
+
+    // as is a .yml or .yaml path, in The Ray Tracer Challenge's format
+    if let Some(path) = args
+        .iter()
+        .find(|arg| arg.ends_with(".yml") || arg.ends_with(".yaml"))
+    {
+        let yaml = std::fs::read_to_string(path)?;
+        let base = std::path::Path::new(path)
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new("."));
+
+        let rendered = racy::render_yaml_scene(&yaml, base, image::ImageFormat::Png)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        File::create(&output)?.write_all(&rendered)?;
+
+        return Ok(());
+    }
+
+    // each STL path may be followed by --name, --color r,g,b,
+    // --translate x,y,z, --rotate x,y,z (degrees) and --scale s for that part
+    let mut meshes = vec![];
+    let mut parts = vec![];
+    let mut args = args.iter().filter(|arg| *arg != "--stats");
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            let file = std::fs::File::open(arg)?;
+            let stl = nom_stl::parse_stl(&mut BufReader::new(file)).map_err(|e| e.to_string())?;
+            let name = std::path::Path::new(arg)
+                .file_stem()
+                .map_or_else(|| arg.clone(), |stem| stem.to_string_lossy().into_owned());
+
+            parts.push(part(&name, &stl));
+            meshes.push(stl);
+            continue;
+        }
+
+        let part = parts
+            .pop()
+            .ok_or_else(|| format!("{} must follow a mesh path", arg))?;
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+
+        parts.push(match arg.as_str() {
+            "--name" => {
+                let mut part = part;
+                part.name = value.clone();
+                part
+            }
+            "--color" => {
+                let [r, g, b] = numbers(arg, value)?;
+                part.with_color([r, g, b].into())
+            }
+            "--translate" => {
+                let [x, y, z] = numbers(arg, value)?;
+                part.translate(x, y, z)
+            }
+            "--rotate" => {
+                let [x, y, z] = numbers(arg, value)?;
+                part.rotate(x.to_radians(), y.to_radians(), z.to_radians())
+            }
+            "--scale" => part.scale(value.parse().map_err(|_| format!("bad {}", arg))?),
+            _ => return Err(format!("unknown option {}", arg).into()),
+        });
+    }
+
     // options.stl_path
     // let file = std::fs::File::open("/Users/Clark/code/nom_stl/fixtures/Root_Vase.stl").unwrap();
     // let file = std::fs::File::open("/home/clark/code/personal/Moon.stl").unwrap();
     // let file = std::fs::File::open("/Users/clark/Downloads/Moon.stl").unwrap();
-    let file =
-        std::fs::File::open("/Users/clark/code/nom_stl/fixtures/MOON_PRISM_POWER.stl").unwrap();
+    if parts.is_empty() {
+        let path = "/Users/clark/code/nom_stl/fixtures/MOON_PRISM_POWER.stl";
+        let file = std::fs::File::open(path)?;
+        let stl = nom_stl::parse_stl(&mut BufReader::new(file)).unwrap();
+
+        parts.push(part("MOON_PRISM_POWER", &stl));
+        meshes.push(stl);
+    }
     // let file = std::fs::File::open("/Users/clark/Downloads/rpi3-top_rev03.stl").unwrap();
 
-    let mut bytes = BufReader::new(file);
+    if stats {
+        if meshes.len() > 1 {
+            return Err("--stats measures one mesh at a time".into());
+        }
+
+        println!("{}", racy::measure(&meshes[0]).to_json());
+        return Ok(());
+    }
 
-    let mut f = File::create("prism_power.png")?;
+    let mut f = File::create(&output)?;
 
     let options = Options {
         image_format: image::ImageFormat::Png,
         ..Default::default()
     };
 
-    let stl = nom_stl::parse_stl(&mut bytes).unwrap();
-    let rendered = racy::render(&stl, &options).unwrap();
+    let rendered = racy::render_parts(&parts, &options)?;
     f.write_all(&rendered).unwrap();
 
     Ok(())
 }
+
+/// a mesh in the default color, until told otherwise
+fn part(name: &str, stl: &nom_stl::Mesh) -> Part {
+    Part::from_stl(name, stl).with_color(Options::default().material_color)
+}
+
+fn numbers(flag: &str, value: &str) -> Result<[f32; 3], String> {
+    let values = value
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("{} takes three numbers, e.g. 1,2,3", flag))?;
+
+    match values.as_slice() {
+        [x, y, z] => Ok([*x, *y, *z]),
+        _ => Err(format!("{} takes three numbers, e.g. 1,2,3", flag)),
+    }
+}
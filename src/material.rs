@@ -1,20 +1,122 @@
-use nalgebra::Vector3;
+use crate::bump::{Bump, NormalMap};
+use crate::intersection::PreparedComputations;
+use crate::pattern::Pattern;
+use crate::pbr::{Pbr, Preset};
+use crate::shape::Shape;
+use crate::visualize::Visualization;
+use nalgebra::{Point2, Point3, Vector3};
+use std::sync::Arc;
 
 pub type Color = Vector3<f32>;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Material {
     pub color: Color,
     pub ambient: f32,
     pub diffuse: f32,
     pub specular: f32,
     pub shininess: f32,
+    /// when set, direct lighting uses the metallic-roughness model instead of Phong
+    pub pbr: Option<Pbr>,
+    /// overrides `color` (and the PBR base color) when set
+    pub pattern: Option<Arc<Pattern>>,
+    pub normal_map: Option<NormalMap>,
+    pub bump: Option<Bump>,
+    /// light given off by the surface, scaled by `emission_strength`
+    pub emissive: Color,
+    pub emission_strength: f32,
+    /// hides the surface except for the shadows falling on it
+    pub shadow_catcher: Option<ShadowCatcher>,
+    /// colors the surface by an analysis, e.g. overhangs, instead of `color`
+    pub visualization: Option<Visualization>,
+    /// cut by the world's clipping planes
+    pub clippable: bool,
+}
+
+/// A surface that is invisible apart from the shadows and ambient occlusion
+/// cast onto it, for compositing a model over a plain or transparent background.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowCatcher {
+    /// how dark a fully shadowed and occluded point gets, from 0.0 to 1.0
+    pub opacity: f32,
+    /// objects further away than this don't occlude the surface
+    pub occlusion_distance: f32,
+    /// hemisphere rays per hit; 0 leaves only the point light's shadow
+    pub occlusion_samples: usize,
+}
+
+impl Default for ShadowCatcher {
+    fn default() -> Self {
+        ShadowCatcher {
+            opacity: 0.6,
+            occlusion_distance: 1.0,
+            occlusion_samples: 16,
+        }
+    }
 }
 
 impl Material {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn from_preset(preset: Preset) -> Self {
+        let pbr = preset.pbr();
+
+        Material {
+            color: pbr.base_color,
+            pbr: Some(pbr),
+            ..Default::default()
+        }
+    }
+
+    /// this material with its pattern evaluated at the hit point
+    pub fn surface_at(&self, comps: &PreparedComputations) -> Material {
+        let mut material = self.clone();
+
+        if let Some(pattern) = &self.pattern {
+            let color = pattern.pattern_at_shape(comps.object, comps.over_point, comps.uv);
+            material.set_color(color);
+        }
+
+        material
+    }
+
+    /// sets `color` and, for PBR materials, the base color
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+
+        if let Some(pbr) = self.pbr.as_mut() {
+            pbr.base_color = color;
+        }
+    }
+
+    pub fn emission(&self) -> Color {
+        self.emissive * self.emission_strength
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission() != Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    /// applies the normal map, then the bump, to a normal from `Shape::normal_at`
+    pub fn perturb_normal(
+        &self,
+        object: &dyn Shape,
+        point: Point3<f32>,
+        uv: Option<Point2<f32>>,
+        normal: Vector3<f32>,
+    ) -> Vector3<f32> {
+        let normal = match &self.normal_map {
+            Some(normal_map) => normal_map.perturb(object, point, uv, normal),
+            None => normal,
+        };
+
+        match &self.bump {
+            Some(bump) => bump.perturb(object, point, normal),
+            None => normal,
+        }
+    }
 }
 
 impl Default for Material {
@@ -25,6 +127,15 @@ impl Default for Material {
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            pbr: None,
+            pattern: None,
+            normal_map: None,
+            bump: None,
+            emissive: Vector3::new(0.0, 0.0, 0.0),
+            emission_strength: 1.0,
+            shadow_catcher: None,
+            visualization: None,
+            clippable: true,
         }
     }
 }
@@ -41,5 +152,19 @@ mod tests {
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.pbr, None);
+        assert_eq!(m.pattern, None);
+        assert_eq!(m.normal_map, None);
+        assert_eq!(m.bump, None);
+        assert!(!m.is_emissive());
+        assert_eq!(m.shadow_catcher, None);
+    }
+
+    #[test]
+    fn a_material_from_a_preset() {
+        let m = Material::from_preset(Preset::Rubber);
+        let pbr = Preset::Rubber.pbr();
+        assert_eq!(m.pbr, Some(pbr));
+        assert_eq!(m.color, pbr.base_color);
     }
 }
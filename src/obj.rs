@@ -0,0 +1,186 @@
+use crate::triangle::Triangle;
+use nalgebra::{Point2, Point3};
+use std::io::BufRead;
+
+/// Reads the geometry of a Wavefront OBJ file: `v`, `vt` and `f` statements.
+/// Polygons are fan-triangulated; normals, groups and materials are ignored.
+pub fn parse_obj<R: BufRead>(reader: R) -> Result<Vec<Triangle>, String> {
+    let mut vertices: Vec<Point3<f32>> = vec![];
+    let mut uvs: Vec<Point2<f32>> = vec![];
+    let mut triangles = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("{}", e))?;
+        let line_number = i + 1;
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("v") => {
+                let [x, y, z] = parse_floats::<3>(words, line_number)?;
+                vertices.push(Point3::new(x, y, z));
+            }
+            Some("vt") => {
+                let [u, v] = parse_floats::<2>(words, line_number)?;
+                uvs.push(Point2::new(u, v));
+            }
+            Some("f") => {
+                let corners = words
+                    .map(|word| parse_corner(word, vertices.len(), uvs.len(), line_number))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if corners.len() < 3 {
+                    return Err(format!(
+                        "line {}: a face needs at least 3 vertices",
+                        line_number
+                    ));
+                }
+
+                for i in 1..corners.len() - 1 {
+                    let (v1, uv1) = corners[0];
+                    let (v2, uv2) = corners[i];
+                    let (v3, uv3) = corners[i + 1];
+
+                    let triangle = Triangle::new(vertices[v1], vertices[v2], vertices[v3]);
+
+                    let triangle = match (uv1, uv2, uv3) {
+                        (Some(uv1), Some(uv2), Some(uv3)) => {
+                            triangle.with_uvs(uvs[uv1], uvs[uv2], uvs[uv3])
+                        }
+                        _ => triangle,
+                    };
+
+                    triangles.push(triangle);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(triangles)
+}
+
+fn parse_floats<'a, const N: usize>(
+    words: impl Iterator<Item = &'a str>,
+    line_number: usize,
+) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+    let mut words = words;
+
+    for value in values.iter_mut() {
+        let word = words
+            .next()
+            .ok_or_else(|| format!("line {}: expected {} numbers", line_number, N))?;
+
+        *value = word
+            .parse()
+            .map_err(|e| format!("line {}: {}: {}", line_number, word, e))?;
+    }
+
+    Ok(values)
+}
+
+/// `v`, `v/vt`, `v//vn` or `v/vt/vn`, 1-based or negative (relative) indices
+fn parse_corner(
+    word: &str,
+    vertex_count: usize,
+    uv_count: usize,
+    line_number: usize,
+) -> Result<(usize, Option<usize>), String> {
+    let mut parts = word.split('/');
+
+    let vertex = parts
+        .next()
+        .ok_or_else(|| format!("line {}: empty face vertex", line_number))?;
+    let vertex = resolve_index(vertex, vertex_count, line_number)?;
+
+    let uv = match parts.next() {
+        Some(uv) if !uv.is_empty() => Some(resolve_index(uv, uv_count, line_number)?),
+        _ => None,
+    };
+
+    Ok((vertex, uv))
+}
+
+fn resolve_index(index: &str, count: usize, line_number: usize) -> Result<usize, String> {
+    let index: i64 = index
+        .parse()
+        .map_err(|e| format!("line {}: {}: {}", line_number, index, e))?;
+
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        Err(format!(
+            "line {}: index {} out of range ({} defined)",
+            line_number, index, count
+        ))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n";
+        let triangles = parse_obj(gibberish.as_bytes()).unwrap();
+        assert!(triangles.is_empty());
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\nf 1 3 4\n";
+        let triangles = parse_obj(file.as_bytes()).unwrap();
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(triangles[0].p1, Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(triangles[0].p2, Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(triangles[0].p3, Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(triangles[1].p1, Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(triangles[1].p2, Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(triangles[1].p3, Point3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\nf 1 2 3 4 5\n";
+        let triangles = parse_obj(file.as_bytes()).unwrap();
+        assert_eq!(triangles.len(), 3);
+        assert_eq!(triangles[2].p1, Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(triangles[2].p2, Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(triangles[2].p3, Point3::new(0.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn faces_with_texture_coordinates() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\n\
+                    vt 0.5 1\nvt 0 0\nvt 1 0\n\
+                    vn 0 0 -1\n\
+                    f 1/1/1 2/2/1 3/3/1\n\
+                    f -3//1 -2//1 -1//1\n";
+        let triangles = parse_obj(file.as_bytes()).unwrap();
+        assert_eq!(
+            triangles[0].uvs,
+            Some([
+                Point2::new(0.5, 1.0),
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 0.0)
+            ])
+        );
+        assert_eq!(triangles[1].uvs, None);
+        assert_eq!(triangles[1].p1, Point3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn out_of_range_indices_report_the_line() {
+        let file = "v 0 1 0\nv -1 0 0\n\nf 1 2 3\n";
+        let error = parse_obj(file.as_bytes()).err().unwrap();
+        assert!(error.starts_with("line 4:"), "{}", error);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::adjacency::Adjacency;
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::gbuffer::GBuffer;
+use crate::material::Color;
+use nalgebra::{Point3, Vector3};
+
+/// Line-art outlines drawn over a rendered image: silhouettes found in the
+/// geometry buffers, plus the creases, boundaries and silhouettes of a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    pub color: Color,
+    /// in pixels
+    pub width: f32,
+    /// mesh edges where the faces meet at more than this many radians are drawn
+    pub crease_angle: f32,
+    /// relative depth jump, e.g. 0.1 for 10%, that counts as a silhouette
+    pub depth_threshold: f32,
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Outline {
+            color: Vector3::new(0.0, 0.0, 0.0),
+            width: 2.0,
+            crease_angle: 40.0_f32.to_radians(),
+            depth_threshold: 0.1,
+        }
+    }
+}
+
+impl Outline {
+    pub(crate) fn draw(
+        &self,
+        canvas: &mut Canvas,
+        buffers: &GBuffer,
+        camera: &Camera,
+        mesh: Option<&Adjacency>,
+    ) {
+        let mut lines = buffers.silhouettes(self.depth_threshold);
+
+        if let Some(mesh) = mesh {
+            let eye = camera.eye();
+
+            let edges = mesh
+                .boundary_edges()
+                .chain(mesh.crease_edges(self.crease_angle))
+                .chain(mesh.silhouette_edges(eye));
+
+            for edge in edges {
+                let (a, b) = mesh.endpoints(edge);
+                self.trace_edge(a, b, buffers, camera, &mut lines);
+            }
+        }
+
+        let radius = ((self.width - 1.0) / 2.0).max(0.0);
+        let reach = radius.ceil() as isize;
+
+        for y in 0..buffers.height {
+            for x in 0..buffers.width {
+                if !lines[y * buffers.width + x] {
+                    continue;
+                }
+
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let (nx, ny) = (x as isize + dx, y as isize + dy);
+
+                        let inside = nx >= 0
+                            && ny >= 0
+                            && (nx as usize) < buffers.width
+                            && (ny as usize) < buffers.height;
+
+                        if inside && ((dx * dx + dy * dy) as f32) <= radius * radius + 0.5 {
+                            canvas.write_pixel(nx as usize, ny as usize, self.color);
+                            canvas.write_alpha(nx as usize, ny as usize, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// marks the pixels along a mesh edge that aren't hidden behind other surfaces
+    fn trace_edge(
+        &self,
+        a: Point3<f32>,
+        b: Point3<f32>,
+        buffers: &GBuffer,
+        camera: &Camera,
+        lines: &mut [bool],
+    ) {
+        let steps = match (camera.project(a), camera.project(b)) {
+            (Some((ax, ay, _)), Some((bx, by, _))) => {
+                ((bx - ax).abs().max((by - ay).abs()).ceil() as usize).min(4 * buffers.width)
+            }
+            _ => 4 * buffers.width,
+        };
+
+        for step in 0..=steps {
+            let point = a + (b - a) * (step as f32 / steps.max(1) as f32);
+
+            let (px, py, distance) = match camera.project(point) {
+                Some(projected) => projected,
+                None => continue,
+            };
+
+            if px < 0.0 || py < 0.0 {
+                continue;
+            }
+
+            let (x, y) = (px as usize, py as usize);
+
+            if x >= buffers.width || y >= buffers.height {
+                continue;
+            }
+
+            // the edge lies on the surfaces beside it, so compare against the
+            // furthest neighbouring depth with some slack for grazing faces
+            let mut depth: f32 = 0.0;
+
+            for ny in y.saturating_sub(1)..(y + 2).min(buffers.height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(buffers.width) {
+                    depth = depth.max(buffers.depth_at(nx, ny));
+                }
+            }
+
+            let slack = distance * (3.0 * camera.pixel_size).max(0.01);
+
+            if distance <= depth + slack {
+                lines[y * buffers.width + x] = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbuffer::SurfaceSample;
+
+    #[test]
+    fn outlines_are_drawn_at_object_boundaries() {
+        let camera = Camera::new(5, 1, std::f32::consts::FRAC_PI_2);
+        let mut buffers = GBuffer::new(5, 1);
+
+        for x in 0..2 {
+            buffers.write(x, 0, Some(SurfaceSample::at_depth(1, 4.0)));
+        }
+
+        let mut canvas = Canvas::new(5, 1);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+
+        for x in 0..5 {
+            canvas.write_pixel(x, 0, white);
+        }
+
+        let outline = Outline {
+            width: 1.0,
+            ..Default::default()
+        };
+        outline.draw(&mut canvas, &buffers, &camera, None);
+
+        let drawn = (0..5)
+            .map(|x| canvas.pixel_at(x, 0) == outline.color)
+            .collect::<Vec<_>>();
+        assert_eq!(drawn, vec![false, true, false, false, false]);
+    }
+}
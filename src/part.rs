@@ -0,0 +1,122 @@
+use crate::material::Material;
+use crate::triangle::Triangle;
+use nalgebra::{Matrix4, Vector3};
+use std::io::BufRead;
+
+/// One mesh of an assembly, with its own material and placement.
+#[derive(Clone)]
+pub struct Part {
+    pub name: String,
+    pub material: Material,
+    /// places the mesh in the scene, e.g. to lay out parts loaded from
+    /// separate files
+    pub transform: Matrix4<f32>,
+    pub(crate) triangles: Vec<Triangle>,
+}
+
+impl Part {
+    pub fn from_stl(name: &str, mesh: &nom_stl::Mesh) -> Self {
+        Part::new(name, crate::stl_triangles(mesh))
+    }
+
+    pub fn from_obj<R: BufRead>(name: &str, reader: R) -> Result<Self, String> {
+        Ok(Part::new(name, crate::obj::parse_obj(reader)?))
+    }
+
+    pub fn from_ply<R: BufRead>(name: &str, reader: R) -> Result<Self, String> {
+        Ok(Part::new(name, crate::ply::parse_ply(reader)?))
+    }
+
+    fn new(name: &str, triangles: Vec<Triangle>) -> Self {
+        Part {
+            name: name.to_string(),
+            material: Material::new(),
+            transform: Matrix4::identity(),
+            triangles,
+        }
+    }
+
+    pub fn with_color(mut self, color: Vector3<f32>) -> Self {
+        self.material.set_color(color);
+        self
+    }
+
+    pub fn translate(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.transform = Matrix4::new_translation(&Vector3::new(x, y, z)) * self.transform;
+        self
+    }
+
+    /// rotates about the x, then y, then z axis, in radians
+    pub fn rotate(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.transform = Matrix4::from_euler_angles(x, y, z) * self.transform;
+        self
+    }
+
+    pub fn scale(mut self, factor: f32) -> Self {
+        self.transform = Matrix4::new_scaling(factor) * self.transform;
+        self
+    }
+
+    /// the mesh's triangles where `transform` puts them
+    pub(crate) fn placed_triangles(&self) -> Vec<Triangle> {
+        self.triangles
+            .iter()
+            .map(|triangle| triangle.transformed(&self.transform))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    fn part() -> Part {
+        Part::new(
+            "tab",
+            vec![Triangle::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            )],
+        )
+    }
+
+    #[test]
+    fn placing_a_part_applies_its_transforms_in_order() {
+        let placed = part()
+            .scale(2.0)
+            .translate(0.0, 0.0, 5.0)
+            .placed_triangles();
+
+        assert_eq!(placed[0].p2, Point3::new(2.0, 0.0, 5.0));
+        assert_eq!(placed[0].p3, Point3::new(0.0, 2.0, 5.0));
+    }
+
+    #[test]
+    fn a_rotated_part_turns_about_the_origin() {
+        let placed = part()
+            .rotate(0.0, 0.0, std::f32::consts::FRAC_PI_2)
+            .placed_triangles();
+
+        assert!((placed[0].p2 - Point3::new(0.0, 1.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn a_mirrored_part_keeps_its_normals_facing_out() {
+        use crate::shape::Shape;
+
+        let placed = part().scale(-1.0).placed_triangles();
+        let point = Point3::new(-0.25, -0.25, 0.0);
+
+        // the tab faced +z; turned inside out by the mirror it now faces -z
+        assert_eq!(
+            part().triangles[0].outward_normal_at(Point3::new(0.25, 0.25, 0.0)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            placed[0].outward_normal_at(point),
+            Vector3::new(0.0, 0.0, -1.0)
+        );
+    }
+}
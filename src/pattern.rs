@@ -0,0 +1,328 @@
+use crate::material::Color;
+use crate::noise::perlin;
+use crate::shape::Shape;
+use crate::texture::{Texture, UvMapping};
+use nalgebra::{Matrix4, Point2, Point3, Projective3, Transform, Vector3};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub transform: Matrix4<f32>,
+}
+
+/// Every composite pattern is built from sub-patterns, so patterns can be
+/// nested arbitrarily: the leaves are `Solid` colors.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatternKind {
+    Solid(Color),
+    Stripe(Box<Pattern>, Box<Pattern>),
+    Gradient(Box<Pattern>, Box<Pattern>),
+    Ring(Box<Pattern>, Box<Pattern>),
+    Checker(Box<Pattern>, Box<Pattern>),
+    Blend(Box<Pattern>, Box<Pattern>),
+    /// displaces the lookup point by Perlin noise before evaluating `pattern`
+    Jitter {
+        pattern: Box<Pattern>,
+        scale: f32,
+    },
+    Texture {
+        texture: Arc<Texture>,
+        mapping: UvMapping,
+    },
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind) -> Self {
+        Pattern {
+            kind,
+            transform: Matrix4::identity(),
+        }
+    }
+
+    pub fn solid(color: Color) -> Self {
+        Pattern::new(PatternKind::Solid(color))
+    }
+
+    pub fn stripe(a: Pattern, b: Pattern) -> Self {
+        Pattern::new(PatternKind::Stripe(Box::new(a), Box::new(b)))
+    }
+
+    pub fn gradient(a: Pattern, b: Pattern) -> Self {
+        Pattern::new(PatternKind::Gradient(Box::new(a), Box::new(b)))
+    }
+
+    pub fn ring(a: Pattern, b: Pattern) -> Self {
+        Pattern::new(PatternKind::Ring(Box::new(a), Box::new(b)))
+    }
+
+    pub fn checker(a: Pattern, b: Pattern) -> Self {
+        Pattern::new(PatternKind::Checker(Box::new(a), Box::new(b)))
+    }
+
+    pub fn blend(a: Pattern, b: Pattern) -> Self {
+        Pattern::new(PatternKind::Blend(Box::new(a), Box::new(b)))
+    }
+
+    pub fn jitter(pattern: Pattern, scale: f32) -> Self {
+        Pattern::new(PatternKind::Jitter {
+            pattern: Box::new(pattern),
+            scale,
+        })
+    }
+
+    pub fn texture(texture: Arc<Texture>, mapping: UvMapping) -> Self {
+        Pattern::new(PatternKind::Texture { texture, mapping })
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4<f32>) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// `point` is in the space of whatever contains this pattern:
+    /// object space for a material's pattern, the parent's pattern space for
+    /// a nested one.
+    pub fn pattern_at(&self, point: Point3<f32>) -> Color {
+        self.pattern_at_uv(point, None)
+    }
+
+    /// `uv` is the surface's own texture coordinate at the point, if it has one
+    pub fn pattern_at_uv(&self, point: Point3<f32>, uv: Option<Point2<f32>>) -> Color {
+        let transform: Projective3<f32> = Transform::from_matrix_unchecked(self.transform);
+        let point = transform.inverse_transform_point(&point);
+
+        match &self.kind {
+            PatternKind::Solid(color) => *color,
+            PatternKind::Stripe(a, b) => {
+                if point.x.floor() as i64 % 2 == 0 {
+                    a.pattern_at_uv(point, uv)
+                } else {
+                    b.pattern_at_uv(point, uv)
+                }
+            }
+            PatternKind::Gradient(a, b) => {
+                let a = a.pattern_at_uv(point, uv);
+                let b = b.pattern_at_uv(point, uv);
+                a + (b - a) * (point.x - point.x.floor())
+            }
+            PatternKind::Ring(a, b) => {
+                let distance = (point.x * point.x + point.z * point.z).sqrt();
+                if distance.floor() as i64 % 2 == 0 {
+                    a.pattern_at_uv(point, uv)
+                } else {
+                    b.pattern_at_uv(point, uv)
+                }
+            }
+            PatternKind::Checker(a, b) => {
+                let sum = point.x.floor() + point.y.floor() + point.z.floor();
+                if sum as i64 % 2 == 0 {
+                    a.pattern_at_uv(point, uv)
+                } else {
+                    b.pattern_at_uv(point, uv)
+                }
+            }
+            PatternKind::Blend(a, b) => {
+                (a.pattern_at_uv(point, uv) + b.pattern_at_uv(point, uv)) / 2.0
+            }
+            PatternKind::Jitter { pattern, scale } => {
+                // decorrelate the three axes by sampling the noise field at offsets
+                let offset = Vector3::new(
+                    perlin(point),
+                    perlin(point + Vector3::new(31.416, 47.853, 12.793)),
+                    perlin(point + Vector3::new(-19.171, 5.237, 73.561)),
+                );
+                pattern.pattern_at_uv(point + offset * *scale, uv)
+            }
+            PatternKind::Texture { texture, mapping } => texture.sample(mapping.uv_at(point, uv)),
+        }
+    }
+
+    pub fn pattern_at_shape(
+        &self,
+        object: &dyn Shape,
+        world_point: Point3<f32>,
+        uv: Option<Point2<f32>>,
+    ) -> Color {
+        let object_transform: Projective3<f32> =
+            Transform::from_matrix_unchecked(object.transform());
+        let object_point = object_transform.inverse_transform_point(&world_point);
+
+        self.pattern_at_uv(object_point, uv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+
+    fn white() -> Color {
+        Vector3::new(1.0, 1.0, 1.0)
+    }
+
+    fn black() -> Color {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    fn stripes() -> Pattern {
+        Pattern::stripe(Pattern::solid(white()), Pattern::solid(black()))
+    }
+
+    #[test]
+    fn a_stripe_pattern_is_constant_in_y() {
+        let pattern = stripes();
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 1.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 2.0, 0.0)), white());
+    }
+
+    #[test]
+    fn a_stripe_pattern_is_constant_in_z() {
+        let pattern = stripes();
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 1.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 2.0)), white());
+    }
+
+    #[test]
+    fn a_stripe_pattern_alternates_in_x() {
+        let pattern = stripes();
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.9, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(-0.1, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(-1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(-1.1, 0.0, 0.0)), white());
+    }
+
+    #[test]
+    fn stripes_with_an_object_transformation() {
+        let mut object = Sphere::new();
+        object.transform = Matrix4::new_scaling(2.0);
+        let pattern = stripes();
+        let c = pattern.pattern_at_shape(&object, Point3::new(1.5, 0.0, 0.0), None);
+        assert_eq!(c, white());
+    }
+
+    #[test]
+    fn stripes_with_a_pattern_transformation() {
+        let object = Sphere::new();
+        let pattern = stripes().with_transform(Matrix4::new_scaling(2.0));
+        let c = pattern.pattern_at_shape(&object, Point3::new(1.5, 0.0, 0.0), None);
+        assert_eq!(c, white());
+    }
+
+    #[test]
+    fn stripes_with_both_an_object_and_a_pattern_transformation() {
+        let mut object = Sphere::new();
+        object.transform = Matrix4::new_scaling(2.0);
+        let pattern =
+            stripes().with_transform(Matrix4::new_translation(&Vector3::new(0.5, 0.0, 0.0)));
+        let c = pattern.pattern_at_shape(&object, Point3::new(2.5, 0.0, 0.0), None);
+        assert_eq!(c, white());
+    }
+
+    #[test]
+    fn a_gradient_linearly_interpolates_between_colors() {
+        let pattern = Pattern::gradient(Pattern::solid(white()), Pattern::solid(black()));
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.0)), white());
+        assert_eq!(
+            pattern.pattern_at(Point3::new(0.25, 0.0, 0.0)),
+            Vector3::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(Point3::new(0.5, 0.0, 0.0)),
+            Vector3::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.pattern_at(Point3::new(0.75, 0.0, 0.0)),
+            Vector3::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn a_ring_should_extend_in_both_x_and_z() {
+        let pattern = Pattern::ring(Pattern::solid(white()), Pattern::solid(black()));
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 1.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(0.708, 0.0, 0.708)), black());
+    }
+
+    #[test]
+    fn checkers_should_repeat_in_x_y_and_z() {
+        let pattern = Pattern::checker(Pattern::solid(white()), Pattern::solid(black()));
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.99, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(1.01, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.99, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 1.01, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 0.99)), white());
+        assert_eq!(pattern.pattern_at(Point3::new(0.0, 0.0, 1.01)), black());
+    }
+
+    #[test]
+    fn a_blended_pattern_averages_its_sub_patterns() {
+        let horizontal = stripes();
+        let vertical = stripes().with_transform(Matrix4::new_rotation(Vector3::new(
+            0.0,
+            std::f32::consts::FRAC_PI_2,
+            0.0,
+        )));
+        let pattern = Pattern::blend(horizontal, vertical);
+        assert_eq!(
+            pattern.pattern_at(Point3::new(0.5, 0.0, 0.5)),
+            Vector3::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn nested_patterns_are_evaluated_in_the_parents_space() {
+        let red = Vector3::new(1.0, 0.0, 0.0);
+        let inner = Pattern::stripe(Pattern::solid(red), Pattern::solid(black()))
+            .with_transform(Matrix4::new_scaling(0.5));
+        let pattern = Pattern::checker(inner, Pattern::solid(white()));
+        assert_eq!(pattern.pattern_at(Point3::new(0.25, 0.0, 0.0)), red);
+        assert_eq!(pattern.pattern_at(Point3::new(0.75, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Point3::new(1.25, 0.0, 0.0)), white());
+    }
+
+    #[test]
+    fn a_texture_pattern_samples_the_image_through_its_mapping() {
+        let mut texture = Texture::new(
+            2,
+            1,
+            vec![Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0)],
+        )
+        .unwrap();
+        texture.filter = crate::texture::Filter::Nearest;
+        let texture = Arc::new(texture);
+
+        let planar = Pattern::texture(texture.clone(), UvMapping::Planar);
+        assert_eq!(
+            planar.pattern_at(Point3::new(0.25, 0.0, 0.5)),
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            planar.pattern_at(Point3::new(0.75, 0.0, 0.5)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+
+        let vertex = Pattern::texture(texture, UvMapping::Vertex);
+        let uv = Some(Point2::new(0.8, 0.5));
+        assert_eq!(
+            vertex.pattern_at_uv(Point3::new(0.25, 0.0, 0.5), uv),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn a_jittered_pattern_still_yields_its_sub_patterns_colors() {
+        let pattern = Pattern::jitter(stripes(), 0.3);
+        for i in 0..100 {
+            let c = pattern.pattern_at(Point3::new(i as f32 * 0.173, 0.4, 0.1));
+            assert!(c == white() || c == black());
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::material::Color;
+use nalgebra::Vector3;
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+/// Metallic-roughness surface description, shaded with a GGX microfacet BRDF
+/// (Trowbridge-Reitz distribution, Smith-Schlick geometry, Schlick Fresnel).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pbr {
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub ior: f32,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Pbr {
+            base_color: Vector3::new(1.0, 1.0, 1.0),
+            metallic: 0.0,
+            roughness: 0.5,
+            ior: 1.5,
+        }
+    }
+}
+
+impl Pbr {
+    /// reflectance at normal incidence: derived from `ior` for dielectrics,
+    /// tinted by `base_color` as the surface becomes metallic
+    pub fn f0(&self) -> Color {
+        let dielectric = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        let dielectric = Vector3::new(dielectric, dielectric, dielectric);
+
+        dielectric.lerp(&self.base_color, self.metallic)
+    }
+
+    /// Outgoing radiance towards `eyev` for light arriving along `lightv`,
+    /// already multiplied by the cosine term. Light intensity is treated as
+    /// irradiance at normal incidence, so a white lambertian surface lit head-on
+    /// returns `intensity`, matching the Phong model's scale.
+    pub fn brdf_cos(
+        &self,
+        lightv: Vector3<f32>,
+        eyev: Vector3<f32>,
+        normalv: Vector3<f32>,
+        intensity: Vector3<f32>,
+    ) -> Color {
+        let n_dot_l = normalv.dot(&lightv);
+
+        if n_dot_l <= 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let n_dot_v = normalv.dot(&eyev).max(1e-4);
+        let halfv = (lightv + eyev).normalize();
+        let n_dot_h = normalv.dot(&halfv).max(0.0);
+        let v_dot_h = eyev.dot(&halfv).max(0.0);
+
+        let roughness = self.roughness.clamp(0.045, 1.0);
+        let alpha = roughness * roughness;
+
+        let d = distribution_ggx(n_dot_h, alpha);
+        let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+        let f = fresnel_schlick(v_dot_h, self.f0());
+
+        let specular = f * (d * g / (4.0 * n_dot_v * n_dot_l));
+
+        let ones = Vector3::new(1.0, 1.0, 1.0);
+        let kd = (ones - f) * (1.0 - self.metallic);
+        let diffuse = kd.component_mul(&self.base_color) / PI;
+
+        (diffuse + specular).component_mul(&intensity) * n_dot_l * PI
+    }
+}
+
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+    a2 / (PI * denom * denom)
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0).powi(2) / 8.0;
+    let g1 = |x: f32| x / (x * (1.0 - k) + k);
+
+    g1(n_dot_v) * g1(n_dot_l)
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: Color) -> Color {
+    let ones = Vector3::new(1.0, 1.0, 1.0);
+
+    f0 + (ones - f0) * (1.0 - cos_theta).powi(5)
+}
+
+/// Named materials for the parts we usually render.
+/// Glass is shaded as a smooth dielectric: there is no transmission.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    BrushedAluminium,
+    AbsPlastic,
+    Pla,
+    Rubber,
+    Glass,
+}
+
+impl Preset {
+    pub fn pbr(self) -> Pbr {
+        match self {
+            Preset::BrushedAluminium => Pbr {
+                base_color: Vector3::new(0.91, 0.92, 0.92),
+                metallic: 1.0,
+                roughness: 0.35,
+                ior: 1.5,
+            },
+            Preset::AbsPlastic => Pbr {
+                base_color: Vector3::new(0.85, 0.83, 0.78),
+                metallic: 0.0,
+                roughness: 0.45,
+                ior: 1.53,
+            },
+            Preset::Pla => Pbr {
+                base_color: Vector3::new(0.9, 0.9, 0.9),
+                metallic: 0.0,
+                roughness: 0.3,
+                ior: 1.46,
+            },
+            Preset::Rubber => Pbr {
+                base_color: Vector3::new(0.05, 0.05, 0.05),
+                metallic: 0.0,
+                roughness: 0.9,
+                ior: 1.52,
+            },
+            Preset::Glass => Pbr {
+                base_color: Vector3::new(0.02, 0.02, 0.02),
+                metallic: 0.0,
+                roughness: 0.05,
+                ior: 1.5,
+            },
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "brushed-aluminium" | "brushed_aluminium" => Ok(Preset::BrushedAluminium),
+            "abs" | "abs-plastic" | "abs_plastic" => Ok(Preset::AbsPlastic),
+            "pla" => Ok(Preset::Pla),
+            "rubber" => Ok(Preset::Rubber),
+            "glass" => Ok(Preset::Glass),
+            _ => Err(format!("unknown material preset: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx_eq(a: Color, b: Color) -> bool {
+        (a - b).norm() < 0.0001
+    }
+
+    #[test]
+    fn a_dielectric_reflects_four_percent_at_normal_incidence() {
+        let pbr = Pbr::default();
+        assert!(approx_eq(pbr.f0(), Vector3::new(0.04, 0.04, 0.04)));
+    }
+
+    #[test]
+    fn a_metal_reflects_its_base_color_at_normal_incidence() {
+        let pbr = Preset::BrushedAluminium.pbr();
+        assert!(approx_eq(pbr.f0(), pbr.base_color));
+    }
+
+    #[test]
+    fn no_light_reaches_a_surface_facing_away() {
+        let pbr = Pbr::default();
+        let normalv = Vector3::new(0.0, 0.0, -1.0);
+        let eyev = Vector3::new(0.0, 0.0, -1.0);
+        let lightv = Vector3::new(0.0, 0.0, 1.0);
+        let result = pbr.brdf_cos(lightv, eyev, normalv, Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(result, Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn a_black_metal_has_no_diffuse_response() {
+        let pbr = Pbr {
+            base_color: Vector3::new(0.0, 0.0, 0.0),
+            metallic: 1.0,
+            ..Default::default()
+        };
+        let normalv = Vector3::new(0.0, 0.0, -1.0);
+        let eyev = Vector3::new(0.0, 0.0, -1.0);
+        let result = pbr.brdf_cos(eyev, eyev, normalv, Vector3::new(1.0, 1.0, 1.0));
+        assert!(approx_eq(result, Vector3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn rougher_surfaces_have_dimmer_highlights() {
+        let normalv = Vector3::new(0.0, 0.0, -1.0);
+        let eyev = Vector3::new(0.0, 0.0, -1.0);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let smooth = Pbr {
+            roughness: 0.1,
+            ..Default::default()
+        };
+        let rough = Pbr {
+            roughness: 0.9,
+            ..Default::default()
+        };
+        let smooth = smooth.brdf_cos(eyev, eyev, normalv, white);
+        let rough = rough.brdf_cos(eyev, eyev, normalv, white);
+        assert!(smooth.x > rough.x);
+    }
+
+    #[test]
+    fn presets_can_be_parsed_by_name() {
+        assert_eq!("pla".parse::<Preset>(), Ok(Preset::Pla));
+        assert_eq!(
+            "brushed-aluminium".parse::<Preset>(),
+            Ok(Preset::BrushedAluminium)
+        );
+        assert!("unobtainium".parse::<Preset>().is_err());
+    }
+}
@@ -2,13 +2,14 @@ use crate::bounding_box::{BoundingBox, AABB};
 use crate::intersection::Intersection;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::shape::Shape;
-use nalgebra::{Matrix4, Point3, Projective3, Transform, Vector3};
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point3, Rotation3, Vector3};
+use std::f32::consts::PI;
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Plane {
-    transform: Matrix4<f32>,
-    material: Material,
+    pub transform: Matrix4<f32>,
+    pub material: Material,
     bounding_box: AABB,
 }
 
@@ -20,6 +21,28 @@ impl Plane {
             bounding_box: AABB::default(),
         }
     }
+
+    /// a plane facing `up` that touches the lowest of `points`, so a model
+    /// rests on it instead of floating above or sinking through it
+    pub fn under(points: impl IntoIterator<Item = Point3<f32>>, up: Vector3<f32>) -> Self {
+        let up = up.normalize();
+
+        let lowest = points
+            .into_iter()
+            .map(|point| point.coords.dot(&up))
+            .fold(f32::INFINITY, f32::min);
+
+        let height = if lowest.is_finite() { lowest } else { 0.0 };
+
+        let rotation = Rotation3::rotation_between(&Vector3::y(), &up)
+            .unwrap_or_else(|| Rotation3::from_axis_angle(&Vector3::x_axis(), PI));
+
+        let mut plane = Plane::new();
+
+        plane.transform = Matrix4::new_translation(&(up * height)) * rotation.to_homogeneous();
+
+        plane
+    }
 }
 
 impl BoundingBox for Plane {
@@ -29,13 +52,17 @@ impl BoundingBox for Plane {
 }
 
 impl Shape for Plane {
-    fn material(&self) -> Material {
-        self.material
+    fn material(&self) -> &Material {
+        &self.material
     }
     fn transform(&self) -> Matrix4<f32> {
         self.transform
     }
 
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
         if ray.direction.y.abs() < 0.00001 {
             vec![]
@@ -46,23 +73,56 @@ impl Shape for Plane {
     }
 
     fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
-        let transformed_transform: Projective3<f32> =
-            Transform::from_matrix_unchecked(self.transform);
-        let local_point: Point3<f32> = transformed_transform.inverse_transform_point(&point);
-        let local_normal = self.local_normal_at(local_point);
-        let transposed_transform: Projective3<f32> =
-            Transform::from_matrix_unchecked(self.transform.transpose());
-        let world_normal = transposed_transform.inverse_transform_vector(&local_normal);
-        let mut world_normal_homogeneous = world_normal.to_homogeneous();
-
-        world_normal_homogeneous.w = 0.0;
-
-        Vector3::from_homogeneous(world_normal_homogeneous)
-            .unwrap()
-            .normalize()
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
     }
 
     fn local_normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
         Vector3::new(0.0, 1.0, 0.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx_eq(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+        (a - b).norm() < 0.0001
+    }
+
+    #[test]
+    fn the_normal_of_a_plane_is_constant_everywhere() {
+        let p = Plane::new();
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        assert_eq!(p.local_normal_at(Point3::new(0.0, 0.0, 0.0)), n);
+        assert_eq!(p.local_normal_at(Point3::new(10.0, 0.0, -10.0)), n);
+        assert_eq!(p.local_normal_at(Point3::new(-5.0, 0.0, 150.0)), n);
+    }
+
+    #[test]
+    fn a_plane_under_points_touches_the_lowest_one() {
+        let points = vec![
+            Point3::new(0.0, 3.0, 0.0),
+            Point3::new(1.0, -2.0, 5.0),
+            Point3::new(4.0, 1.0, -1.0),
+        ];
+        let p = Plane::under(points, Vector3::new(0.0, 1.0, 0.0));
+        let r = Ray::new(Point3::new(1.0, 10.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+        let xs = p.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 12.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_plane_under_points_faces_up() {
+        let points = vec![Point3::new(2.0, 0.0, 0.0), Point3::new(-3.0, 0.0, 1.0)];
+        let up = Vector3::new(1.0, 0.0, 0.0);
+        let p = Plane::under(points, up);
+        assert!(approx_eq(p.normal_at(Point3::new(-3.0, 7.0, 2.0)), up));
+
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        let p = Plane::under(vec![Point3::new(0.0, 4.0, 0.0)], down);
+        assert!(approx_eq(p.normal_at(Point3::new(1.0, 4.0, 1.0)), down));
+    }
+}
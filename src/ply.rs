@@ -0,0 +1,357 @@
+use crate::triangle::Triangle;
+use nalgebra::{Point2, Point3};
+use std::convert::TryInto;
+use std::io::BufRead;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads vertices (with optional `u`/`v`, `s`/`t` or `texture_u`/`texture_v`
+/// coordinates) and faces from an ASCII or binary PLY file.
+/// Polygons are fan-triangulated; any other elements are skipped.
+pub fn parse_ply<R: BufRead>(mut reader: R) -> Result<Vec<Triangle>, String> {
+    let (format, elements) = parse_header(&mut reader)?;
+
+    let mut body = Body::new(reader, format);
+    let mut vertices = vec![];
+    let mut uvs = vec![];
+    let mut triangles = vec![];
+
+    for element in elements.iter() {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut uv = [None, None];
+            let mut indices = vec![];
+
+            for property in element.properties.iter() {
+                match property {
+                    Property::Scalar(name, scalar) => {
+                        let value = body.read(*scalar)?;
+
+                        match name.as_str() {
+                            "x" => position[0] = value as f32,
+                            "y" => position[1] = value as f32,
+                            "z" => position[2] = value as f32,
+                            "u" | "s" | "texture_u" | "texture_s" => uv[0] = Some(value as f32),
+                            "v" | "t" | "texture_v" | "texture_t" => uv[1] = Some(value as f32),
+                            _ => (),
+                        }
+                    }
+                    Property::List(name, count_type, item_type) => {
+                        let count = body.read(*count_type)? as usize;
+                        let is_indices = name == "vertex_indices" || name == "vertex_index";
+
+                        for _ in 0..count {
+                            let value = body.read(*item_type)?;
+
+                            if is_indices {
+                                indices.push(value as usize);
+                            }
+                        }
+                    }
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    vertices.push(Point3::from(position));
+
+                    if let [Some(u), Some(v)] = uv {
+                        uvs.push(Point2::new(u, v));
+                    }
+                }
+                "face" => {
+                    for i in 1..indices.len().saturating_sub(1) {
+                        let corners = [indices[0], indices[i], indices[i + 1]];
+
+                        if let Some(&bad) = corners.iter().find(|&&c| c >= vertices.len()) {
+                            return Err(format!(
+                                "face refers to vertex {} but only {} are defined",
+                                bad,
+                                vertices.len()
+                            ));
+                        }
+
+                        let [a, b, c] = corners;
+                        let triangle = Triangle::new(vertices[a], vertices[b], vertices[c]);
+
+                        let triangle = if uvs.len() == vertices.len() {
+                            triangle.with_uvs(uvs[a], uvs[b], uvs[c])
+                        } else {
+                            triangle
+                        };
+
+                        triangles.push(triangle);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    Ok(triangles)
+}
+
+fn parse_header<R: BufRead>(reader: &mut R) -> Result<(Format, Vec<Element>), String> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut line_number = 0;
+
+    loop {
+        let mut line = String::new();
+
+        let read = reader.read_line(&mut line).map_err(|e| format!("{}", e))?;
+
+        line_number += 1;
+
+        if read == 0 {
+            return Err("unexpected end of file in PLY header".to_string());
+        }
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let error = |message: &str| format!("line {}: {}", line_number, message);
+
+        match words.as_slice() {
+            ["ply"] if line_number == 1 => (),
+            _ if line_number == 1 => return Err("not a PLY file".to_string()),
+            ["format", kind, _version] => {
+                format = Some(match *kind {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(error("unknown format")),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error("bad element count"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, item_type, name] => elements
+                .last_mut()
+                .ok_or_else(|| error("property before element"))?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    parse_scalar(count_type).ok_or_else(|| error("unknown type"))?,
+                    parse_scalar(item_type).ok_or_else(|| error("unknown type"))?,
+                )),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or_else(|| error("property before element"))?
+                .properties
+                .push(Property::Scalar(
+                    name.to_string(),
+                    parse_scalar(scalar).ok_or_else(|| error("unknown type"))?,
+                )),
+            ["end_header"] => break,
+            _ => (),
+        }
+    }
+
+    let format = format.ok_or_else(|| "PLY header has no format".to_string())?;
+
+    Ok((format, elements))
+}
+
+fn parse_scalar(name: &str) -> Option<Scalar> {
+    match name {
+        "char" | "int8" => Some(Scalar::I8),
+        "uchar" | "uint8" => Some(Scalar::U8),
+        "short" | "int16" => Some(Scalar::I16),
+        "ushort" | "uint16" => Some(Scalar::U16),
+        "int" | "int32" => Some(Scalar::I32),
+        "uint" | "uint32" => Some(Scalar::U32),
+        "float" | "float32" => Some(Scalar::F32),
+        "double" | "float64" => Some(Scalar::F64),
+        _ => None,
+    }
+}
+
+struct Body<R: BufRead> {
+    reader: R,
+    format: Format,
+    words: std::vec::IntoIter<String>,
+}
+
+impl<R: BufRead> Body<R> {
+    fn new(reader: R, format: Format) -> Self {
+        Body {
+            reader,
+            format,
+            words: vec![].into_iter(),
+        }
+    }
+
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        match self.format {
+            Format::Ascii => {
+                let word = self.next_word()?;
+                word.parse::<f64>()
+                    .map_err(|_| format!("bad number in PLY body: {}", word))
+            }
+            Format::BinaryLittleEndian | Format::BinaryBigEndian => {
+                let size = match scalar {
+                    Scalar::I8 | Scalar::U8 => 1,
+                    Scalar::I16 | Scalar::U16 => 2,
+                    Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+                    Scalar::F64 => 8,
+                };
+
+                let mut bytes = [0u8; 8];
+
+                self.reader
+                    .read_exact(&mut bytes[..size])
+                    .map_err(|e| format!("{}", e))?;
+
+                if self.format == Format::BinaryBigEndian {
+                    bytes[..size].reverse();
+                }
+
+                let b = &bytes[..size];
+
+                Ok(match scalar {
+                    Scalar::I8 => b[0] as i8 as f64,
+                    Scalar::U8 => b[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes(b.try_into().unwrap()) as f64,
+                    Scalar::U16 => u16::from_le_bytes(b.try_into().unwrap()) as f64,
+                    Scalar::I32 => i32::from_le_bytes(b.try_into().unwrap()) as f64,
+                    Scalar::U32 => u32::from_le_bytes(b.try_into().unwrap()) as f64,
+                    Scalar::F32 => f32::from_le_bytes(b.try_into().unwrap()) as f64,
+                    Scalar::F64 => f64::from_le_bytes(b.try_into().unwrap()),
+                })
+            }
+        }
+    }
+
+    fn next_word(&mut self) -> Result<String, String> {
+        loop {
+            if let Some(word) = self.words.next() {
+                return Ok(word);
+            }
+
+            let mut line = String::new();
+
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| format!("{}", e))?;
+
+            if read == 0 {
+                return Err("unexpected end of file in PLY body".to_string());
+            }
+
+            self.words = line
+                .split_whitespace()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment a unit quad
+element vertex 4
+property float x
+property float y
+property float z
+property float u
+property float v
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0
+1 0 0 1 0
+1 1 0 1 1
+0 1 0 0 1
+4 0 1 2 3
+";
+
+    #[test]
+    fn parsing_an_ascii_ply() {
+        let triangles = parse_ply(ASCII.as_bytes()).unwrap();
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(triangles[1].p1, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(triangles[1].p2, Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(triangles[1].p3, Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            triangles[1].uvs,
+            Some([
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 1.0),
+                Point2::new(0.0, 1.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn parsing_a_binary_little_endian_ply() {
+        let mut bytes = b"ply\nformat binary_little_endian 1.0\n\
+                          element vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                          element face 1\nproperty list uchar uint vertex_indices\nend_header\n"
+            .to_vec();
+
+        for v in &[[0.0f32, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]] {
+            for c in v {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+
+        bytes.push(3);
+
+        for i in 0u32..3 {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+
+        let triangles = parse_ply(bytes.as_slice()).unwrap();
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0].p2, Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(triangles[0].uvs, None);
+    }
+
+    #[test]
+    fn rejecting_files_that_are_not_ply() {
+        assert!(parse_ply("solid cube\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejecting_faces_with_missing_vertices() {
+        let file = ASCII.replace("4 0 1 2 3", "3 0 1 7");
+        assert!(parse_ply(file.as_bytes()).is_err());
+    }
+}
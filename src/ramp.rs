@@ -0,0 +1,73 @@
+use crate::material::Color;
+use nalgebra::Vector3;
+
+/// Colors interpolated between evenly spaced stops, for heatmaps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    pub stops: Vec<Color>,
+}
+
+impl ColorRamp {
+    pub fn new(stops: Vec<Color>) -> Self {
+        ColorRamp { stops }
+    }
+
+    /// blue, cyan, green, yellow, red
+    pub fn heat() -> Self {
+        ColorRamp::new(vec![
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        ])
+    }
+
+    /// `t` runs from 0.0 at the first stop to 1.0 at the last and is clamped
+    pub fn at(&self, t: f32) -> Color {
+        match self.stops.len() {
+            0 => Vector3::new(0.0, 0.0, 0.0),
+            1 => self.stops[0],
+            n => {
+                let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+                let position = t * (n - 1) as f32;
+                let i = (position.floor() as usize).min(n - 2);
+                let f = position - i as f32;
+
+                self.stops[i] * (1.0 - f) + self.stops[i + 1] * f
+            }
+        }
+    }
+}
+
+impl Default for ColorRamp {
+    fn default() -> Self {
+        Self::heat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ramp_interpolates_between_stops() {
+        let ramp = ColorRamp::new(vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+        ]);
+        assert_eq!(ramp.at(0.0), Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(ramp.at(0.25), Vector3::new(0.5, 0.0, 0.0));
+        assert_eq!(ramp.at(0.75), Vector3::new(1.0, 0.5, 0.0));
+        assert_eq!(ramp.at(1.0), Vector3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn a_ramp_clamps_out_of_range_values() {
+        let ramp = ColorRamp::heat();
+        assert_eq!(ramp.at(-3.0), ramp.at(0.0));
+        assert_eq!(ramp.at(7.0), ramp.at(1.0));
+        assert_eq!(ramp.at(f32::NAN), ramp.at(0.0));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::bounding_box::{BoundingBox, AABB};
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point2, Point3, Vector3};
+
+/// The square from -1 to 1 in the xz plane facing +y, like a cube's top;
+/// scaled, any rectangle or, transformed, any parallelogram.
+///
+/// Hits carry texture coordinates running from 0 to 1 along +x and +z.
+#[derive(Clone, Debug)]
+pub struct Rectangle {
+    pub transform: Matrix4<f32>,
+    pub material: Material,
+}
+
+impl Default for Rectangle {
+    fn default() -> Self {
+        Self {
+            transform: Matrix4::identity(),
+            material: Material::default(),
+        }
+    }
+}
+
+impl Rectangle {
+    fn corners(&self) -> [Point3<f32>; 4] {
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(x, z)| self.transform.transform_point(&Point3::new(x, 0.0, z)))
+    }
+}
+
+impl BoundingBox for Rectangle {
+    fn bounding_box(&self) -> AABB {
+        AABB {
+            min: Point3::new(-1.0, 0.0, -1.0),
+            max: Point3::new(1.0, 0.0, 1.0),
+        }
+    }
+}
+
+impl Shape for Rectangle {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        if ray.direction.y.abs() < 0.00001 {
+            return vec![];
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+        let point = ray.position(t);
+
+        if point.x.abs() > 1.0 || point.z.abs() > 1.0 {
+            return vec![];
+        }
+
+        vec![Intersection::with_uv(
+            t,
+            self,
+            (point.x + 1.0) / 2.0,
+            (point.z + 1.0) / 2.0,
+        )]
+    }
+
+    fn local_normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+
+    fn uv_at(&self, hit: &Intersection) -> Option<Point2<f32>> {
+        Some(Point2::new(hit.u, hit.v))
+    }
+
+    /// +u and +v follow the rectangle's x and z edges
+    fn tangent_at(&self, _point: Point3<f32>) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        Some((
+            self.transform
+                .transform_vector(&Vector3::x())
+                .try_normalize(1e-8)?,
+            self.transform
+                .transform_vector(&Vector3::z())
+                .try_normalize(1e-8)?,
+        ))
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        if !self.material.is_emissive() {
+            return vec![];
+        }
+
+        let [a, b, c, d] = self.corners();
+
+        vec![
+            Emitter::triangle(a, b, c, self.material.emission()),
+            Emitter::triangle(a, c, d, self.material.emission()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ray_hits_a_rectangle_inside_its_edges() {
+        let rectangle = Rectangle::default();
+        let down = Vector3::new(0.0, -1.0, 0.0);
+
+        let xs = rectangle.local_intersect(Ray::new(Point3::new(0.5, 1.0, -0.5), down));
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 1.0);
+        assert_eq!(rectangle.uv_at(&xs[0]), Some(Point2::new(0.75, 0.25)));
+
+        assert!(rectangle
+            .local_intersect(Ray::new(Point3::new(1.5, 1.0, 0.0), down))
+            .is_empty());
+    }
+
+    #[test]
+    fn an_emissive_rectangle_is_two_triangles_of_its_area() {
+        let rectangle = Rectangle {
+            transform: Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 0.5)),
+            material: Material {
+                emissive: Vector3::new(1.0, 1.0, 1.0),
+                ..Material::default()
+            },
+        };
+
+        let emitters = rectangle.emitters();
+        assert_eq!(emitters.len(), 2);
+        assert!((emitters.iter().map(|e| e.area).sum::<f32>() - 4.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,1249 @@
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::cone::Cone;
+use crate::csg::{Csg, CsgOperation};
+use crate::cube::Cube;
+use crate::cylinder::Cylinder;
+use crate::disk::Disk;
+use crate::dyn_group::DynGroup;
+use crate::instance::Instance;
+use crate::json::{self, Json};
+use crate::light::Light;
+use crate::material::{Color, Material};
+use crate::plane::Plane;
+use crate::rectangle::Rectangle;
+use crate::shape::Shape;
+use crate::sphere::Sphere;
+use crate::torus::Torus;
+use crate::triangle::Triangle;
+use crate::world::World;
+use nalgebra::{Matrix4, Point3, Vector3};
+use std::fmt::Write;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A scene written out as JSON: camera, lights, named materials, objects
+/// and render settings.
+///
+/// ```json
+/// {
+///   "camera": { "width": 400, "height": 200, "field_of_view": 60,
+///               "from": [0, 1.5, -5], "to": [0, 1, 0], "up": [0, 1, 0] },
+///   "lights": [{ "position": [-10, 10, -10], "intensity": [1, 1, 1] },
+///              { "position": [10, 5, -10], "intensity": [0.3, 0.3, 0.3] }],
+///   "materials": { "red": { "color": [1, 0.2, 0.2], "specular": 0.3 } },
+///   "objects": [
+///     { "type": "plane" },
+///     { "type": "sphere", "material": "red",
+///       "transform": [{ "scale": [0.5, 0.5, 0.5] }, { "translate": [0, 0.5, 0] }] },
+///     { "type": "group", "transform": [{ "rotate_y": 30 }], "children": [
+///       { "type": "mesh", "path": "bracket.stl", "material": "red" }
+///     ] },
+///     { "type": "torus", "major_radius": 1, "minor_radius": 0.25 },
+///     { "type": "csg", "operation": "difference",
+///       "left": { "type": "cube" }, "right": { "type": "sphere" } }
+///   ],
+///   "settings": { "background": [0, 0, 0], "light_samples": 16 }
+/// }
+/// ```
+///
+/// Every section and setting may be left out for its default, one light
+/// for the lights. Mesh paths are relative to the scene file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneFile {
+    pub camera: CameraSpec,
+    pub lights: Vec<LightSpec>,
+    /// in the order they were written, referred to by name from objects
+    pub materials: Vec<(String, MaterialSpec)>,
+    pub objects: Vec<ObjectSpec>,
+    pub settings: RenderSettings,
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        SceneFile {
+            camera: CameraSpec::default(),
+            lights: vec![LightSpec::default()],
+            materials: vec![],
+            objects: vec![],
+            settings: RenderSettings::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSpec {
+    pub width: usize,
+    pub height: usize,
+    /// in degrees
+    pub field_of_view: f32,
+    pub from: Point3<f32>,
+    pub to: Point3<f32>,
+    pub up: Vector3<f32>,
+}
+
+impl Default for CameraSpec {
+    fn default() -> Self {
+        CameraSpec {
+            width: 400,
+            height: 400,
+            field_of_view: 90.0,
+            from: Point3::new(0.0, 1.5, -5.0),
+            to: Point3::new(0.0, 1.0, 0.0),
+            up: Vector3::new(0.0, 1.0, 0.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSpec {
+    pub position: Point3<f32>,
+    pub intensity: Color,
+}
+
+impl Default for LightSpec {
+    fn default() -> Self {
+        let light = Light::default();
+
+        LightSpec {
+            position: light.position,
+            intensity: light.intensity,
+        }
+    }
+}
+
+/// The parts of a `Material` a scene file can set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialSpec {
+    pub color: Color,
+    pub ambient: f32,
+    pub diffuse: f32,
+    pub specular: f32,
+    pub shininess: f32,
+    pub emissive: Color,
+    pub emission_strength: f32,
+}
+
+impl Default for MaterialSpec {
+    fn default() -> Self {
+        let material = Material::default();
+
+        MaterialSpec {
+            color: material.color,
+            ambient: material.ambient,
+            diffuse: material.diffuse,
+            specular: material.specular,
+            shininess: material.shininess,
+            emissive: material.emissive,
+            emission_strength: material.emission_strength,
+        }
+    }
+}
+
+impl MaterialSpec {
+    pub fn material(&self) -> Material {
+        Material {
+            color: self.color,
+            ambient: self.ambient,
+            diffuse: self.diffuse,
+            specular: self.specular,
+            shininess: self.shininess,
+            emissive: self.emissive,
+            emission_strength: self.emission_strength,
+            ..Material::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectSpec {
+    pub shape: ShapeSpec,
+    /// the name of one of the scene's materials; objects without one take
+    /// their group's
+    pub material: Option<String>,
+    /// applied in order, each after the ones before it
+    pub transform: Vec<TransformStep>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeSpec {
+    /// the unit sphere
+    Sphere,
+    /// the xz plane
+    Plane,
+    /// the cube from -1 to 1
+    Cube,
+    /// of radius 1 around the y axis, between heights `minimum` and
+    /// `maximum`, which are unbounded if left out, and capped if `closed`
+    Cylinder {
+        minimum: f32,
+        maximum: f32,
+        closed: bool,
+    },
+    /// the double cone x² + z² = y², truncated and capped like a cylinder
+    Cone {
+        minimum: f32,
+        maximum: f32,
+        closed: bool,
+    },
+    /// a ring around the y axis, its tube `minor_radius` thick and centred
+    /// `major_radius` from the axis
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    /// of radius 1 in the xz plane, with a hole of `inner_radius`
+    Disk {
+        inner_radius: f32,
+    },
+    /// the square from -1 to 1 in the xz plane
+    Rectangle,
+    Triangle([Point3<f32>; 3]),
+    /// an STL, OBJ or PLY file
+    Mesh(String),
+    Group(Vec<ObjectSpec>),
+    /// two objects combined, both taking this one's transform and material
+    /// like a group's children
+    Csg(CsgOperation, Box<ObjectSpec>, Box<ObjectSpec>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformStep {
+    Translate(Vector3<f32>),
+    Scale(Vector3<f32>),
+    /// about the x axis, in degrees
+    RotateX(f32),
+    RotateY(f32),
+    RotateZ(f32),
+}
+
+impl TransformStep {
+    fn matrix(&self) -> Matrix4<f32> {
+        match *self {
+            TransformStep::Translate(v) => Matrix4::new_translation(&v),
+            TransformStep::Scale(v) => Matrix4::new_nonuniform_scaling(&v),
+            TransformStep::RotateX(degrees) => {
+                Matrix4::from_euler_angles(degrees.to_radians(), 0.0, 0.0)
+            }
+            TransformStep::RotateY(degrees) => {
+                Matrix4::from_euler_angles(0.0, degrees.to_radians(), 0.0)
+            }
+            TransformStep::RotateZ(degrees) => {
+                Matrix4::from_euler_angles(0.0, 0.0, degrees.to_radians())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    pub background: Color,
+    pub transparent_background: bool,
+    /// shadow rays per hit spent on emissive objects
+    pub light_samples: usize,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            background: Vector3::new(0.0, 0.0, 0.0),
+            transparent_background: false,
+            light_samples: 16,
+        }
+    }
+}
+
+impl SceneFile {
+    /// reads a scene, with the line of the first problem in any error
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let json = json::parse(text)?;
+        let members = members(
+            &json,
+            &["camera", "lights", "materials", "objects", "settings"],
+        )?;
+        let mut scene = SceneFile::default();
+
+        if let Some(camera) = member(members, "camera") {
+            scene.camera = parse_camera(camera)?;
+        }
+
+        if let Some(lights) = member(members, "lights") {
+            scene.lights = lights
+                .as_array()?
+                .iter()
+                .map(parse_light)
+                .collect::<Result<_, _>>()?;
+        }
+
+        if let Some(materials) = member(members, "materials") {
+            for (name, material) in materials.as_object()? {
+                scene
+                    .materials
+                    .push((name.clone(), parse_material(material)?));
+            }
+        }
+
+        if let Some(objects) = member(members, "objects") {
+            for object in objects.as_array()? {
+                scene.objects.push(parse_object(object, &scene.materials)?);
+            }
+        }
+
+        if let Some(settings) = member(members, "settings") {
+            scene.settings = parse_settings(settings)?;
+        }
+
+        Ok(scene)
+    }
+
+    /// the scene as JSON that `parse` reads back unchanged
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let camera = &self.camera;
+        let settings = &self.settings;
+
+        writeln!(json, "{{").unwrap();
+        writeln!(json, "  \"camera\": {{").unwrap();
+        writeln!(json, "    \"width\": {},", camera.width).unwrap();
+        writeln!(json, "    \"height\": {},", camera.height).unwrap();
+        writeln!(json, "    \"field_of_view\": {},", camera.field_of_view).unwrap();
+        writeln!(json, "    \"from\": {},", vector(camera.from.coords)).unwrap();
+        writeln!(json, "    \"to\": {},", vector(camera.to.coords)).unwrap();
+        writeln!(json, "    \"up\": {}", vector(camera.up)).unwrap();
+        writeln!(json, "  }},").unwrap();
+
+        let lights = self
+            .lights
+            .iter()
+            .map(|light| {
+                format!(
+                    "    {{ \"position\": {}, \"intensity\": {} }}",
+                    vector(light.position.coords),
+                    vector(light.intensity)
+                )
+            })
+            .collect::<Vec<_>>();
+        writeln!(json, "  \"lights\": [{}],", block(&lights, 2)).unwrap();
+
+        let materials = self
+            .materials
+            .iter()
+            .map(|(name, material)| {
+                format!(
+                    "    {}: {{ \"color\": {}, \"ambient\": {}, \"diffuse\": {}, \
+                     \"specular\": {}, \"shininess\": {}, \"emissive\": {}, \
+                     \"emission_strength\": {} }}",
+                    json::quote(name),
+                    vector(material.color),
+                    material.ambient,
+                    material.diffuse,
+                    material.specular,
+                    material.shininess,
+                    vector(material.emissive),
+                    material.emission_strength
+                )
+            })
+            .collect::<Vec<_>>();
+        writeln!(json, "  \"materials\": {{{}}},", block(&materials, 2)).unwrap();
+
+        let objects = self
+            .objects
+            .iter()
+            .map(|object| write_object(object, 2))
+            .collect::<Vec<_>>();
+        writeln!(json, "  \"objects\": [{}],", block(&objects, 2)).unwrap();
+
+        writeln!(json, "  \"settings\": {{").unwrap();
+        writeln!(json, "    \"background\": {},", vector(settings.background)).unwrap();
+        writeln!(
+            json,
+            "    \"transparent_background\": {},",
+            settings.transparent_background
+        )
+        .unwrap();
+        writeln!(json, "    \"light_samples\": {}", settings.light_samples).unwrap();
+        writeln!(json, "  }}").unwrap();
+        write!(json, "}}").unwrap();
+
+        json
+    }
+
+    /// the world and camera the scene describes, loading meshes from
+    /// paths relative to `base`; a mesh used several times is loaded once
+    /// and shared
+    pub(crate) fn build(&self, base: &Path) -> Result<(World, Camera), String> {
+        let mut world = World::new(Light::default());
+        world.lights = self
+            .lights
+            .iter()
+            .map(|light| Light::point_light(light.position, light.intensity))
+            .collect();
+        let mut meshes = vec![];
+
+        for object in &self.objects {
+            world
+                .objects
+                .push(self.object(object, None, base, &mut meshes)?);
+        }
+
+        world.background = self.settings.background;
+        world.transparent_background = self.settings.transparent_background;
+        world.light_samples = self.settings.light_samples;
+        world.collect_emitters();
+
+        let mut camera = Camera::new(
+            self.camera.width,
+            self.camera.height,
+            self.camera.field_of_view.to_radians(),
+        );
+        camera.transform =
+            Camera::view_transforms(self.camera.from, self.camera.to, self.camera.up);
+
+        Ok((world, camera))
+    }
+
+    /// the shape `object` describes; a group's children take on its
+    /// transform and, unless they have their own, its material
+    fn object(
+        &self,
+        object: &ObjectSpec,
+        inherited: Option<&str>,
+        base: &Path,
+        meshes: &mut Vec<(String, Arc<Bvh>)>,
+    ) -> Result<Box<dyn Shape>, String> {
+        let transform = object
+            .transform
+            .iter()
+            .fold(Matrix4::identity(), |transform, step| {
+                step.matrix() * transform
+            });
+        let name = object.material.as_deref().or(inherited);
+        let material = name
+            .and_then(|name| self.materials.iter().find(|(n, _)| n == name))
+            .map(|(_, material)| material.material())
+            .unwrap_or_default();
+
+        Ok(match &object.shape {
+            ShapeSpec::Sphere => Box::new(Sphere {
+                transform,
+                material,
+                ..Sphere::new()
+            }),
+            ShapeSpec::Plane => {
+                let mut plane = Plane::new();
+                plane.transform = transform;
+                plane.material = material;
+                Box::new(plane)
+            }
+            ShapeSpec::Cube => Box::new(Cube {
+                transform,
+                material,
+                ..Cube::default()
+            }),
+            ShapeSpec::Cylinder {
+                minimum,
+                maximum,
+                closed,
+            } => Box::new(Cylinder {
+                minimum: *minimum,
+                maximum: *maximum,
+                closed: *closed,
+                transform,
+                material,
+            }),
+            ShapeSpec::Cone {
+                minimum,
+                maximum,
+                closed,
+            } => Box::new(Cone {
+                minimum: *minimum,
+                maximum: *maximum,
+                closed: *closed,
+                transform,
+                material,
+            }),
+            ShapeSpec::Torus {
+                major_radius,
+                minor_radius,
+            } => Box::new(Torus {
+                major_radius: *major_radius,
+                minor_radius: *minor_radius,
+                transform,
+                material,
+            }),
+            ShapeSpec::Disk { inner_radius } => Box::new(Disk {
+                inner_radius: *inner_radius,
+                transform,
+                material,
+            }),
+            ShapeSpec::Rectangle => Box::new(Rectangle {
+                transform,
+                material,
+            }),
+            ShapeSpec::Triangle([p1, p2, p3]) => {
+                let mut triangle = Triangle::new(*p1, *p2, *p3).transformed(&transform);
+                triangle.material = material;
+                Box::new(triangle)
+            }
+            ShapeSpec::Mesh(path) => {
+                let mesh = match meshes.iter().find(|(loaded, _)| loaded == path) {
+                    Some((_, mesh)) => mesh.clone(),
+                    None => {
+                        let triangles = load_mesh(&base.join(path))
+                            .map_err(|e| format!("mesh \"{}\": {}", path, e))?;
+                        let mesh = Arc::new(Bvh::new(&triangles));
+                        meshes.push((path.clone(), mesh.clone()));
+                        mesh
+                    }
+                };
+
+                Box::new(
+                    Instance::new(mesh)
+                        .with_material(material)
+                        .with_transform(transform),
+                )
+            }
+            ShapeSpec::Group(children) => {
+                let children = children
+                    .iter()
+                    .map(|child| self.object(child, name, base, meshes))
+                    .collect::<Result<_, _>>()?;
+
+                Box::new(DynGroup::new(children).with_transform(transform))
+            }
+            ShapeSpec::Csg(operation, left, right) => Box::new(
+                Csg::new(
+                    *operation,
+                    self.object(left, name, base, meshes)?,
+                    self.object(right, name, base, meshes)?,
+                )
+                .with_transform(transform),
+            ),
+        })
+    }
+}
+
+fn load_mesh(path: &Path) -> Result<Vec<Triangle>, String> {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(file);
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "stl" => nom_stl::parse_stl(&mut reader)
+            .map(|mesh| crate::stl_triangles(&mesh))
+            .map_err(|e| e.to_string()),
+        "obj" => crate::obj::parse_obj(reader),
+        "ply" => crate::ply::parse_ply(reader),
+        _ => Err("expected an .stl, .obj or .ply file".to_string()),
+    }
+}
+
+/// `json`'s members, refusing any not in `known` so typos don't pass silently
+fn members<'a>(json: &'a Json, known: &[&str]) -> Result<&'a [(String, Json)], String> {
+    let members = json.as_object()?;
+
+    for (name, value) in members {
+        if !known.contains(&name.as_str()) {
+            return Err(value.error(&format!("unknown setting \"{}\"", name)));
+        }
+    }
+
+    Ok(members)
+}
+
+fn member<'a>(members: &'a [(String, Json)], name: &str) -> Option<&'a Json> {
+    members
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value)
+}
+
+fn point(json: &Json) -> Result<Point3<f32>, String> {
+    json.as_numbers().map(Point3::from)
+}
+
+fn vector3(json: &Json) -> Result<Vector3<f32>, String> {
+    json.as_numbers().map(Vector3::from)
+}
+
+fn parse_camera(json: &Json) -> Result<CameraSpec, String> {
+    let members = members(
+        json,
+        &["width", "height", "field_of_view", "from", "to", "up"],
+    )?;
+    let mut camera = CameraSpec::default();
+
+    for (name, value) in members {
+        match name.as_str() {
+            "width" => camera.width = value.as_usize()?,
+            "height" => camera.height = value.as_usize()?,
+            "field_of_view" => camera.field_of_view = value.as_f32()?,
+            "from" => camera.from = point(value)?,
+            "to" => camera.to = point(value)?,
+            _ => camera.up = vector3(value)?,
+        }
+    }
+
+    if camera.width == 0 || camera.height == 0 {
+        return Err(json.error("the camera needs a width and height of at least 1"));
+    }
+
+    Ok(camera)
+}
+
+fn parse_light(json: &Json) -> Result<LightSpec, String> {
+    let mut light = LightSpec::default();
+
+    for (name, value) in members(json, &["position", "intensity"])? {
+        match name.as_str() {
+            "position" => light.position = point(value)?,
+            _ => light.intensity = vector3(value)?,
+        }
+    }
+
+    Ok(light)
+}
+
+fn parse_material(json: &Json) -> Result<MaterialSpec, String> {
+    let members = members(
+        json,
+        &[
+            "color",
+            "ambient",
+            "diffuse",
+            "specular",
+            "shininess",
+            "emissive",
+            "emission_strength",
+        ],
+    )?;
+    let mut material = MaterialSpec::default();
+
+    for (name, value) in members {
+        match name.as_str() {
+            "color" => material.color = vector3(value)?,
+            "ambient" => material.ambient = value.as_f32()?,
+            "diffuse" => material.diffuse = value.as_f32()?,
+            "specular" => material.specular = value.as_f32()?,
+            "shininess" => material.shininess = value.as_f32()?,
+            "emissive" => material.emissive = vector3(value)?,
+            _ => material.emission_strength = value.as_f32()?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_object(json: &Json, materials: &[(String, MaterialSpec)]) -> Result<ObjectSpec, String> {
+    let members = members(
+        json,
+        &[
+            "type",
+            "material",
+            "transform",
+            "points",
+            "path",
+            "children",
+            "minimum",
+            "maximum",
+            "closed",
+            "major_radius",
+            "minor_radius",
+            "inner_radius",
+            "operation",
+            "left",
+            "right",
+        ],
+    )?;
+    let kind = member(members, "type").ok_or_else(|| json.error("object without a \"type\""))?;
+
+    // settings that belong to other kinds of object are mistakes
+    let only_for = |setting: &str, kinds: &str| -> Result<(), String> {
+        match member(members, setting) {
+            Some(value) if !kinds.split(',').any(|k| k == kind.as_str().unwrap_or("")) => {
+                Err(value.error(&format!("\"{}\" only applies to a {}", setting, kinds)))
+            }
+            _ => Ok(()),
+        }
+    };
+    only_for("points", "triangle")?;
+    only_for("path", "mesh")?;
+    only_for("children", "group")?;
+    only_for("minimum", "cylinder,cone")?;
+    only_for("maximum", "cylinder,cone")?;
+    only_for("closed", "cylinder,cone")?;
+    only_for("major_radius", "torus")?;
+    only_for("minor_radius", "torus")?;
+    only_for("inner_radius", "disk")?;
+    only_for("operation", "csg")?;
+    only_for("left", "csg")?;
+    only_for("right", "csg")?;
+
+    let required = |setting: &str| {
+        member(members, setting).ok_or_else(|| json.error(&format!("missing \"{}\"", setting)))
+    };
+
+    let number = |setting: &str, default: f32| match member(members, setting) {
+        Some(value) => value.as_f32(),
+        None => Ok(default),
+    };
+    let minimum = number("minimum", -f32::INFINITY)?;
+    let maximum = number("maximum", f32::INFINITY)?;
+    let closed = match member(members, "closed") {
+        Some(value) => value.as_bool()?,
+        None => false,
+    };
+
+    let shape = match kind.as_str()? {
+        "sphere" => ShapeSpec::Sphere,
+        "plane" => ShapeSpec::Plane,
+        "cube" => ShapeSpec::Cube,
+        "cylinder" => ShapeSpec::Cylinder {
+            minimum,
+            maximum,
+            closed,
+        },
+        "cone" => ShapeSpec::Cone {
+            minimum,
+            maximum,
+            closed,
+        },
+        "torus" => {
+            let defaults = Torus::default();
+
+            ShapeSpec::Torus {
+                major_radius: number("major_radius", defaults.major_radius)?,
+                minor_radius: number("minor_radius", defaults.minor_radius)?,
+            }
+        }
+        "disk" => {
+            let inner_radius = number("inner_radius", 0.0)?;
+
+            // a hole as wide as the disk would leave v dividing by zero
+            if !(0.0..1.0).contains(&inner_radius) {
+                return Err(required("inner_radius")?
+                    .error("\"inner_radius\" must be at least 0 and less than 1"));
+            }
+
+            ShapeSpec::Disk { inner_radius }
+        }
+        "rectangle" => ShapeSpec::Rectangle,
+        "triangle" => {
+            let points = required("points")?;
+            let corners = points.as_array()?;
+
+            if corners.len() != 3 {
+                return Err(points.error("a triangle needs 3 points"));
+            }
+
+            ShapeSpec::Triangle([
+                point(&corners[0])?,
+                point(&corners[1])?,
+                point(&corners[2])?,
+            ])
+        }
+        "mesh" => ShapeSpec::Mesh(required("path")?.as_str()?.to_string()),
+        "group" => ShapeSpec::Group(
+            required("children")?
+                .as_array()?
+                .iter()
+                .map(|child| parse_object(child, materials))
+                .collect::<Result<_, _>>()?,
+        ),
+        "csg" => {
+            let operation = required("operation")?;
+            let operation = match operation.as_str()? {
+                "union" => CsgOperation::Union,
+                "intersection" => CsgOperation::Intersection,
+                "difference" => CsgOperation::Difference,
+                other => {
+                    return Err(operation.error(&format!(
+                        "unknown operation \"{}\", expected union, intersection or difference",
+                        other
+                    )))
+                }
+            };
+
+            ShapeSpec::Csg(
+                operation,
+                Box::new(parse_object(required("left")?, materials)?),
+                Box::new(parse_object(required("right")?, materials)?),
+            )
+        }
+        other => {
+            return Err(kind.error(&format!(
+                "unknown object type \"{}\", expected sphere, plane, cube, cylinder, cone, torus, disk, rectangle, triangle, mesh, group or csg",
+                other
+            )))
+        }
+    };
+
+    let material = match member(members, "material") {
+        Some(value) => {
+            let name = value.as_str()?;
+
+            if !materials.iter().any(|(n, _)| n == name) {
+                return Err(value.error(&format!("no material named \"{}\"", name)));
+            }
+
+            Some(name.to_string())
+        }
+        None => None,
+    };
+
+    let transform = match member(members, "transform") {
+        Some(steps) => steps
+            .as_array()?
+            .iter()
+            .map(parse_step)
+            .collect::<Result<_, _>>()?,
+        None => vec![],
+    };
+
+    Ok(ObjectSpec {
+        shape,
+        material,
+        transform,
+    })
+}
+
+fn parse_step(json: &Json) -> Result<TransformStep, String> {
+    let members = members(
+        json,
+        &["translate", "scale", "rotate_x", "rotate_y", "rotate_z"],
+    )?;
+
+    let (name, value) = match members {
+        [step] => step,
+        _ => {
+            return Err(json.error("each transform step is one of translate, scale or rotate_x/y/z"))
+        }
+    };
+
+    Ok(match name.as_str() {
+        "translate" => TransformStep::Translate(vector3(value)?),
+        // a single number scales evenly
+        "scale" => TransformStep::Scale(match value.as_f32() {
+            Ok(factor) => Vector3::repeat(factor),
+            Err(_) => vector3(value)?,
+        }),
+        "rotate_x" => TransformStep::RotateX(value.as_f32()?),
+        "rotate_y" => TransformStep::RotateY(value.as_f32()?),
+        _ => TransformStep::RotateZ(value.as_f32()?),
+    })
+}
+
+fn parse_settings(json: &Json) -> Result<RenderSettings, String> {
+    let members = members(
+        json,
+        &["background", "transparent_background", "light_samples"],
+    )?;
+    let mut settings = RenderSettings::default();
+
+    for (name, value) in members {
+        match name.as_str() {
+            "background" => settings.background = vector3(value)?,
+            "transparent_background" => settings.transparent_background = value.as_bool()?,
+            _ => settings.light_samples = value.as_usize()?,
+        }
+    }
+
+    Ok(settings)
+}
+
+fn vector(v: Vector3<f32>) -> String {
+    format!("[{}, {}, {}]", v.x, v.y, v.z)
+}
+
+/// `items` one per line at `indent`, or nothing for an empty list
+fn block(items: &[String], indent: usize) -> String {
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!("\n{}\n{}", items.join(",\n"), " ".repeat(indent))
+    }
+}
+
+fn write_object(object: &ObjectSpec, indent: usize) -> String {
+    let pad = " ".repeat(indent + 2);
+    let mut fields = vec![];
+
+    let kind = match &object.shape {
+        ShapeSpec::Sphere => "sphere",
+        ShapeSpec::Plane => "plane",
+        ShapeSpec::Cube => "cube",
+        ShapeSpec::Cylinder { .. } => "cylinder",
+        ShapeSpec::Cone { .. } => "cone",
+        ShapeSpec::Torus { .. } => "torus",
+        ShapeSpec::Disk { .. } => "disk",
+        ShapeSpec::Rectangle => "rectangle",
+        ShapeSpec::Triangle(_) => "triangle",
+        ShapeSpec::Mesh(_) => "mesh",
+        ShapeSpec::Group(_) => "group",
+        ShapeSpec::Csg(..) => "csg",
+    };
+    fields.push(format!("\"type\": \"{}\"", kind));
+
+    if let Some(material) = &object.material {
+        fields.push(format!("\"material\": {}", json::quote(material)));
+    }
+
+    if !object.transform.is_empty() {
+        let steps = object
+            .transform
+            .iter()
+            .map(|step| match step {
+                TransformStep::Translate(v) => format!("{{ \"translate\": {} }}", vector(*v)),
+                TransformStep::Scale(v) => format!("{{ \"scale\": {} }}", vector(*v)),
+                TransformStep::RotateX(degrees) => format!("{{ \"rotate_x\": {} }}", degrees),
+                TransformStep::RotateY(degrees) => format!("{{ \"rotate_y\": {} }}", degrees),
+                TransformStep::RotateZ(degrees) => format!("{{ \"rotate_z\": {} }}", degrees),
+            })
+            .collect::<Vec<_>>();
+        fields.push(format!("\"transform\": [{}]", steps.join(", ")));
+    }
+
+    match &object.shape {
+        ShapeSpec::Cylinder {
+            minimum,
+            maximum,
+            closed,
+        }
+        | ShapeSpec::Cone {
+            minimum,
+            maximum,
+            closed,
+        } => {
+            if minimum.is_finite() {
+                fields.push(format!("\"minimum\": {}", minimum));
+            }
+
+            if maximum.is_finite() {
+                fields.push(format!("\"maximum\": {}", maximum));
+            }
+
+            if *closed {
+                fields.push("\"closed\": true".to_string());
+            }
+        }
+        ShapeSpec::Torus {
+            major_radius,
+            minor_radius,
+        } => {
+            fields.push(format!("\"major_radius\": {}", major_radius));
+            fields.push(format!("\"minor_radius\": {}", minor_radius));
+        }
+        ShapeSpec::Disk { inner_radius } if *inner_radius > 0.0 => {
+            fields.push(format!("\"inner_radius\": {}", inner_radius));
+        }
+        ShapeSpec::Triangle(points) => {
+            let points = points.iter().map(|p| vector(p.coords)).collect::<Vec<_>>();
+            fields.push(format!("\"points\": [{}]", points.join(", ")));
+        }
+        ShapeSpec::Mesh(path) => fields.push(format!("\"path\": {}", json::quote(path))),
+        ShapeSpec::Group(children) => {
+            let children = children
+                .iter()
+                .map(|child| write_object(child, indent + 2))
+                .collect::<Vec<_>>();
+            fields.push(format!("\"children\": [{}]", block(&children, indent + 2)));
+        }
+        ShapeSpec::Csg(operation, left, right) => {
+            let operation = match operation {
+                CsgOperation::Union => "union",
+                CsgOperation::Intersection => "intersection",
+                CsgOperation::Difference => "difference",
+            };
+            fields.push(format!("\"operation\": \"{}\"", operation));
+            fields.push(format!(
+                "\"left\": {}",
+                write_object(left, indent + 2).trim_start()
+            ));
+            fields.push(format!(
+                "\"right\": {}",
+                write_object(right, indent + 2).trim_start()
+            ));
+        }
+        _ => {}
+    }
+
+    let fields = fields
+        .iter()
+        .map(|field| format!("{}{}", pad, field))
+        .collect::<Vec<_>>();
+
+    format!(
+        "{}{{\n{}\n{}}}",
+        " ".repeat(indent),
+        fields.join(",\n"),
+        " ".repeat(indent)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    const SCENE: &str = r#"{
+  "camera": { "width": 20, "height": 10, "field_of_view": 60, "from": [0, 1, -5] },
+  "lights": [
+    { "position": [-10, 10, -10] },
+    { "position": [10, 10, -10], "intensity": [0.5, 0.5, 0.5] }
+  ],
+  "materials": {
+    "red": { "color": [1, 0.2, 0.2], "specular": 0.3 },
+    "glow": { "emissive": [1, 1, 0.8] }
+  },
+  "objects": [
+    { "type": "plane" },
+    { "type": "sphere", "material": "red",
+      "transform": [{ "scale": 0.5 }, { "translate": [0, 0.5, 0] }] },
+    { "type": "group", "material": "glow", "transform": [{ "rotate_y": 30 }], "children": [
+      { "type": "cube" },
+      { "type": "triangle", "points": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "material": "red" }
+    ] }
+  ],
+  "settings": { "light_samples": 4 }
+}"#;
+
+    #[test]
+    fn parsing_a_scene() {
+        let scene = SceneFile::parse(SCENE).unwrap();
+
+        assert_eq!(scene.camera.width, 20);
+        assert_eq!(scene.camera.to, CameraSpec::default().to);
+        assert_eq!(scene.lights.len(), 2);
+        assert_eq!(scene.lights[1].intensity, Vector3::repeat(0.5));
+        assert_eq!(scene.materials[0].1.specular, 0.3);
+        assert_eq!(scene.materials[1].1.color, Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(scene.objects.len(), 3);
+        assert_eq!(
+            scene.objects[1].transform,
+            vec![
+                TransformStep::Scale(Vector3::repeat(0.5)),
+                TransformStep::Translate(Vector3::new(0.0, 0.5, 0.0))
+            ]
+        );
+
+        match &scene.objects[2].shape {
+            ShapeSpec::Group(children) => assert_eq!(children.len(), 2),
+            _ => panic!("expected a group"),
+        }
+
+        assert_eq!(scene.settings.light_samples, 4);
+    }
+
+    #[test]
+    fn a_scene_survives_a_round_trip() {
+        let scene = SceneFile::parse(SCENE).unwrap();
+        let json = scene.to_json();
+
+        assert_eq!(SceneFile::parse(&json), Ok(scene));
+        assert_eq!(
+            SceneFile::parse(&SceneFile::default().to_json()),
+            Ok(SceneFile::default())
+        );
+    }
+
+    #[test]
+    fn building_a_scene() {
+        let scene = SceneFile::parse(SCENE).unwrap();
+        let (world, camera) = scene.build(Path::new(".")).unwrap();
+
+        assert_eq!(world.objects.len(), 3);
+        assert_eq!(world.lights.len(), 2);
+        assert_eq!(world.light_samples, 4);
+        assert_eq!(camera.hsize, 20);
+        assert!((camera.eye() - Point3::new(0.0, 1.0, -5.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn scene_files_and_the_command_line_place_the_camera_alike() {
+        let scene = SceneFile::parse(
+            r#"{ "camera": { "from": [1, 3, -6], "to": [0, 1, 0], "up": [0, 0.8, 0.6] } }"#,
+        )
+        .unwrap();
+        let (_, from_file) = scene.build(Path::new(".")).unwrap();
+
+        let options = crate::Options {
+            from: scene.camera.from,
+            to: scene.camera.to,
+            up: scene.camera.up,
+            ..Default::default()
+        };
+        let (_, from_options) = crate::scene(vec![], &options);
+
+        assert_eq!(from_file.transform, from_options.transform);
+        assert!((from_options.eye() - scene.camera.from).norm() < 1e-5);
+    }
+
+    #[test]
+    fn group_members_take_the_groups_material_unless_they_have_one() {
+        let scene = SceneFile::parse(SCENE).unwrap();
+        let (world, _) = scene.build(Path::new(".")).unwrap();
+
+        let ray = Ray::new(Point3::new(0.2, 0.2, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = world.objects[2].intersect(&ray);
+        let materials = hits
+            .iter()
+            .map(|hit| hit.object.material())
+            .collect::<Vec<_>>();
+
+        // the cube, twice, and the triangle inside it
+        assert_eq!(materials.len(), 3);
+        assert_eq!(
+            materials
+                .iter()
+                .filter(|m| m.emissive == Vector3::new(1.0, 1.0, 0.8))
+                .count(),
+            2
+        );
+        assert_eq!(materials.iter().filter(|m| m.specular == 0.3).count(), 1);
+    }
+
+    #[test]
+    fn csg_objects_round_trip_and_build() {
+        let scene = SceneFile::parse(
+            r#"{ "materials": { "red": {} }, "objects": [
+                { "type": "csg", "operation": "difference", "material": "red",
+                  "left": { "type": "cube" },
+                  "right": { "type": "sphere", "transform": [{ "scale": 1.3 }] } }
+            ] }"#,
+        )
+        .unwrap();
+
+        match &scene.objects[0].shape {
+            ShapeSpec::Csg(CsgOperation::Difference, left, _) => {
+                assert_eq!(left.shape, ShapeSpec::Cube)
+            }
+            _ => panic!("expected a csg object"),
+        }
+        assert_eq!(SceneFile::parse(&scene.to_json()), Ok(scene.clone()));
+
+        // the sphere hollows out the middle of the cube but not its corners
+        let (world, _) = scene.build(Path::new(".")).unwrap();
+        let through = |x: f32, y: f32| {
+            let ray = Ray::new(Point3::new(x, y, -5.0), Vector3::new(0.0, 0.0, 1.0));
+            world.objects[0].intersect(&ray).len()
+        };
+        assert_eq!(through(0.0, 0.0), 0);
+        assert_eq!(through(0.95, 0.95), 2);
+    }
+
+    #[test]
+    fn cylinders_and_cones_keep_their_truncation() {
+        let scene = SceneFile::parse(
+            r#"{ "objects": [
+                { "type": "cylinder", "minimum": 0, "maximum": 2, "closed": true },
+                { "type": "cone", "maximum": 0 }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene.objects[1].shape,
+            ShapeSpec::Cone {
+                minimum: -f32::INFINITY,
+                maximum: 0.0,
+                closed: false
+            }
+        );
+        assert_eq!(SceneFile::parse(&scene.to_json()), Ok(scene.clone()));
+
+        // straight down through the capped cylinder
+        let (world, _) = scene.build(Path::new(".")).unwrap();
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let ts = world.objects[0]
+            .intersect(&ray)
+            .iter()
+            .map(|hit| hit.t)
+            .collect::<Vec<_>>();
+        assert_eq!(ts, vec![5.0, 3.0]);
+    }
+
+    #[test]
+    fn tori_disks_and_rectangles_round_trip_and_build() {
+        let scene = SceneFile::parse(
+            r#"{ "objects": [
+                { "type": "torus", "minor_radius": 0.5 },
+                { "type": "disk", "inner_radius": 0.5 },
+                { "type": "rectangle", "transform": [{ "translate": [0, 1, 0] }] }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene.objects[0].shape,
+            ShapeSpec::Torus {
+                major_radius: 1.0,
+                minor_radius: 0.5
+            }
+        );
+        assert_eq!(SceneFile::parse(&scene.to_json()), Ok(scene.clone()));
+
+        // straight down at x = 0.75 through the rectangle, the washer and
+        // both sides of the tube
+        let (world, _) = scene.build(Path::new(".")).unwrap();
+        let ray = Ray::new(Point3::new(0.75, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hits = world
+            .objects
+            .iter()
+            .map(|object| object.intersect(&ray).len())
+            .collect::<Vec<_>>();
+        assert_eq!(hits, vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn transform_steps_apply_in_order() {
+        let scene = SceneFile::parse(SCENE).unwrap();
+        let (world, _) = scene.build(Path::new(".")).unwrap();
+
+        let sphere = world.objects[1].transform();
+        let top = sphere.transform_point(&Point3::new(0.0, 1.0, 0.0));
+
+        assert!((top - Point3::new(0.0, 1.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let error = |text: &str| SceneFile::parse(text).unwrap_err();
+
+        assert_eq!(
+            error(
+                "{\n  \"objects\": [\n    { \"type\": \"sphere\", \"material\": \"blue\" }\n  ]\n}"
+            ),
+            "line 3: no material named \"blue\""
+        );
+        assert_eq!(
+            error("{\n  \"camera\": {\n    \"feild_of_view\": 60\n  }\n}"),
+            "line 3: unknown setting \"feild_of_view\""
+        );
+        assert_eq!(
+            error("{ \"objects\": [\n  { \"type\": \"blob\" }\n] }"),
+            "line 2: unknown object type \"blob\", expected sphere, plane, cube, cylinder, cone, torus, disk, rectangle, triangle, mesh, group or csg"
+        );
+        assert_eq!(
+            error("{ \"objects\": [\n  { \"type\": \"sphere\",\n    \"points\": [] }\n] }"),
+            "line 3: \"points\" only applies to a triangle"
+        );
+        assert_eq!(
+            error("{ \"objects\": [\n  { \"type\": \"disk\", \"inner_radius\": 1 }\n] }"),
+            "line 2: \"inner_radius\" must be at least 0 and less than 1"
+        );
+    }
+
+    #[test]
+    fn missing_meshes_are_reported_by_path() {
+        let scene =
+            SceneFile::parse(r#"{ "objects": [{ "type": "mesh", "path": "nowhere.stl" }] }"#)
+                .unwrap();
+
+        let error = scene.build(Path::new("/nonexistent")).err().unwrap();
+        assert!(error.starts_with("mesh \"nowhere.stl\": "), "{}", error);
+    }
+}
@@ -1,16 +1,63 @@
 use crate::bounding_box::BoundingBox;
+use crate::emitter::Emitter;
 use crate::intersection::Intersection;
 use crate::material::Material;
 use crate::ray::Ray;
-use nalgebra::{Matrix4, Point3, Projective3, Transform, Vector3};
+use nalgebra::{Matrix4, Point2, Point3, Projective3, Transform, Vector3};
 
 pub trait Shape: BoundingBox + Send + Sync {
-    fn material(&self) -> Material;
+    fn material(&self) -> &Material;
     fn transform(&self) -> Matrix4<f32>;
+    /// moves the shape by `parent` after its own transform, as when it's
+    /// put in a transformed group, so `transform` stays object-to-world
+    fn transform_by(&mut self, parent: &Matrix4<f32>);
     fn normal_at(&self, point: Point3<f32>) -> Vector3<f32>;
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection>;
     fn local_normal_at(&self, point: Point3<f32>) -> Vector3<f32>;
 
+    /// the normal pointing out of a closed surface, before any flip towards the eye
+    fn outward_normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        self.normal_at(point)
+    }
+
+    /// the normal at a hit, for shapes whose hits say more than their
+    /// points do, e.g. which triangle of a shared mesh was hit
+    fn normal_at_hit(&self, _hit: &Intersection, point: Point3<f32>) -> Vector3<f32> {
+        self.normal_at(point)
+    }
+
+    /// `outward_normal_at`, as `normal_at_hit` is to `normal_at`
+    fn outward_normal_at_hit(&self, _hit: &Intersection, point: Point3<f32>) -> Vector3<f32> {
+        self.outward_normal_at(point)
+    }
+
+    /// texture coordinates carried by the surface itself, e.g. a mesh's vertex UVs
+    fn uv_at(&self, _hit: &Intersection) -> Option<Point2<f32>> {
+        None
+    }
+
+    /// world-space tangent and bitangent following the surface's +u and +v
+    fn tangent_at(&self, _point: Point3<f32>) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        None
+    }
+
+    /// the parts of an emissive shape that can be sampled as lights;
+    /// other emissive shapes glow but don't illuminate their surroundings
+    fn emitters(&self) -> Vec<Emitter> {
+        vec![]
+    }
+
+    /// world-space distance from a hit to the nearest edge of the primitive,
+    /// for shapes with edges worth drawing in a wireframe
+    fn edge_distance(&self, _hit: &Intersection) -> Option<f32> {
+        None
+    }
+
+    /// for groups, the index of the child a hit landed on
+    fn primitive_index(&self, _hit: &Intersection) -> Option<usize> {
+        None
+    }
+
     fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
         let projective_inverse: Projective3<f32> =
             Transform::from_matrix_unchecked(self.transform()).inverse();
@@ -19,3 +66,24 @@ pub trait Shape: BoundingBox + Send + Sync {
         self.local_intersect(local_ray)
     }
 }
+
+/// `point` in the space of a shape whose transform is `transform`
+pub(crate) fn world_to_object(transform: &Matrix4<f32>, point: Point3<f32>) -> Point3<f32> {
+    let transform: Projective3<f32> = Transform::from_matrix_unchecked(*transform);
+
+    transform.inverse_transform_point(&point)
+}
+
+/// a normal from a shape's space in world space, by the inverse transpose
+/// of the transform's linear part; translations don't turn normals
+pub(crate) fn normal_to_world(transform: &Matrix4<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+    let linear = transform
+        .fixed_slice::<nalgebra::U3, nalgebra::U3>(0, 0)
+        .into_owned();
+
+    linear
+        .try_inverse()
+        .map(|inverse| inverse.transpose() * normal)
+        .unwrap_or(normal)
+        .normalize()
+}
@@ -2,10 +2,10 @@ use crate::bounding_box::{BoundingBox, AABB};
 use crate::intersection::Intersection;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::shape::Shape;
-use nalgebra::{Matrix4, Point3, Projective3, Transform, Vector3};
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point3, Vector3};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Sphere {
     pub origin: Point3<f32>,
     pub radius: f32,
@@ -14,29 +14,22 @@ pub struct Sphere {
 }
 
 impl Shape for Sphere {
-    fn material(&self) -> Material {
-        self.material
+    fn material(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix4<f32> {
         self.transform
     }
 
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
     fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
-        let transformed_transform: Projective3<f32> =
-            Transform::from_matrix_unchecked(self.transform);
-        let local_point: Point3<f32> = transformed_transform.inverse_transform_point(&point);
-        let local_normal = self.local_normal_at(local_point);
-        let transposed_transform: Projective3<f32> =
-            Transform::from_matrix_unchecked(self.transform.transpose());
-        let world_normal = transposed_transform.inverse_transform_vector(&local_normal);
-        let mut world_normal_homogeneous = world_normal.to_homogeneous();
-
-        world_normal_homogeneous.w = 0.0;
-
-        Vector3::from_homogeneous(world_normal_homogeneous)
-            .unwrap()
-            .normalize()
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
     }
 
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
@@ -50,8 +43,8 @@ impl Shape for Sphere {
             vec![]
         } else {
             let disc_sqrt = discriminant.sqrt();
-            let t1 = -b - disc_sqrt / (2.0 * a);
-            let t2 = -b + disc_sqrt / (2.0 * a);
+            let t1 = (-b - disc_sqrt) / (2.0 * a);
+            let t2 = (-b + disc_sqrt) / (2.0 * a);
             vec![Intersection::new(t1, self), Intersection::new(t2, self)]
         }
     }
@@ -86,6 +79,8 @@ impl Sphere {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn a_spheres_default_transformation() {}
 
@@ -93,7 +88,18 @@ mod tests {
     fn changing_a_spheres_transformation() {}
 
     #[test]
-    fn intersecting_a_scaled_sphere_with_a_ray() {}
+    fn intersecting_a_scaled_sphere_with_a_ray() {
+        let s = Sphere {
+            transform: Matrix4::new_scaling(2.0),
+            ..Sphere::new()
+        };
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+
+        assert_eq!(xs.len(), 2);
+        assert!((xs[0].t - 3.0).abs() < 1e-5);
+        assert!((xs[1].t - 7.0).abs() < 1e-5);
+    }
 
     #[test]
     fn intersecting_a_translated_sphere_with_a_ray() {}
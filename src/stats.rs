@@ -0,0 +1,269 @@
+use crate::triangle::Triangle;
+use nalgebra::{Matrix3, Point3, Vector3};
+use std::fmt::Write;
+
+/// Measurements of a triangle mesh, in the mesh's own units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshStats {
+    pub triangles: usize,
+    pub surface_area: f32,
+    /// enclosed volume, negative for meshes wound inside out
+    pub volume: f32,
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+    /// centroid of the enclosed solid at uniform density; `None` for meshes
+    /// that enclose no volume
+    pub center_of_mass: Option<Point3<f32>>,
+    /// axes of the solid's inertia tensor, from the smallest moment of
+    /// inertia to the largest
+    pub principal_axes: Option<[Vector3<f32>; 3]>,
+}
+
+impl MeshStats {
+    pub fn new(triangles: &[Triangle]) -> Self {
+        let mut surface_area = 0.0;
+        let mut volume = 0.0;
+        let mut moment = Vector3::zeros();
+        // second moment about the origin, ∫ x xᵀ dV
+        let mut covariance = Matrix3::zeros();
+
+        let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        // each triangle and the origin form a tetrahedron whose signed
+        // volumes sum to the volume enclosed by a closed mesh
+        let canonical = Matrix3::new(2.0, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0, 1.0, 2.0);
+
+        for triangle in triangles {
+            let (a, b, c) = (triangle.p1.coords, triangle.p2.coords, triangle.p3.coords);
+
+            for point in &[triangle.p1, triangle.p2, triangle.p3] {
+                min = min.inf(point);
+                max = max.sup(point);
+            }
+
+            surface_area += (b - a).cross(&(c - a)).norm() / 2.0;
+
+            let det = a.dot(&b.cross(&c));
+            volume += det / 6.0;
+            moment += (a + b + c) * (det / 24.0);
+
+            let corners = Matrix3::from_columns(&[a, b, c]);
+            covariance += corners * canonical * corners.transpose() * (det / 120.0);
+        }
+
+        let (center_of_mass, principal_axes) = if volume.abs() > f32::EPSILON * extent(min, max) {
+            let center = moment / volume;
+            let central = covariance - center * center.transpose() * volume;
+            let inertia = Matrix3::identity() * central.trace() - central;
+
+            (
+                Some(Point3::from(center)),
+                principal_axes(inertia * volume.signum()),
+            )
+        } else {
+            (None, None)
+        };
+
+        MeshStats {
+            triangles: triangles.len(),
+            surface_area,
+            volume,
+            min,
+            max,
+            center_of_mass,
+            principal_axes,
+        }
+    }
+
+    /// size along each axis
+    pub fn extents(&self) -> Vector3<f32> {
+        if self.triangles == 0 {
+            Vector3::zeros()
+        } else {
+            self.max - self.min
+        }
+    }
+
+    /// the measurements as a JSON object, with `null` for anything unknown
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+
+        writeln!(json, "{{").unwrap();
+        writeln!(json, "  \"triangles\": {},", self.triangles).unwrap();
+        writeln!(json, "  \"surface_area\": {},", number(self.surface_area)).unwrap();
+        writeln!(json, "  \"volume\": {},", number(self.volume)).unwrap();
+        writeln!(json, "  \"min\": {},", vector(self.min.coords)).unwrap();
+        writeln!(json, "  \"max\": {},", vector(self.max.coords)).unwrap();
+        writeln!(json, "  \"extents\": {},", vector(self.extents())).unwrap();
+
+        let center = self
+            .center_of_mass
+            .map_or_else(|| "null".to_string(), |c| vector(c.coords));
+        writeln!(json, "  \"center_of_mass\": {},", center).unwrap();
+
+        let axes = self.principal_axes.map_or_else(
+            || "null".to_string(),
+            |axes| {
+                let axes = axes.iter().map(|a| vector(*a)).collect::<Vec<_>>();
+                format!("[{}]", axes.join(", "))
+            },
+        );
+        writeln!(json, "  \"principal_axes\": {}", axes).unwrap();
+        write!(json, "}}").unwrap();
+
+        json
+    }
+}
+
+fn extent(min: Point3<f32>, max: Point3<f32>) -> f32 {
+    let extent = (max - min).max();
+
+    if extent.is_finite() {
+        extent * extent * extent
+    } else {
+        0.0
+    }
+}
+
+/// eigenvectors of the inertia tensor, ordered by their moments
+fn principal_axes(inertia: Matrix3<f32>) -> Option<[Vector3<f32>; 3]> {
+    let eigen = inertia.symmetric_eigen();
+
+    let mut order = [0, 1, 2];
+    order.sort_by(|a, b| {
+        eigen.eigenvalues[*a]
+            .partial_cmp(&eigen.eigenvalues[*b])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let axes = [
+        eigen.eigenvectors.column(order[0]).normalize(),
+        eigen.eigenvectors.column(order[1]).normalize(),
+        eigen.eigenvectors.column(order[2]).normalize(),
+    ];
+
+    if axes.iter().all(|axis| axis.iter().all(|c| c.is_finite())) {
+        Some(axes)
+    } else {
+        None
+    }
+}
+
+fn number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn vector(v: Vector3<f32>) -> String {
+    format!("[{}, {}, {}]", number(v.x), number(v.y), number(v.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    // an axis-aligned box from `min` to `max`, wound counter-clockwise
+    // seen from outside
+    fn cuboid(min: Point3<f32>, max: Point3<f32>) -> Vec<Triangle> {
+        let corner = |x: usize, y: usize, z: usize| {
+            Point3::new(
+                if x == 0 { min.x } else { max.x },
+                if y == 0 { min.y } else { max.y },
+                if z == 0 { min.z } else { max.z },
+            )
+        };
+
+        // each face as a quad, counter-clockwise from outside
+        let quads = [
+            [(0, 0, 0), (0, 1, 0), (1, 1, 0), (1, 0, 0)],
+            [(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)],
+            [(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)],
+            [(0, 1, 0), (0, 1, 1), (1, 1, 1), (1, 1, 0)],
+            [(0, 0, 0), (0, 0, 1), (0, 1, 1), (0, 1, 0)],
+            [(1, 0, 0), (1, 1, 0), (1, 1, 1), (1, 0, 1)],
+        ];
+
+        quads
+            .iter()
+            .flat_map(|q| {
+                let p = |i: usize| corner(q[i].0, q[i].1, q[i].2);
+                vec![
+                    Triangle::new(p(0), p(1), p(2)),
+                    Triangle::new(p(0), p(2), p(3)),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measuring_a_box() {
+        let stats = MeshStats::new(&cuboid(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(5.0, 4.0, 4.0),
+        ));
+
+        assert_eq!(stats.triangles, 12);
+        assert_close(stats.volume, 8.0);
+        assert_close(stats.surface_area, 2.0 * (8.0 + 4.0 + 2.0));
+        assert_eq!(stats.extents(), Vector3::new(4.0, 2.0, 1.0));
+
+        let center = stats.center_of_mass.unwrap();
+        assert_close((center - Point3::new(3.0, 3.0, 3.5)).norm(), 0.0);
+
+        // a long box turns most easily about its length
+        let axes = stats.principal_axes.unwrap();
+        assert_close(axes[0].x.abs(), 1.0);
+        assert_close(axes[1].y.abs(), 1.0);
+        assert_close(axes[2].z.abs(), 1.0);
+    }
+
+    #[test]
+    fn an_inside_out_box_has_negative_volume() {
+        let triangles = cuboid(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0))
+            .into_iter()
+            .map(|t| Triangle::new(t.p1, t.p3, t.p2))
+            .collect::<Vec<_>>();
+
+        let stats = MeshStats::new(&triangles);
+        assert_close(stats.volume, -1.0);
+        assert_close(
+            (stats.center_of_mass.unwrap() - Point3::new(0.5, 0.5, 0.5)).norm(),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn an_open_surface_has_no_center_of_mass() {
+        let triangles = vec![Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        )];
+
+        let stats = MeshStats::new(&triangles);
+        assert_close(stats.surface_area, 0.5);
+        assert_eq!(stats.center_of_mass, None);
+        assert!(stats.to_json().contains("\"center_of_mass\": null"));
+    }
+
+    #[test]
+    fn stats_as_json() {
+        let stats = MeshStats::new(&cuboid(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 1.0, 1.0),
+        ));
+        let json = stats.to_json();
+
+        assert!(json.starts_with('{') && json.ends_with('}'));
+        assert!(json.contains("\"triangles\": 12,"));
+        assert!(json.contains("\"extents\": [2, 1, 1],"));
+    }
+}
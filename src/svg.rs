@@ -0,0 +1,262 @@
+use crate::adjacency::{Adjacency, Edge};
+use crate::camera::Camera;
+use crate::material::Color;
+use crate::world::World;
+use nalgebra::{Point2, Vector3};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// How one kind of edge is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stroke {
+    pub color: Color,
+    /// in pixels
+    pub width: f32,
+    /// dash and gap lengths in pixels; solid when `None`
+    pub dash: Option<(f32, f32)>,
+}
+
+impl Stroke {
+    pub fn new(color: Color, width: f32) -> Self {
+        Stroke {
+            color,
+            width,
+            dash: None,
+        }
+    }
+}
+
+/// Strokes for each kind of edge in an SVG export; `None` leaves that kind out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgStyle {
+    pub silhouette: Option<Stroke>,
+    pub crease: Option<Stroke>,
+    pub boundary: Option<Stroke>,
+    /// edges where the faces meet at more than this many radians are creases
+    pub crease_angle: f32,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        let black = Vector3::new(0.0, 0.0, 0.0);
+
+        SvgStyle {
+            silhouette: Some(Stroke::new(black, 2.0)),
+            crease: Some(Stroke::new(black, 1.0)),
+            boundary: Some(Stroke {
+                dash: Some((4.0, 2.0)),
+                ..Stroke::new(black, 1.0)
+            }),
+            crease_angle: 40.0_f32.to_radians(),
+        }
+    }
+}
+
+/// Projects the mesh's silhouettes, creases and boundaries through the
+/// camera and writes the parts that aren't hidden by anything in the world
+/// as SVG polylines, one `<g>` per kind of edge.
+pub fn export_svg(camera: &Camera, world: &World, mesh: &Adjacency, style: &SvgStyle) -> String {
+    let eye = camera.eye();
+
+    let silhouettes = mesh.silhouette_edges(eye).collect::<Vec<_>>();
+    let on_silhouette = silhouettes
+        .iter()
+        .map(|edge| (edge.a, edge.b))
+        .collect::<HashSet<_>>();
+
+    let creases = mesh
+        .crease_edges(style.crease_angle)
+        .filter(|edge| !on_silhouette.contains(&(edge.a, edge.b)))
+        .collect::<Vec<_>>();
+
+    let boundaries = mesh.boundary_edges().collect::<Vec<_>>();
+
+    let mut svg = String::new();
+
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = camera.hsize,
+        h = camera.vsize
+    )
+    .unwrap();
+
+    let groups = [
+        ("silhouette", style.silhouette, silhouettes),
+        ("crease", style.crease, creases),
+        ("boundary", style.boundary, boundaries),
+    ];
+
+    for (class, stroke, edges) in groups.iter() {
+        let stroke = match stroke {
+            Some(stroke) => stroke,
+            None => continue,
+        };
+
+        write!(
+            svg,
+            "<g class=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"",
+            class,
+            hex(stroke.color),
+            stroke.width
+        )
+        .unwrap();
+
+        if let Some((dash, gap)) = stroke.dash {
+            write!(svg, " stroke-dasharray=\"{} {}\"", dash, gap).unwrap();
+        }
+
+        writeln!(svg, ">").unwrap();
+
+        for edge in edges.iter() {
+            for polyline in visible_polylines(camera, world, mesh, edge) {
+                let points = polyline
+                    .iter()
+                    .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                writeln!(svg, "<polyline points=\"{}\"/>", points).unwrap();
+            }
+        }
+
+        writeln!(svg, "</g>").unwrap();
+    }
+
+    writeln!(svg, "</svg>").unwrap();
+
+    svg
+}
+
+/// samples the edge about once per pixel and keeps the runs of samples that
+/// a ray from the camera reaches unobstructed; the edge is straight on
+/// screen too, so each run only needs its ends
+fn visible_polylines(
+    camera: &Camera,
+    world: &World,
+    mesh: &Adjacency,
+    edge: &Edge,
+) -> Vec<Vec<Point2<f32>>> {
+    let (a, b) = mesh.endpoints(edge);
+    let eye = camera.eye();
+
+    let steps = match (camera.project(a), camera.project(b)) {
+        (Some((ax, ay, _)), Some((bx, by, _))) => {
+            ((bx - ax).abs().max((by - ay).abs()).ceil() as usize).clamp(1, 4 * camera.hsize)
+        }
+        _ => 4 * camera.hsize,
+    };
+
+    let mut polylines = vec![];
+    let mut current: Vec<Point2<f32>> = vec![];
+
+    for step in 0..=steps {
+        let point = a + (b - a) * (step as f32 / steps as f32);
+
+        let projected = camera
+            .project(point)
+            .filter(|_| world.is_visible_from(eye, point));
+
+        match projected {
+            Some((x, y, _)) if current.len() > 1 => current[1] = Point2::new(x, y),
+            Some((x, y, _)) => current.push(Point2::new(x, y)),
+            None if current.len() > 1 => polylines.push(std::mem::take(&mut current)),
+            None => current.clear(),
+        }
+    }
+
+    if current.len() > 1 {
+        polylines.push(current);
+    }
+
+    polylines
+}
+
+fn hex(color: Color) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.x),
+        channel(color.y),
+        channel(color.z)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane::Plane;
+    use crate::triangle::Triangle;
+    use nalgebra::{Matrix4, Point3};
+
+    fn facing_the_origin() -> Camera {
+        let mut camera = Camera::new(100, 100, std::f32::consts::FRAC_PI_2);
+        camera.transform = Camera::view_transforms(
+            Point3::new(0.0, 0.0, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        camera
+    }
+
+    fn far_below_floor() -> World {
+        let mut floor = Plane::new();
+        floor.transform = Matrix4::new_translation(&Vector3::new(0.0, -100.0, 0.0));
+        World::with_floor(floor)
+    }
+
+    fn triangle_at(z: f32, size: f32) -> Triangle {
+        Triangle::new(
+            Point3::new(0.0, size, z),
+            Point3::new(-size, -size, z),
+            Point3::new(size, -size, z),
+        )
+    }
+
+    #[test]
+    fn colors_are_written_as_hex() {
+        assert_eq!(hex(Vector3::new(1.0, 0.5, 0.0)), "#ff8000");
+        assert_eq!(hex(Vector3::new(2.0, -1.0, 0.2)), "#ff0033");
+    }
+
+    #[test]
+    fn the_boundary_of_a_lone_triangle_is_visible() {
+        let mesh = Adjacency::new(&[triangle_at(0.0, 1.0)]);
+        let mut world = far_below_floor();
+        world.objects.push(Box::new(triangle_at(0.0, 1.0)));
+
+        let svg = export_svg(&facing_the_origin(), &world, &mesh, &SvgStyle::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("stroke-dasharray=\"4 2\""));
+        assert_eq!(svg.matches("<polyline").count(), 3);
+    }
+
+    #[test]
+    fn edges_behind_other_objects_are_clipped() {
+        let camera = facing_the_origin();
+        let mesh = Adjacency::new(&[triangle_at(0.0, 1.0)]);
+        let mut world = far_below_floor();
+        world.objects.push(Box::new(triangle_at(0.0, 1.0)));
+        world
+            .objects
+            .push(Box::new(triangle_at(camera.eye().z / 2.0, 10.0)));
+
+        let svg = export_svg(&camera, &world, &mesh, &SvgStyle::default());
+        assert_eq!(svg.matches("<polyline").count(), 0);
+    }
+
+    #[test]
+    fn kinds_of_edges_can_be_left_out() {
+        let mesh = Adjacency::new(&[triangle_at(0.0, 1.0)]);
+        let world = far_below_floor();
+        let style = SvgStyle {
+            boundary: None,
+            ..Default::default()
+        };
+
+        let svg = export_svg(&facing_the_origin(), &world, &mesh, &style);
+        assert!(!svg.contains("class=\"boundary\""));
+        assert_eq!(svg.matches("<polyline").count(), 0);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::material::Color;
+use nalgebra::{Point2, Point3, Vector3};
+use std::f32::consts::PI;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+/// An image sampled with `(u, v)` in [0, 1], `v` pointing up the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    pub filter: Filter,
+    pub wrap: Wrap,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Result<Self, String> {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            return Err(format!(
+                "texture of {}x{} needs {} pixels, got {}",
+                width,
+                height,
+                width * height,
+                pixels.len()
+            ));
+        }
+
+        Ok(Texture {
+            width,
+            height,
+            pixels,
+            filter: Filter::Bilinear,
+            wrap: Wrap::Repeat,
+        })
+    }
+
+    /// loads a PNG or JPEG (or anything else `image` can decode)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = image::open(path).map_err(|e| format!("{}", e))?;
+
+        Texture::from_image(&image)
+    }
+
+    pub fn from_image(image: &image::DynamicImage) -> Result<Self, String> {
+        let rgb = image.to_rgb8();
+        let (width, height) = rgb.dimensions();
+
+        let pixels = rgb
+            .pixels()
+            .map(|p| {
+                Vector3::new(
+                    p[0] as f32 / 255.0,
+                    p[1] as f32 / 255.0,
+                    p[2] as f32 / 255.0,
+                )
+            })
+            .collect();
+
+        Texture::new(width as usize, height as usize, pixels)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn sample(&self, uv: Point2<f32>) -> Color {
+        // texel centers sit at half-integer coordinates
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (1.0 - uv.y) * self.height as f32 - 0.5;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.round() as i64, y.round() as i64),
+            Filter::Bilinear => {
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let fx = x - x0;
+                let fy = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = self.texel(x0, y0).lerp(&self.texel(x0 + 1, y0), fx);
+                let bottom = self.texel(x0, y0 + 1).lerp(&self.texel(x0 + 1, y0 + 1), fx);
+
+                top.lerp(&bottom, fy)
+            }
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let x = wrap(x, self.width as i64, self.wrap);
+        let y = wrap(y, self.height as i64, self.wrap);
+
+        self.pixels[y * self.width + x]
+    }
+}
+
+fn wrap(i: i64, size: i64, mode: Wrap) -> usize {
+    let wrapped = match mode {
+        Wrap::Repeat => i.rem_euclid(size),
+        Wrap::ClampToEdge => i.max(0).min(size - 1),
+        Wrap::MirroredRepeat => {
+            let period = i.rem_euclid(2 * size);
+            if period < size {
+                period
+            } else {
+                2 * size - 1 - period
+            }
+        }
+    };
+
+    wrapped as usize
+}
+
+/// How a point on a surface is turned into texture coordinates.
+/// `Vertex` uses the coordinates interpolated from the mesh, falling back
+/// to `Planar` for shapes that have none.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UvMapping {
+    Vertex,
+    Spherical,
+    Planar,
+    Cylindrical,
+    Cubic,
+}
+
+impl UvMapping {
+    pub fn uv_at(self, point: Point3<f32>, surface_uv: Option<Point2<f32>>) -> Point2<f32> {
+        match self {
+            UvMapping::Vertex => surface_uv.unwrap_or_else(|| planar_map(point)),
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cubic => cubic_map(point),
+        }
+    }
+}
+
+fn spherical_map(point: Point3<f32>) -> Point2<f32> {
+    let theta = point.x.atan2(point.z);
+    let radius = point.coords.magnitude();
+    let phi = (point.y / radius).acos();
+    let raw_u = theta / (2.0 * PI);
+
+    Point2::new(1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+fn planar_map(point: Point3<f32>) -> Point2<f32> {
+    Point2::new(point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+fn cylindrical_map(point: Point3<f32>) -> Point2<f32> {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+
+    Point2::new(1.0 - (raw_u + 0.5), point.y.rem_euclid(1.0))
+}
+
+/// every face of the cube shows the whole texture
+fn cubic_map(point: Point3<f32>) -> Point2<f32> {
+    let abs_x = point.x.abs();
+    let abs_y = point.y.abs();
+    let abs_z = point.z.abs();
+    let coord = |c: f32| c.rem_euclid(2.0) / 2.0;
+
+    if abs_x >= abs_y && abs_x >= abs_z {
+        if point.x > 0.0 {
+            Point2::new(coord(1.0 - point.z), coord(point.y + 1.0))
+        } else {
+            Point2::new(coord(point.z + 1.0), coord(point.y + 1.0))
+        }
+    } else if abs_y >= abs_z {
+        if point.y > 0.0 {
+            Point2::new(coord(point.x + 1.0), coord(1.0 - point.z))
+        } else {
+            Point2::new(coord(point.x + 1.0), coord(point.z + 1.0))
+        }
+    } else if point.z > 0.0 {
+        Point2::new(coord(point.x + 1.0), coord(point.y + 1.0))
+    } else {
+        Point2::new(coord(1.0 - point.x), coord(point.y + 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn assert_uv(actual: Point2<f32>, u: f32, v: f32) {
+        assert!(
+            (actual.x - u).abs() < 0.0001 && (actual.y - v).abs() < 0.0001,
+            "expected ({}, {}), got {:?}",
+            u,
+            v,
+            actual
+        );
+    }
+
+    fn two_by_two() -> Texture {
+        Texture::new(
+            2,
+            2,
+            vec![
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(1.0, 1.0, 1.0),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn a_texture_needs_one_pixel_per_texel() {
+        assert!(Texture::new(2, 2, vec![Vector3::new(0.0, 0.0, 0.0)]).is_err());
+    }
+
+    #[test]
+    fn nearest_filtering_picks_the_closest_texel() {
+        let mut texture = two_by_two();
+        texture.filter = Filter::Nearest;
+        assert_eq!(
+            texture.sample(Point2::new(0.25, 0.75)),
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            texture.sample(Point2::new(0.75, 0.75)),
+            Vector3::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            texture.sample(Point2::new(0.25, 0.25)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn bilinear_filtering_blends_neighboring_texels() {
+        let mut texture = two_by_two();
+        texture.wrap = Wrap::ClampToEdge;
+        assert_eq!(
+            texture.sample(Point2::new(0.25, 0.75)),
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            texture.sample(Point2::new(0.5, 0.75)),
+            Vector3::new(0.5, 0.5, 0.0)
+        );
+        assert_eq!(
+            texture.sample(Point2::new(0.5, 0.5)),
+            Vector3::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn wrap_modes() {
+        assert_eq!(wrap(-1, 4, Wrap::Repeat), 3);
+        assert_eq!(wrap(5, 4, Wrap::Repeat), 1);
+        assert_eq!(wrap(-1, 4, Wrap::ClampToEdge), 0);
+        assert_eq!(wrap(5, 4, Wrap::ClampToEdge), 3);
+        assert_eq!(wrap(-1, 4, Wrap::MirroredRepeat), 0);
+        assert_eq!(wrap(5, 4, Wrap::MirroredRepeat), 2);
+    }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        let inputs = vec![
+            (Point3::new(0.0, 0.0, -1.0), 0.0, 0.5),
+            (Point3::new(1.0, 0.0, 0.0), 0.25, 0.5),
+            (Point3::new(0.0, 0.0, 1.0), 0.5, 0.5),
+            (Point3::new(-1.0, 0.0, 0.0), 0.75, 0.5),
+            (Point3::new(0.0, 1.0, 0.0), 0.5, 1.0),
+            (Point3::new(0.0, -1.0, 0.0), 0.5, 0.0),
+            (
+                Point3::new(2.0_f32.sqrt() / 2.0, 2.0_f32.sqrt() / 2.0, 0.0),
+                0.25,
+                0.75,
+            ),
+        ];
+
+        for (point, u, v) in inputs {
+            assert_uv(UvMapping::Spherical.uv_at(point, None), u, v);
+        }
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        let inputs = vec![
+            (Point3::new(0.25, 0.0, 0.5), 0.25, 0.5),
+            (Point3::new(0.25, 0.0, -0.25), 0.25, 0.75),
+            (Point3::new(0.25, 0.5, -0.25), 0.25, 0.75),
+            (Point3::new(1.25, 0.0, 0.5), 0.25, 0.5),
+            (Point3::new(0.25, 0.0, -1.75), 0.25, 0.25),
+            (Point3::new(1.0, 0.0, -1.0), 0.0, 0.0),
+            (Point3::new(0.0, 0.0, 0.0), 0.0, 0.0),
+        ];
+
+        for (point, u, v) in inputs {
+            assert_uv(UvMapping::Planar.uv_at(point, None), u, v);
+        }
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        let inputs = vec![
+            (Point3::new(0.0, 0.0, -1.0), 0.0, 0.0),
+            (Point3::new(0.0, 0.5, -1.0), 0.0, 0.5),
+            (Point3::new(0.0, 1.0, -1.0), 0.0, 0.0),
+            (Point3::new(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), 0.125, 0.5),
+            (Point3::new(1.0, 0.5, 0.0), 0.25, 0.5),
+            (Point3::new(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), 0.375, 0.5),
+            (Point3::new(0.0, -0.25, 1.0), 0.5, 0.75),
+            (Point3::new(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), 0.625, 0.5),
+            (Point3::new(-1.0, 1.25, 0.0), 0.75, 0.25),
+            (Point3::new(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), 0.875, 0.5),
+        ];
+
+        for (point, u, v) in inputs {
+            assert_uv(UvMapping::Cylindrical.uv_at(point, None), u, v);
+        }
+    }
+
+    #[test]
+    fn uv_mapping_the_faces_of_a_cube() {
+        let inputs = vec![
+            (Point3::new(-0.5, 0.5, 1.0), 0.25, 0.75),
+            (Point3::new(0.5, -0.5, 1.0), 0.75, 0.25),
+            (Point3::new(0.5, 0.5, -1.0), 0.25, 0.75),
+            (Point3::new(-0.5, -0.5, -1.0), 0.75, 0.25),
+            (Point3::new(-1.0, 0.5, -0.5), 0.25, 0.75),
+            (Point3::new(-1.0, -0.5, 0.5), 0.75, 0.25),
+            (Point3::new(1.0, 0.5, 0.5), 0.25, 0.75),
+            (Point3::new(1.0, -0.5, -0.5), 0.75, 0.25),
+            (Point3::new(-0.5, 1.0, -0.5), 0.25, 0.75),
+            (Point3::new(0.5, 1.0, 0.5), 0.75, 0.25),
+            (Point3::new(-0.5, -1.0, 0.5), 0.25, 0.75),
+            (Point3::new(0.5, -1.0, -0.5), 0.75, 0.25),
+        ];
+
+        for (point, u, v) in inputs {
+            assert_uv(UvMapping::Cubic.uv_at(point, None), u, v);
+        }
+    }
+
+    #[test]
+    fn vertex_mapping_prefers_the_surface_coordinates() {
+        let uv = Point2::new(0.3, 0.6);
+        assert_eq!(
+            UvMapping::Vertex.uv_at(Point3::new(0.0, 0.0, 0.0), Some(uv)),
+            uv
+        );
+    }
+}
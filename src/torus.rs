@@ -0,0 +1,356 @@
+use crate::bounding_box::{BoundingBox, AABB};
+use crate::emitter::Emitter;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point2, Point3, Vector3};
+use std::f32::consts::PI;
+
+/// how many segments an emissive torus is sampled as, around and across
+const EMITTER_SEGMENTS: (usize, usize) = (32, 12);
+
+/// The ring around the y axis whose tube, of `minor_radius`, is centred
+/// `major_radius` from the axis in the xz plane: an O-ring.
+///
+/// Hits carry texture coordinates: u goes once around the axis from +x
+/// towards +z, v once around the tube from its outer edge.
+#[derive(Clone, Debug)]
+pub struct Torus {
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub transform: Matrix4<f32>,
+    pub material: Material,
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self {
+            major_radius: 1.0,
+            minor_radius: 0.25,
+            transform: Matrix4::identity(),
+            material: Material::default(),
+        }
+    }
+}
+
+impl Torus {
+    /// a point on the surface, `around` the axis and `across` the tube,
+    /// both in turns
+    fn point_at(&self, around: f32, across: f32) -> Point3<f32> {
+        let (around, across) = (around * 2.0 * PI, across * 2.0 * PI);
+        let distance = self.major_radius + self.minor_radius * across.cos();
+
+        Point3::new(
+            distance * around.cos(),
+            self.minor_radius * across.sin(),
+            distance * around.sin(),
+        )
+    }
+}
+
+impl BoundingBox for Torus {
+    fn bounding_box(&self) -> AABB {
+        let outer = self.major_radius + self.minor_radius;
+
+        AABB {
+            min: Point3::new(-outer, -self.minor_radius, -outer),
+            max: Point3::new(outer, self.minor_radius, outer),
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn transform(&self) -> Matrix4<f32> {
+        self.transform
+    }
+
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        self.transform = parent * self.transform;
+    }
+
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
+
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
+    }
+
+    /// solves the torus's quartic in double precision, starting the ray
+    /// where it passes nearest the centre so the coefficients stay small
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let d = ray.direction.map(f64::from);
+        let dd = d.dot(&d);
+
+        if dd < f64::EPSILON {
+            return vec![];
+        }
+
+        let shift = -ray.origin.coords.map(f64::from).dot(&d) / dd;
+        let o = ray.origin.coords.map(f64::from) + d * shift;
+        let outer = f64::from(self.major_radius + self.minor_radius);
+
+        // misses the sphere around the torus
+        if o.dot(&o) > outer * outer {
+            return vec![];
+        }
+
+        let major = f64::from(self.major_radius).powi(2);
+        let minor = f64::from(self.minor_radius).powi(2);
+        let e = o.dot(&o) - major - minor;
+        let f = o.dot(&d);
+
+        let coefficients = [
+            dd * dd,
+            4.0 * dd * f,
+            2.0 * dd * e + 4.0 * f * f + 4.0 * major * d.y * d.y,
+            4.0 * f * e + 8.0 * major * o.y * d.y,
+            e * e - 4.0 * major * (minor - o.y * o.y),
+        ];
+
+        let mut ts = real_roots(&coefficients)
+            .into_iter()
+            .map(|t| (t + shift) as f32)
+            .collect::<Vec<_>>();
+        ts.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        ts.into_iter()
+            .map(|t| {
+                let point = ray.position(t);
+                let around = point.z.atan2(point.x) / (2.0 * PI);
+                let from_tube = (point.x.powi(2) + point.z.powi(2)).sqrt() - self.major_radius;
+                let across = point.y.atan2(from_tube) / (2.0 * PI);
+
+                Intersection::with_uv(t, self, around.rem_euclid(1.0), across.rem_euclid(1.0))
+            })
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let param =
+            point.coords.norm_squared() - self.major_radius.powi(2) - self.minor_radius.powi(2);
+
+        Vector3::new(
+            point.x * param,
+            point.y * (param + 2.0 * self.major_radius.powi(2)),
+            point.z * param,
+        )
+    }
+
+    fn uv_at(&self, hit: &Intersection) -> Option<Point2<f32>> {
+        Some(Point2::new(hit.u, hit.v))
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        if !self.material.is_emissive() {
+            return vec![];
+        }
+
+        let (around, across) = EMITTER_SEGMENTS;
+        let at = |i: usize, j: usize| {
+            self.transform
+                .transform_point(&self.point_at(i as f32 / around as f32, j as f32 / across as f32))
+        };
+
+        (0..around)
+            .flat_map(|i| (0..across).map(move |j| (i, j)))
+            .flat_map(|(i, j)| {
+                vec![
+                    Emitter::triangle(
+                        at(i, j),
+                        at(i + 1, j),
+                        at(i + 1, j + 1),
+                        self.material.emission(),
+                    ),
+                    Emitter::triangle(
+                        at(i, j),
+                        at(i + 1, j + 1),
+                        at(i, j + 1),
+                        self.material.emission(),
+                    ),
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+/// the real roots of the polynomial with `coefficients`, highest power
+/// first, in no particular order
+///
+/// Between neighbouring roots of the derivative the polynomial is monotonic,
+/// so each of those intervals holds at most one root, found by bisection;
+/// unlike the closed-form quartic this doesn't lose roots to cancellation.
+fn real_roots(coefficients: &[f64]) -> Vec<f64> {
+    let largest = coefficients.iter().fold(0.0, |m: f64, c| m.max(c.abs()));
+    let start = coefficients
+        .iter()
+        .position(|c| c.abs() > largest * 1e-12)
+        .unwrap_or(coefficients.len());
+    let c = &coefficients[start..];
+
+    match c.len() {
+        0 | 1 => vec![],
+        2 => vec![-c[1] / c[0]],
+        3 => {
+            let discriminant = c[1] * c[1] - 4.0 * c[0] * c[2];
+
+            if discriminant < 0.0 {
+                return vec![];
+            }
+
+            // the stable form, avoiding subtracting nearly equal numbers
+            let q = -0.5 * (c[1] + c[1].signum() * discriminant.sqrt());
+
+            if q == 0.0 {
+                vec![0.0]
+            } else {
+                vec![q / c[0], c[2] / q]
+            }
+        }
+        n => {
+            let degree = n - 1;
+            let derivative = c[..degree]
+                .iter()
+                .enumerate()
+                .map(|(i, c)| c * (degree - i) as f64)
+                .collect::<Vec<_>>();
+
+            // Cauchy's bound on the roots' size
+            let bound = 1.0
+                + c[1..]
+                    .iter()
+                    .fold(0.0, |m: f64, ci| m.max((ci / c[0]).abs()));
+
+            let mut ends = real_roots(&derivative)
+                .into_iter()
+                .filter(|x| x.abs() < bound)
+                .collect::<Vec<_>>();
+            ends.push(-bound);
+            ends.push(bound);
+            ends.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+            let value = |x: f64| c.iter().fold(0.0, |sum, c| sum * x + c);
+
+            ends.windows(2)
+                .filter_map(|ends| bisect(&value, ends[0], ends[1]))
+                .collect()
+        }
+    }
+}
+
+/// the root of `f` between `low` and `high`, if it changes sign there
+fn bisect(f: &impl Fn(f64) -> f64, mut low: f64, mut high: f64) -> Option<f64> {
+    let (mut f_low, f_high) = (f(low), f(high));
+
+    if f_low == 0.0 {
+        return Some(low);
+    }
+
+    if f_low.signum() == f_high.signum() {
+        return None;
+    }
+
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+
+        if middle <= low || middle >= high {
+            break;
+        }
+
+        let f_middle = f(middle);
+
+        if f_middle.signum() == f_low.signum() {
+            low = middle;
+            f_low = f_middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_the_roots_of_polynomials() {
+        let mut roots = real_roots(&[1.0, -10.0, 35.0, -50.0, 24.0]);
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(&[1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < 1e-9, "{:?}", roots);
+        }
+
+        assert!(real_roots(&[1.0, 0.0, 1.0]).is_empty());
+        assert!(real_roots(&[1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn a_ray_through_the_middle_of_a_torus_crosses_it_four_times() {
+        let torus = Torus::default();
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let ts = torus
+            .local_intersect(ray)
+            .iter()
+            .map(|hit| hit.t)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ts.len(), 4);
+        for (t, expected) in ts.iter().zip(&[3.75, 4.25, 5.75, 6.25]) {
+            assert!((t - expected).abs() < 1e-4, "{:?}", ts);
+        }
+    }
+
+    #[test]
+    fn a_ray_through_the_hole_misses_a_torus() {
+        let torus = Torus::default();
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert!(torus.local_intersect(ray).is_empty());
+    }
+
+    #[test]
+    fn a_ray_far_away_still_finds_the_torus() {
+        let torus = Torus::default();
+        let ray = Ray::new(Point3::new(1.0, 1000.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let ts = torus
+            .local_intersect(ray)
+            .iter()
+            .map(|hit| hit.t)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ts.len(), 2);
+        assert!((ts[0] - 999.75).abs() < 1e-3, "{:?}", ts);
+    }
+
+    #[test]
+    fn the_normal_on_a_torus_points_out_of_the_tube() {
+        let torus = Torus::default();
+
+        for (point, normal) in &[
+            (Point3::new(1.25, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+            (Point3::new(0.75, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0)),
+            (Point3::new(0.0, 0.25, 1.0), Vector3::new(0.0, 1.0, 0.0)),
+        ] {
+            assert!((torus.local_normal_at(*point).normalize() - normal).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn texture_coordinates_wrap_around_the_torus() {
+        let torus = Torus::default();
+        let ray = Ray::new(Point3::new(0.0, 5.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+        let hits = torus.local_intersect(ray);
+
+        // the top of the tube, a quarter of the way around
+        let uv = torus.uv_at(&hits[0]).unwrap();
+        assert!((uv - Point2::new(0.25, 0.25)).norm() < 1e-4, "{}", uv);
+    }
+}
@@ -1,11 +1,12 @@
 use crate::bounding_box::{BoundingBox, AABB};
+use crate::emitter::Emitter;
 use crate::intersection::Intersection;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::shape::Shape;
-use nalgebra::{Matrix4, Point3, Projective3, Transform, Vector3};
+use crate::shape::{normal_to_world, world_to_object, Shape};
+use nalgebra::{Matrix4, Point2, Point3, Projective3, Transform, Vector3};
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Triangle {
     pub p1: Point3<f32>,
     pub p2: Point3<f32>,
@@ -15,13 +16,18 @@ pub struct Triangle {
     pub normal: Vector3<f32>,
     pub transform: Matrix4<f32>,
     pub material: Material,
+    pub uvs: Option<[Point2<f32>; 3]>,
 }
 
 impl Triangle {
     pub fn new(p1: Point3<f32>, p2: Point3<f32>, p3: Point3<f32>) -> Triangle {
         let e1 = p2 - p1;
         let e2 = p3 - p1;
-        let normal = e2.cross(&e1).normalize();
+        // zero-area triangles have no normal; they're never hit either
+        let normal = e2
+            .cross(&e1)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::zeros);
         let transform = Matrix4::identity();
         let material = Material::default();
 
@@ -34,63 +40,73 @@ impl Triangle {
             normal,
             transform,
             material,
+            uvs: None,
         }
     }
-}
 
-impl BoundingBox for Triangle {
-    fn bounding_box(&self) -> AABB {
-        let mut aabb = AABB::default();
+    /// the triangle with its vertices moved by its own transform and then
+    /// `transform`, keeping its material and texture coordinates; mirroring
+    /// transforms swap two corners so the winding, and with it the outward
+    /// normal, still faces out
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Triangle {
+        let matrix = transform * self.transform;
+        let transform: Projective3<f32> = Transform::from_matrix_unchecked(matrix);
+        let mirrored = matrix
+            .fixed_slice::<nalgebra::U3, nalgebra::U3>(0, 0)
+            .determinant()
+            < 0.0;
 
-        for point in &[self.p1, self.p2, self.p3] {
-            if point.x <= aabb.min.x {
-                aabb.min.x = point.x;
-            }
-            if point.y <= aabb.min.y {
-                aabb.min.y = point.y;
-            }
-            if point.z <= aabb.min.z {
-                aabb.min.z = point.z;
-            }
-            if point.x >= aabb.max.x {
-                aabb.max.x = point.x;
-            }
-            if point.y >= aabb.max.y {
-                aabb.max.y = point.y;
-            }
-            if point.y >= aabb.max.y {
-                aabb.max.y = point.y;
-            }
+        let (p2, p3) = if mirrored {
+            (self.p3, self.p2)
+        } else {
+            (self.p2, self.p3)
+        };
+        let uvs = match self.uvs {
+            Some([uv1, uv2, uv3]) if mirrored => Some([uv1, uv3, uv2]),
+            uvs => uvs,
+        };
+
+        Triangle {
+            material: self.material.clone(),
+            uvs,
+            ..Triangle::new(
+                transform.transform_point(&self.p1),
+                transform.transform_point(&p2),
+                transform.transform_point(&p3),
+            )
         }
+    }
+
+    pub fn with_uvs(mut self, uv1: Point2<f32>, uv2: Point2<f32>, uv3: Point2<f32>) -> Self {
+        self.uvs = Some([uv1, uv2, uv3]);
+        self
+    }
+}
 
-        aabb
+impl BoundingBox for Triangle {
+    fn bounding_box(&self) -> AABB {
+        AABB::around(vec![self.p1, self.p2, self.p3])
     }
 }
 
 impl Shape for Triangle {
-    fn material(&self) -> Material {
-        self.material
+    fn material(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix4<f32> {
         self.transform
     }
 
-    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
-        let transformed_transform: Projective3<f32> =
-            Transform::from_matrix_unchecked(self.transform);
-        let local_point: Point3<f32> = transformed_transform.inverse_transform_point(&point);
-        let local_normal = self.local_normal_at(local_point);
-        let transposed_transform: Projective3<f32> =
-            Transform::from_matrix_unchecked(self.transform.transpose());
-        let world_normal = transposed_transform.inverse_transform_vector(&local_normal);
-        let mut world_normal_homogeneous = world_normal.to_homogeneous();
+    /// moves the vertices themselves, as meshes are measured in world space
+    fn transform_by(&mut self, parent: &Matrix4<f32>) {
+        *self = self.transformed(parent);
+    }
 
-        world_normal_homogeneous.w = 0.0;
+    fn normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        let local_point = world_to_object(&self.transform, point);
 
-        Vector3::from_homogeneous(world_normal_homogeneous)
-            .unwrap()
-            .normalize()
+        normal_to_world(&self.transform, self.local_normal_at(local_point))
     }
 
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
@@ -118,7 +134,7 @@ impl Shape for Triangle {
                     vec![]
                 } else {
                     let t = f * self.e2.dot(&origin_cross_e1);
-                    vec![Intersection::new(t, self)]
+                    vec![Intersection::with_uv(t, self, u, v)]
                 }
             }
         }
@@ -126,6 +142,79 @@ impl Shape for Triangle {
     fn local_normal_at(&self, _point: Point3<f32>) -> Vector3<f32> {
         self.normal
     }
+
+    // `normal` is (p3 - p1) x (p2 - p1), which points inwards for the
+    // counter-clockwise winding STL and OBJ files use
+    fn outward_normal_at(&self, point: Point3<f32>) -> Vector3<f32> {
+        -self.normal_at(point)
+    }
+
+    fn tangent_at(&self, _point: Point3<f32>) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let [uv1, uv2, uv3] = self.uvs?;
+        let duv1 = uv2 - uv1;
+        let duv2 = uv3 - uv1;
+        let det = duv1.x * duv2.y - duv1.y * duv2.x;
+
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let tangent = (self.e1 * duv2.y - self.e2 * duv1.y) / det;
+        let bitangent = (self.e2 * duv1.x - self.e1 * duv2.x) / det;
+
+        let transform: Projective3<f32> = Transform::from_matrix_unchecked(self.transform);
+
+        Some((
+            transform.transform_vector(&tangent).try_normalize(1e-8)?,
+            transform.transform_vector(&bitangent).try_normalize(1e-8)?,
+        ))
+    }
+
+    fn emitters(&self) -> Vec<Emitter> {
+        if !self.material.is_emissive() {
+            return vec![];
+        }
+
+        let transform: Projective3<f32> = Transform::from_matrix_unchecked(self.transform);
+
+        Emitter::triangle(
+            transform.transform_point(&self.p1),
+            transform.transform_point(&self.p2),
+            transform.transform_point(&self.p3),
+            self.material.emission(),
+        )
+        .into_iter()
+        .collect()
+    }
+
+    fn edge_distance(&self, hit: &Intersection) -> Option<f32> {
+        let transform: Projective3<f32> = Transform::from_matrix_unchecked(self.transform);
+        let p1 = transform.transform_point(&self.p1);
+        let p2 = transform.transform_point(&self.p2);
+        let p3 = transform.transform_point(&self.p3);
+
+        let double_area = (p2 - p1).cross(&(p3 - p1)).magnitude();
+
+        if double_area < f32::EPSILON {
+            return Some(0.0);
+        }
+
+        // each barycentric coordinate scaled by the height above the opposite edge
+        let w = 1.0 - hit.u - hit.v;
+
+        Some(
+            (w * double_area / (p3 - p2).magnitude())
+                .min(hit.u * double_area / (p3 - p1).magnitude())
+                .min(hit.v * double_area / (p2 - p1).magnitude()),
+        )
+    }
+
+    fn uv_at(&self, hit: &Intersection) -> Option<Point2<f32>> {
+        self.uvs.map(|[uv1, uv2, uv3]| {
+            let w = 1.0 - hit.u - hit.v;
+            Point2::from(uv1.coords * w + uv2.coords * hit.u + uv3.coords * hit.v)
+        })
+    }
 }
 
 #[cfg(test)]
@@ -225,4 +314,137 @@ mod tests {
         assert_eq!(xs.len(), 1);
         assert_eq!(xs[0].t, 2.0);
     }
+
+    #[test]
+    fn an_intersection_with_a_triangle_stores_u_and_v() {
+        let t = Triangle::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        );
+
+        let r = Ray::new(Point3::new(-0.2, 0.3, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(r);
+        assert!((xs[0].u - 0.45).abs() < 0.0001);
+        assert!((xs[0].v - 0.25).abs() < 0.0001);
+    }
+
+    #[test]
+    fn the_tangent_frame_follows_the_texture_coordinates() {
+        let t = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        );
+        assert!(t.tangent_at(Point3::new(0.5, 0.5, 0.0)).is_none());
+
+        let t = t.with_uvs(
+            Point2::new(0.0, 0.0),
+            Point2::new(0.0, 1.0),
+            Point2::new(1.0, 0.0),
+        );
+        let (tangent, bitangent) = t.tangent_at(Point3::new(0.5, 0.5, 0.0)).unwrap();
+        assert_eq!(tangent, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(bitangent, Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn vertex_uvs_are_interpolated_across_the_triangle() {
+        let t = Triangle::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        )
+        .with_uvs(
+            Point2::new(0.5, 1.0),
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+        );
+
+        let r = Ray::new(Point3::new(-0.2, 0.3, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(r);
+        let uv = t.uv_at(&xs[0]).unwrap();
+        assert!((uv.x - 0.4).abs() < 0.0001);
+        assert!((uv.y - 0.3).abs() < 0.0001);
+    }
+
+    #[test]
+    fn the_distance_from_a_hit_to_the_nearest_edge() {
+        let t = Triangle::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        );
+
+        let r = Ray::new(Point3::new(0.0, 0.25, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(r);
+        assert!((t.edge_distance(&xs[0]).unwrap() - 0.25).abs() < 0.0001);
+
+        let r = Ray::new(Point3::new(0.4, 0.5, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(r);
+        let expected = 0.1 / 2.0_f32.sqrt();
+        assert!((t.edge_distance(&xs[0]).unwrap() - expected).abs() < 0.0001);
+    }
+
+    #[test]
+    fn the_outward_normal_follows_counter_clockwise_winding() {
+        let t = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        );
+        let n = t.outward_normal_at(Point3::new(0.2, 0.2, 0.0));
+        assert_eq!(n, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn a_degenerate_triangle_has_no_normal() {
+        let t = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(2.0, 2.0, 2.0),
+        );
+        assert_eq!(t.normal, Vector3::zeros());
+    }
+
+    #[test]
+    fn transforming_a_triangle_moves_its_vertices() {
+        let t = Triangle::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        )
+        .transformed(&Matrix4::new_translation(&Vector3::new(0.0, 0.0, 2.0)));
+
+        assert_eq!(t.p1, Point3::new(0.0, 1.0, 2.0));
+        assert_eq!(t.e1, Vector3::new(-1.0, -1.0, 0.0));
+        assert_eq!(t.normal, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn a_triangles_bounds_hold_its_corners() {
+        let bounds = Triangle::new(
+            Point3::new(0.0, 1.0, -2.0),
+            Point3::new(-1.0, 0.0, 3.0),
+            Point3::new(1.0, 0.0, 0.0),
+        )
+        .bounding_box();
+
+        assert_eq!(bounds.min, Point3::new(-1.0, 0.0, -2.0));
+        assert_eq!(bounds.max, Point3::new(1.0, 1.0, 3.0));
+    }
+
+    #[test]
+    fn a_triangle_keeps_its_own_transform_when_moved_by_a_group() {
+        let mut t = Triangle::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        );
+        t.transform = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 2.0));
+        t.transform_by(&Matrix4::new_scaling(2.0));
+
+        assert_eq!(t.p1, Point3::new(0.0, 2.0, 4.0));
+        assert_eq!(t.transform, Matrix4::identity());
+    }
 }
@@ -0,0 +1,348 @@
+use crate::adjacency::Adjacency;
+use crate::legend::Legend;
+use crate::material::Color;
+use crate::triangle::Triangle;
+use nalgebra::{Point3, Vector3};
+
+/// What's wrong with a mesh, as found by welding its vertices and walking
+/// the edges between faces. Triangles are numbered in file order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    pub triangles: usize,
+    /// after welding
+    pub vertices: usize,
+    /// edges shared by more than two faces
+    pub non_manifold_edges: Vec<(Point3<f32>, Point3<f32>)>,
+    /// edges with a single face: holes and open rims
+    pub boundary_edges: Vec<(Point3<f32>, Point3<f32>)>,
+    /// triangles whose winding disagrees with the normal stored in the file
+    pub flipped_normals: Vec<usize>,
+    /// triangles with no area
+    pub degenerate_triangles: Vec<usize>,
+    /// pairs of triangles that cross each other without sharing a vertex
+    pub self_intersections: Vec<(usize, usize)>,
+    /// the most serious problem with each triangle, for highlighting
+    problems: Vec<Option<Problem>>,
+}
+
+/// Kinds of problem highlighted on faces, most serious first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Problem {
+    SelfIntersection,
+    NonManifold,
+    Boundary,
+    Flipped,
+}
+
+impl Problem {
+    const ALL: [Problem; 4] = [
+        Problem::SelfIntersection,
+        Problem::NonManifold,
+        Problem::Boundary,
+        Problem::Flipped,
+    ];
+
+    fn color(self) -> Color {
+        match self {
+            Problem::SelfIntersection => Vector3::new(0.9, 0.1, 0.1),
+            Problem::NonManifold => Vector3::new(0.9, 0.1, 0.9),
+            Problem::Boundary => Vector3::new(1.0, 0.55, 0.0),
+            Problem::Flipped => Vector3::new(0.4, 0.2, 0.9),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Problem::SelfIntersection => "self-intersecting",
+            Problem::NonManifold => "non-manifold edge",
+            Problem::Boundary => "open edge",
+            Problem::Flipped => "flipped normal",
+        }
+    }
+}
+
+impl ValidationReport {
+    /// `stored_normals` are the facet normals from the file, one per
+    /// triangle; zero normals are treated as unknown
+    pub fn new(triangles: &[Triangle], stored_normals: &[Vector3<f32>]) -> Self {
+        let mesh = Adjacency::new(triangles);
+        let mut problems = vec![None; triangles.len()];
+
+        let mut flag = |face: usize, problem: Problem| {
+            let slot: &mut Option<Problem> = &mut problems[face];
+            *slot = Some(slot.map_or(problem, |p| p.min(problem)));
+        };
+
+        let mut non_manifold_edges = vec![];
+        let mut boundary_edges = vec![];
+
+        for edge in mesh.edges.iter() {
+            let problem = match edge.faces.len() {
+                1 => {
+                    boundary_edges.push(mesh.endpoints(edge));
+                    Problem::Boundary
+                }
+                2 => continue,
+                _ => {
+                    non_manifold_edges.push(mesh.endpoints(edge));
+                    Problem::NonManifold
+                }
+            };
+
+            for face in edge.faces.iter() {
+                flag(*face, problem);
+            }
+        }
+
+        let degenerate_triangles = (0..mesh.faces.len())
+            .filter(|&face| mesh.face_normal(face).is_none())
+            .collect::<Vec<_>>();
+
+        let flipped_normals = stored_normals
+            .iter()
+            .enumerate()
+            .filter(|(face, stored)| match mesh.face_normal(*face) {
+                Some(normal) => normal.dot(stored) < 0.0,
+                None => false,
+            })
+            .map(|(face, _)| face)
+            .collect::<Vec<_>>();
+
+        for face in flipped_normals.iter() {
+            flag(*face, Problem::Flipped);
+        }
+
+        let self_intersections = self_intersections(&mesh);
+
+        for (a, b) in self_intersections.iter() {
+            flag(*a, Problem::SelfIntersection);
+            flag(*b, Problem::SelfIntersection);
+        }
+
+        ValidationReport {
+            triangles: triangles.len(),
+            vertices: mesh.vertices.len(),
+            non_manifold_edges,
+            boundary_edges,
+            flipped_normals,
+            degenerate_triangles,
+            self_intersections,
+            problems,
+        }
+    }
+
+    /// a closed, consistently wound surface that doesn't cross itself
+    pub fn is_valid(&self) -> bool {
+        self.non_manifold_edges.is_empty()
+            && self.boundary_edges.is_empty()
+            && self.flipped_normals.is_empty()
+            && self.degenerate_triangles.is_empty()
+            && self.self_intersections.is_empty()
+    }
+
+    /// the color to paint a triangle with, if anything is wrong with it
+    pub(crate) fn highlight(&self, triangle: usize) -> Option<Color> {
+        self.problems
+            .get(triangle)
+            .copied()
+            .flatten()
+            .map(Problem::color)
+    }
+
+    /// the key to the highlights found in this mesh
+    pub fn legend(&self) -> Legend {
+        Legend {
+            entries: Problem::ALL
+                .iter()
+                .filter(|problem| self.problems.contains(&Some(**problem)))
+                .map(|problem| (problem.color(), problem.label().to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// pairs of faces without a shared vertex that cross, found by sweeping
+/// their bounding boxes along x. Coplanar overlaps aren't detected.
+fn self_intersections(mesh: &Adjacency) -> Vec<(usize, usize)> {
+    let corners = |face: usize| {
+        let [a, b, c] = mesh.faces[face];
+        [mesh.vertices[a], mesh.vertices[b], mesh.vertices[c]]
+    };
+
+    let bounds = (0..mesh.faces.len())
+        .map(|face| {
+            let [a, b, c] = corners(face);
+            (a.inf(&b).inf(&c), a.sup(&b).sup(&c))
+        })
+        .collect::<Vec<_>>();
+
+    let mut order = (0..mesh.faces.len())
+        .filter(|&face| mesh.face_normal(face).is_some())
+        .collect::<Vec<_>>();
+    order.sort_by(|a, b| {
+        bounds[*a]
+            .0
+            .x
+            .partial_cmp(&bounds[*b].0.x)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut pairs = vec![];
+    let mut active: Vec<usize> = vec![];
+
+    for &face in order.iter() {
+        let (min, max) = bounds[face];
+        active.retain(|&other| bounds[other].1.x >= min.x);
+
+        for &other in active.iter() {
+            let (other_min, other_max) = bounds[other];
+
+            let overlap = (0..3).all(|i| min[i] <= other_max[i] && other_min[i] <= max[i]);
+            let shares_vertex = mesh.faces[face]
+                .iter()
+                .any(|v| mesh.faces[other].contains(v));
+
+            if overlap && !shares_vertex && triangles_cross(&corners(face), &corners(other)) {
+                pairs.push((face.min(other), face.max(other)));
+            }
+        }
+
+        active.push(face);
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
+
+fn triangles_cross(a: &[Point3<f32>; 3], b: &[Point3<f32>; 3]) -> bool {
+    let edges_cross = |from: &[Point3<f32>; 3], to: &[Point3<f32>; 3]| {
+        (0..3).any(|i| segment_hits_triangle(from[i], from[(i + 1) % 3], to))
+    };
+
+    edges_cross(a, b) || edges_cross(b, a)
+}
+
+/// Möller–Trumbore restricted to the segment from `p` to `q`
+fn segment_hits_triangle(p: Point3<f32>, q: Point3<f32>, [a, b, c]: &[Point3<f32>; 3]) -> bool {
+    let direction = q - p;
+    let e1 = b - a;
+    let e2 = c - a;
+
+    let pvec = direction.cross(&e2);
+    let det = e1.dot(&pvec);
+
+    if det.abs() <= f32::EPSILON * e1.norm() * e2.norm() * direction.norm() {
+        return false;
+    }
+
+    let tvec = p - a;
+    let u = tvec.dot(&pvec) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+
+    let qvec = tvec.cross(&e1);
+    let v = direction.dot(&qvec) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+
+    let t = e2.dot(&qvec) / det;
+    (0.0..=1.0).contains(&t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a unit tetrahedron, wound counter-clockwise seen from outside
+    fn tetrahedron() -> Vec<Triangle> {
+        let o = Point3::new(0.0, 0.0, 0.0);
+        let x = Point3::new(1.0, 0.0, 0.0);
+        let y = Point3::new(0.0, 1.0, 0.0);
+        let z = Point3::new(0.0, 0.0, 1.0);
+
+        vec![
+            Triangle::new(o, y, x),
+            Triangle::new(o, x, z),
+            Triangle::new(o, z, y),
+            Triangle::new(x, y, z),
+        ]
+    }
+
+    fn outward_normals(triangles: &[Triangle]) -> Vec<Vector3<f32>> {
+        triangles.iter().map(|t| -t.normal).collect()
+    }
+
+    #[test]
+    fn a_closed_mesh_is_valid() {
+        let triangles = tetrahedron();
+        let report = ValidationReport::new(&triangles, &outward_normals(&triangles));
+        assert!(report.is_valid(), "{:?}", report);
+        assert_eq!(report.vertices, 4);
+        assert!(report.legend().entries.is_empty());
+    }
+
+    #[test]
+    fn missing_faces_leave_open_edges() {
+        let mut triangles = tetrahedron();
+        triangles.pop();
+
+        let report = ValidationReport::new(&triangles, &[]);
+        assert_eq!(report.boundary_edges.len(), 3);
+        assert_eq!(report.highlight(0), Some(Problem::Boundary.color()));
+    }
+
+    #[test]
+    fn normals_that_disagree_with_the_winding_are_flipped() {
+        let triangles = tetrahedron();
+        let mut normals = outward_normals(&triangles);
+        normals[2] = -normals[2];
+        normals[3] = Vector3::zeros();
+
+        let report = ValidationReport::new(&triangles, &normals);
+        assert_eq!(report.flipped_normals, vec![2]);
+    }
+
+    #[test]
+    fn a_third_face_on_an_edge_is_non_manifold() {
+        let mut triangles = tetrahedron();
+        triangles.push(Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.5, -1.0, 0.0),
+        ));
+
+        let report = ValidationReport::new(&triangles, &[]);
+        assert_eq!(report.non_manifold_edges.len(), 1);
+        assert_eq!(report.highlight(4), Some(Problem::NonManifold.color()));
+    }
+
+    #[test]
+    fn zero_area_triangles_are_degenerate() {
+        let mut triangles = tetrahedron();
+        triangles.push(Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+        ));
+
+        let report = ValidationReport::new(&triangles, &[]);
+        assert_eq!(report.degenerate_triangles, vec![4]);
+    }
+
+    #[test]
+    fn crossing_triangles_are_self_intersections() {
+        let mut triangles = tetrahedron();
+        triangles.push(Triangle::new(
+            Point3::new(0.2, 0.2, -1.0),
+            Point3::new(0.3, 0.2, 2.0),
+            Point3::new(0.2, 0.3, 2.0),
+        ));
+
+        let report = ValidationReport::new(&triangles, &[]);
+        assert_eq!(report.self_intersections, vec![(0, 4), (3, 4)]);
+        assert_eq!(report.highlight(4), Some(Problem::SelfIntersection.color()));
+        assert_eq!(report.legend().entries[0].1, "self-intersecting");
+    }
+}
@@ -0,0 +1,478 @@
+use crate::bvh::Bvh;
+use crate::intersection::PreparedComputations;
+use crate::legend::Legend;
+use crate::material::Color;
+use crate::ramp::ColorRamp;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use nalgebra::Vector3;
+use std::sync::Arc;
+
+/// Replaces a material's color with an analysis of the surface, shaded
+/// as usual so the form still reads.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Visualization {
+    /// colors surfaces by how far they lean out over the build plate, from
+    /// green for walls and upward faces to yellow at `threshold`, and red
+    /// beyond it where a printer would need supports
+    Overhang {
+        build_direction: Vector3<f32>,
+        /// radians from vertical
+        threshold: f32,
+        /// height along `build_direction` of the build plate; faces resting
+        /// on it aren't overhangs. Rendering an STL fills it in from the
+        /// bottom of the mesh
+        bed_height: Option<f32>,
+    },
+    /// colors surfaces by the distance through the mesh behind them, from
+    /// the start of `ramp` at `min` to its end at `max`
+    Thickness { min: f32, max: f32, ramp: ColorRamp },
+    /// colors surfaces by their signed distance to a reference mesh, e.g. a
+    /// scan against its CAD model: a band of `in_tolerance` within
+    /// `tolerance` either side, then `ramp` from `-range` to `range`
+    Deviation {
+        reference: Arc<Bvh>,
+        tolerance: f32,
+        range: f32,
+        ramp: ColorRamp,
+        in_tolerance: Color,
+    },
+    /// colors surfaces by the print layer they fall in, counting layers of
+    /// `layer_height` up `build_direction` from the bottom of `heights`, as
+    /// a slicer preview. Rendering an STL fills `heights` in from the mesh
+    Layers {
+        build_direction: Vector3<f32>,
+        layer_height: f32,
+        colors: LayerColors,
+        heights: Option<(f32, f32)>,
+    },
+}
+
+/// How consecutive layers are told apart.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayerColors {
+    Alternating(Color, Color),
+    /// from the ramp's start at the bottom layer to its end at the top
+    Graded(ColorRamp),
+}
+
+impl Visualization {
+    /// an overhang analysis with the common 45° limit
+    pub fn overhang(build_direction: Vector3<f32>) -> Self {
+        Visualization::Overhang {
+            build_direction,
+            threshold: 45.0_f32.to_radians(),
+            bed_height: None,
+        }
+    }
+
+    /// a thickness heatmap from red for walls `min` thick or thinner to blue
+    /// at `max` and beyond
+    pub fn thickness(min: f32, max: f32) -> Self {
+        let mut ramp = ColorRamp::heat();
+        ramp.stops.reverse();
+
+        Visualization::Thickness { min, max, ramp }
+    }
+
+    /// alternating bands of `layer_height` up the z axis
+    pub fn layers(layer_height: f32) -> Self {
+        Visualization::Layers {
+            build_direction: Vector3::new(0.0, 0.0, 1.0),
+            layer_height,
+            colors: LayerColors::Alternating(
+                Vector3::new(0.85, 0.85, 0.85),
+                Vector3::new(0.35, 0.45, 0.6),
+            ),
+            heights: None,
+        }
+    }
+
+    /// a deviation heatmap from blue for surfaces `range` inside the
+    /// reference to red for `range` outside it, green within `tolerance`
+    pub(crate) fn deviation(reference: Bvh, tolerance: f32, range: f32) -> Self {
+        Visualization::Deviation {
+            reference: Arc::new(reference),
+            tolerance,
+            range,
+            ramp: ColorRamp::heat(),
+            in_tolerance: Vector3::new(0.2, 0.8, 0.3),
+        }
+    }
+
+    /// `object` is the world's object that was hit, e.g. a whole mesh rather
+    /// than the triangle in `comps`
+    pub(crate) fn color_at(&self, object: &dyn Shape, comps: &PreparedComputations) -> Color {
+        match self {
+            Visualization::Overhang {
+                build_direction,
+                threshold,
+                bed_height,
+            } => {
+                let up = build_direction.normalize();
+                let normal = comps.outward_normalv;
+
+                let on_bed = bed_height.is_some_and(|bed| {
+                    (comps.point.coords.dot(&up) - bed).abs() < 1e-3 * bed.abs().max(1.0)
+                });
+
+                let angle = overhang_angle(normal, up);
+
+                if on_bed {
+                    supported_ramp().at(0.0)
+                } else if angle > *threshold {
+                    overhang_color()
+                } else {
+                    supported_ramp().at(angle / threshold)
+                }
+            }
+            Visualization::Thickness { min, max, ramp } => {
+                let thickness = thickness_at(object, comps).unwrap_or(f32::INFINITY);
+
+                // an empty range is a step at `min` rather than a division by zero
+                if max > min {
+                    ramp.at((thickness - min) / (max - min))
+                } else if thickness <= *min {
+                    ramp.at(0.0)
+                } else {
+                    ramp.at(1.0)
+                }
+            }
+            Visualization::Deviation {
+                reference,
+                tolerance,
+                range,
+                ramp,
+                in_tolerance,
+            } => match reference.signed_distance(comps.point) {
+                Some(distance) if distance.abs() <= *tolerance => *in_tolerance,
+                Some(distance) => ramp.at((distance + range) / (2.0 * range)),
+                None => ramp.at(1.0),
+            },
+            Visualization::Layers {
+                build_direction,
+                layer_height,
+                colors,
+                heights,
+            } => {
+                let (bottom, top) = heights.unwrap_or((0.0, 0.0));
+                let height = comps.point.coords.dot(&build_direction.normalize());
+                let layer = ((height - bottom) / layer_height).floor();
+
+                match colors {
+                    LayerColors::Alternating(even, odd) => {
+                        if layer.rem_euclid(2.0) < 1.0 {
+                            *even
+                        } else {
+                            *odd
+                        }
+                    }
+                    LayerColors::Graded(ramp) => {
+                        let layers = ((top - bottom) / layer_height).ceil().max(1.0);
+                        ramp.at(layer / (layers - 1.0).max(1.0))
+                    }
+                }
+            }
+        }
+    }
+
+    /// the key to the colors, for drawing over the image
+    pub fn legend(&self) -> Legend {
+        match self {
+            Visualization::Overhang { threshold, .. } => {
+                let degrees = threshold.to_degrees().round();
+
+                Legend {
+                    entries: vec![
+                        (supported_ramp().at(0.0), "0°".to_string()),
+                        (supported_ramp().at(1.0), format!("{}°", degrees)),
+                        (overhang_color(), format!(">{}°", degrees)),
+                    ],
+                }
+            }
+            Visualization::Thickness { min, max, ramp } => {
+                let steps = 4;
+
+                Legend {
+                    entries: (0..=steps)
+                        .map(|i| {
+                            let t = i as f32 / steps as f32;
+                            (ramp.at(t), number(min + (max - min) * t))
+                        })
+                        .collect(),
+                }
+            }
+            Visualization::Deviation {
+                tolerance,
+                range,
+                ramp,
+                in_tolerance,
+                ..
+            } => Legend {
+                entries: vec![
+                    (ramp.at(0.0), number(-range)),
+                    (ramp.at(0.25), number(-range / 2.0)),
+                    (*in_tolerance, format!("±{}", number(*tolerance))),
+                    (ramp.at(0.75), format!("+{}", number(range / 2.0))),
+                    (ramp.at(1.0), format!("+{}", number(*range))),
+                ],
+            },
+            Visualization::Layers {
+                layer_height,
+                colors,
+                heights,
+                ..
+            } => {
+                let (bottom, top) = heights.unwrap_or((0.0, 0.0));
+                let span = |from: f32, to: f32| format!("{}-{}", number(from), number(to));
+
+                let entries = match colors {
+                    LayerColors::Alternating(even, odd) => vec![
+                        (*even, span(bottom, bottom + layer_height)),
+                        (
+                            *odd,
+                            span(bottom + layer_height, bottom + 2.0 * layer_height),
+                        ),
+                    ],
+                    LayerColors::Graded(ramp) => vec![
+                        (ramp.at(0.0), number(bottom)),
+                        (ramp.at(0.5), number((bottom + top) / 2.0)),
+                        (ramp.at(1.0), number(top)),
+                    ],
+                };
+
+                Legend { entries }
+            }
+        }
+    }
+}
+
+/// how far a ray into the surface, against its outward normal, travels
+/// before it leaves `object`; `None` for open meshes it never leaves.
+/// Other objects, the floor and caps don't count as the far wall
+fn thickness_at(object: &dyn Shape, comps: &PreparedComputations) -> Option<f32> {
+    let inward = -comps.outward_normalv.normalize();
+    let ray = Ray::new(comps.point, inward);
+
+    object
+        .intersect(&ray)
+        .into_iter()
+        .map(|i| i.t)
+        .filter(|&t| t > 1e-4)
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+}
+
+/// a short label, without trailing zeros
+fn number(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// how far past vertical a surface faces downwards: 0 for walls and
+/// anything facing up, a right angle for a flat ceiling
+fn overhang_angle(normal: Vector3<f32>, up: Vector3<f32>) -> f32 {
+    let down = -normal.normalize().dot(&up);
+
+    down.clamp(0.0, 1.0).asin()
+}
+
+fn supported_ramp() -> ColorRamp {
+    ColorRamp::new(vec![
+        Vector3::new(0.2, 0.8, 0.3),
+        Vector3::new(1.0, 0.85, 0.1),
+    ])
+}
+
+fn overhang_color() -> Color {
+    Vector3::new(0.9, 0.1, 0.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::Group;
+    use crate::intersection::Intersection;
+    use crate::ray::Ray;
+    use crate::triangle::Triangle;
+    use crate::world::World;
+    use nalgebra::Point3;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn overhang_angles_are_measured_from_vertical() {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        assert_close(overhang_angle(Vector3::new(1.0, 0.0, 0.0), up), 0.0);
+        assert_close(overhang_angle(Vector3::new(0.0, 1.0, 0.0), up), 0.0);
+        assert_close(overhang_angle(Vector3::new(0.0, -1.0, 0.0), up), FRAC_PI_2);
+        assert_close(
+            overhang_angle(Vector3::new(1.0, -1.0, 0.0), up),
+            FRAC_PI_2 / 2.0,
+        );
+    }
+
+    // a counter-clockwise triangle at height y, facing down
+    fn ceiling(y: f32) -> Triangle {
+        Triangle::new(
+            Point3::new(-1.0, y, -1.0),
+            Point3::new(1.0, y, -1.0),
+            Point3::new(0.0, y, 1.0),
+        )
+    }
+
+    fn color_of(visualization: &Visualization, triangle: &Triangle) -> Color {
+        let ray = Ray::new(Point3::new(0.0, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = Intersection::hit(triangle.intersect(&ray)).unwrap();
+        let comps = hit.prepare_computations(&ray);
+
+        visualization.color_at(triangle, &comps)
+    }
+
+    #[test]
+    fn downward_faces_are_overhangs_unless_they_rest_on_the_bed() {
+        let visualization = Visualization::Overhang {
+            build_direction: Vector3::new(0.0, 1.0, 0.0),
+            threshold: 45.0_f32.to_radians(),
+            bed_height: Some(0.0),
+        };
+
+        assert_eq!(color_of(&visualization, &ceiling(2.0)), overhang_color());
+        assert_eq!(
+            color_of(&visualization, &ceiling(0.0)),
+            supported_ramp().at(0.0)
+        );
+    }
+
+    #[test]
+    fn thickness_is_the_distance_to_the_far_side() {
+        let visualization = Visualization::thickness(0.0, 1.0);
+
+        // the bottom and top of a slab half a unit thick
+        let bottom = ceiling(0.0);
+        let top = Triangle::new(
+            Point3::new(-1.0, 0.5, -1.0),
+            Point3::new(0.0, 0.5, 1.0),
+            Point3::new(1.0, 0.5, -1.0),
+        );
+
+        let slab = Group::new(vec![bottom.clone(), top]);
+
+        let ray = Ray::new(Point3::new(0.0, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = Intersection::hit(slab.intersect(&ray)).unwrap();
+        let comps = hit.prepare_computations(&ray);
+
+        assert_close(thickness_at(&slab, &comps).unwrap(), 0.5);
+        assert_eq!(
+            visualization.color_at(&slab, &comps),
+            ColorRamp::heat().at(0.5)
+        );
+
+        // an open mesh, even with another object in the way
+        let world = World {
+            objects: vec![Box::new(bottom), Box::new(ceiling(0.25))],
+            ..Default::default()
+        };
+
+        let hit = Intersection::hit(world.objects[0].intersect(&ray)).unwrap();
+        let comps = hit.prepare_computations(&ray);
+        assert_eq!(thickness_at(world.objects[0].as_ref(), &comps), None);
+    }
+
+    #[test]
+    fn an_empty_thickness_range_is_a_step() {
+        let visualization = Visualization::thickness(0.25, 0.25);
+        let ray = Ray::new(Point3::new(0.0, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let floor = ceiling(0.0);
+        let hit = Intersection::hit(floor.intersect(&ray)).unwrap();
+        let comps = hit.prepare_computations(&ray);
+
+        assert_eq!(
+            visualization.color_at(&floor, &comps),
+            ColorRamp::heat().at(0.0)
+        );
+    }
+
+    #[test]
+    fn deviation_is_banded_by_the_tolerance() {
+        let reference = Bvh::new(&[ceiling(0.0)]);
+        let visualization = Visualization::deviation(reference, 0.1, 1.0);
+
+        // hits on the ceiling at y = 0.05 and 0.5, which are above the
+        // downward facing reference and so inside it
+        let near = color_of(&visualization, &ceiling(0.05));
+        let far = color_of(&visualization, &ceiling(0.5));
+
+        assert_eq!(near, Vector3::new(0.2, 0.8, 0.3));
+        assert_eq!(far, ColorRamp::heat().at(0.25));
+    }
+
+    #[test]
+    fn layers_alternate_up_the_build_direction() {
+        let visualization = Visualization::Layers {
+            build_direction: Vector3::new(0.0, 1.0, 0.0),
+            layer_height: 0.2,
+            colors: LayerColors::Alternating(
+                Vector3::new(1.0, 1.0, 1.0),
+                Vector3::new(0.0, 0.0, 0.0),
+            ),
+            heights: Some((0.1, 2.0)),
+        };
+
+        assert_eq!(
+            color_of(&visualization, &ceiling(0.2)),
+            Vector3::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            color_of(&visualization, &ceiling(0.4)),
+            Vector3::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            color_of(&visualization, &ceiling(0.6)),
+            Vector3::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn graded_layers_run_the_ramp_from_bottom_to_top() {
+        let visualization = Visualization::Layers {
+            build_direction: Vector3::new(0.0, 1.0, 0.0),
+            layer_height: 0.5,
+            colors: LayerColors::Graded(ColorRamp::heat()),
+            heights: Some((0.0, 2.0)),
+        };
+
+        assert_eq!(
+            color_of(&visualization, &ceiling(0.25)),
+            ColorRamp::heat().at(0.0)
+        );
+        assert_eq!(
+            color_of(&visualization, &ceiling(1.75)),
+            ColorRamp::heat().at(1.0)
+        );
+        assert_eq!(visualization.legend().entries[2].1, "2");
+    }
+
+    #[test]
+    fn labels_drop_trailing_zeros() {
+        assert_eq!(number(0.5), "0.5");
+        assert_eq!(number(2.0), "2");
+        assert_eq!(number(1.25), "1.25");
+        assert_eq!(number(-0.0), "0");
+    }
+
+    #[test]
+    fn the_overhang_legend_names_the_threshold() {
+        let legend = Visualization::overhang(Vector3::new(0.0, 0.0, 1.0)).legend();
+        assert_eq!(legend.entries.len(), 3);
+        assert_eq!(legend.entries[2].1, ">45°");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::gbuffer::GBuffer;
+use crate::material::Color;
+use nalgebra::Vector3;
+
+/// Draws the edges of the triangles seen in each pixel at a constant width
+/// on screen. Only the nearest surface is ever drawn, so edges hidden behind
+/// other triangles stay hidden.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wireframe {
+    pub color: Color,
+    /// in pixels
+    pub width: f32,
+    /// keeps the shaded surfaces under the lines; otherwise they are
+    /// painted with `fill`
+    pub shaded: bool,
+    pub fill: Color,
+}
+
+impl Default for Wireframe {
+    fn default() -> Self {
+        Wireframe {
+            color: Vector3::new(0.0, 0.0, 0.0),
+            width: 1.5,
+            shaded: true,
+            fill: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Wireframe {
+    pub(crate) fn draw(&self, canvas: &mut Canvas, buffers: &GBuffer, camera: &Camera) {
+        for y in 0..buffers.height {
+            for x in 0..buffers.width {
+                let surface = match buffers.surface_at(x, y) {
+                    Some(surface) => surface,
+                    None => continue,
+                };
+
+                // how wide half a line is at the depth of the hit
+                let half_width = surface.depth * camera.pixel_size * self.width / 2.0;
+
+                match surface.edge_distance {
+                    Some(distance) if distance <= half_width => {
+                        canvas.write_pixel(x, y, self.color);
+                    }
+                    _ if !self.shaded => {
+                        canvas.write_pixel(x, y, self.fill);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbuffer::SurfaceSample;
+
+    fn surface(depth: f32, edge_distance: Option<f32>) -> Option<SurfaceSample> {
+        Some(SurfaceSample {
+            edge_distance,
+            ..SurfaceSample::at_depth(0, depth)
+        })
+    }
+
+    fn draw(wireframe: Wireframe) -> Vec<Color> {
+        // pixel_size is 0.5 for a 4 pixel wide, 90 degree camera
+        let camera = Camera::new(4, 1, std::f32::consts::FRAC_PI_2);
+        let mut buffers = GBuffer::new(4, 1);
+        buffers.write(0, 0, surface(2.0, Some(0.4)));
+        buffers.write(1, 0, surface(2.0, Some(0.6)));
+        buffers.write(2, 0, surface(4.0, Some(0.6)));
+        buffers.write(3, 0, surface(2.0, None));
+
+        let mut canvas = Canvas::new(4, 1);
+        let shaded = Vector3::new(0.5, 0.5, 0.5);
+
+        for x in 0..4 {
+            canvas.write_pixel(x, 0, shaded);
+        }
+
+        wireframe.draw(&mut canvas, &buffers, &camera);
+
+        (0..4).map(|x| canvas.pixel_at(x, 0)).collect()
+    }
+
+    #[test]
+    fn lines_keep_their_width_on_screen() {
+        let black = Vector3::new(0.0, 0.0, 0.0);
+        let shaded = Vector3::new(0.5, 0.5, 0.5);
+        let pixels = draw(Wireframe {
+            width: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(pixels, vec![black, shaded, black, shaded]);
+    }
+
+    #[test]
+    fn an_unshaded_wireframe_fills_the_surfaces() {
+        let black = Vector3::new(0.0, 0.0, 0.0);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let pixels = draw(Wireframe {
+            width: 1.0,
+            shaded: false,
+            ..Default::default()
+        });
+        assert_eq!(pixels, vec![black, white, black, white]);
+    }
+}
@@ -1,26 +1,85 @@
 use nalgebra::{Matrix4, Point3, Vector3};
 
 use std::cmp::Ordering;
+use std::f32::consts::PI;
 
+use crate::clip::{Cap, ClipPlane};
+use crate::emitter::{stratified_samples, Emitters};
+use crate::gbuffer::SurfaceSample;
 use crate::intersection::{Intersection, PreparedComputations};
 use crate::light::Light;
-use crate::material::Material;
+use crate::material::{Color, Material, ShadowCatcher};
 use crate::plane::Plane;
 use crate::ray::Ray;
 use crate::shape::Shape;
 use crate::sphere::Sphere;
 
+/// The color, coverage and geometry seen along a camera ray.
+pub struct Trace {
+    pub color: Color,
+    pub alpha: f32,
+    pub surface: Option<SurfaceSample>,
+}
+
 pub struct World {
     pub objects: Vec<Box<dyn Shape>>,
-    pub light: Light,
+    /// point lights, each casting its own shadows
+    pub lights: Vec<Light>,
+    /// emissive triangles, gathered by `collect_emitters`
+    pub emitters: Emitters,
+    /// shadow rays per hit spent on `emitters`
+    pub light_samples: usize,
+    /// shown where rays miss everything
+    pub background: Color,
+    /// leaves the background out of rendered images so they can be composited
+    pub transparent_background: bool,
+    /// cut away parts of clippable objects to show their insides
+    pub clip_planes: Vec<ClipPlane>,
+    /// fills the cut faces of closed meshes
+    pub cap: Cap,
 }
 
 impl World {
-    fn intersect(&self, ray: Ray) -> Vec<Intersection> {
+    /// the floor is left whole when clipping planes cut the rest of the world
+    pub fn with_floor(mut floor: Plane) -> Self {
+        floor.material.clippable = false;
+
+        let light = Light::point_light(Point3::new(16.0, 10.0, 25.0), Vector3::new(1.0, 1.0, 1.0));
+
+        let mut world = World::new(light);
+        world.objects.push(Box::new(floor));
+        world
+    }
+
+    /// an empty world lit by `light`
+    pub fn new(light: Light) -> Self {
+        World {
+            objects: vec![],
+            lights: vec![light],
+            emitters: Emitters::default(),
+            light_samples: 16,
+            background: Vector3::new(0.0, 0.0, 0.0),
+            transparent_background: false,
+            clip_planes: vec![],
+            cap: Cap::default(),
+        }
+    }
+
+    /// call after the objects are in place so emissive meshes can light the scene
+    pub fn collect_emitters(&mut self) {
+        self.emitters = Emitters::new(
+            self.objects
+                .iter()
+                .flat_map(|object| object.emitters())
+                .collect(),
+        );
+    }
+
+    pub(crate) fn intersect(&self, ray: Ray) -> Vec<Intersection> {
         let mut intersections: Vec<Intersection> = self
             .objects
             .iter()
-            .flat_map(|object| object.intersect(&ray))
+            .flat_map(|object| self.unclipped(object.as_ref(), &ray, object.intersect(&ray)))
             .collect();
 
         intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal));
@@ -28,39 +87,346 @@ impl World {
         intersections
     }
 
-    fn shade_hit(&self, comps: PreparedComputations) -> Vector3<f32> {
-        Light::lighting(
-            comps.object.material(),
-            self.light,
+    /// `material` is the object's material with its pattern already
+    /// evaluated, `shadows` whether each of the lights is blocked
+    fn shade_hit(
+        &self,
+        comps: &PreparedComputations,
+        material: &Material,
+        shadows: &[bool],
+    ) -> Vector3<f32> {
+        let surface = self
+            .lights
+            .iter()
+            .zip(shadows)
+            .map(|(light, &shadowed)| {
+                Light::lighting(
+                    material.clone(),
+                    *light,
+                    comps.over_point,
+                    comps.eyev,
+                    comps.normalv,
+                    shadowed,
+                )
+            })
+            .fold(Vector3::new(0.0, 0.0, 0.0), |a, b| a + b);
+
+        surface + self.emitter_lighting(material, comps) + material.emission()
+    }
+
+    fn emitter_lighting(&self, material: &Material, comps: &PreparedComputations) -> Vector3<f32> {
+        let black = Vector3::new(0.0, 0.0, 0.0);
+
+        if self.emitters.is_empty() || self.light_samples == 0 {
+            return black;
+        }
+
+        // ambient was already added by the point lights
+        let direct = Material {
+            ambient: 0.0,
+            ..material.clone()
+        };
+
+        let total = stratified_samples(self.light_samples, comps.over_point)
+            .into_iter()
+            .filter_map(|(u0, u1, u2)| self.emitters.sample(u0, u1, u2))
+            .map(|sample| {
+                let v = sample.point - comps.over_point;
+                let distance_squared = v.magnitude_squared();
+                let lightv = v.normalize();
+                let cos_light = sample.normal.dot(&lightv).abs();
+
+                if cos_light <= 0.0 || self.is_shadowed_from(comps.over_point, sample.point) {
+                    return black;
+                }
+
+                // radiance over the emitter's area becomes irradiance at the hit;
+                // the 1/pi keeps reflected light from exceeding the emitter's own glow
+                let intensity = sample.weighted_radiance * (cos_light / (distance_squared * PI));
+                let light = Light::point_light(sample.point, intensity);
+
+                Light::lighting(
+                    direct.clone(),
+                    light,
+                    comps.over_point,
+                    comps.eyev,
+                    comps.normalv,
+                    false,
+                )
+            })
+            .fold(black, |a, b| a + b);
+
+        total / self.light_samples as f32
+    }
+
+    /// what a camera ray sees, with the background composited in unless it
+    /// is transparent; alpha is then 0 where the background shows through,
+    /// and between 0 and 1 on shadow catchers
+    pub fn trace(&self, ray: Ray) -> Trace {
+        let (color, alpha, surface) = self.shade_ray(ray);
+
+        if self.transparent_background {
+            Trace {
+                color,
+                alpha,
+                surface,
+            }
+        } else {
+            Trace {
+                color: color * alpha + self.background * (1.0 - alpha),
+                alpha: 1.0,
+                surface,
+            }
+        }
+    }
+
+    fn shade_ray(&self, ray: Ray) -> (Vector3<f32>, f32, Option<SurfaceSample>) {
+        let black = Vector3::new(0.0, 0.0, 0.0);
+
+        // a cut face is seen where the ray enters the mesh, in front of the
+        // inside of the mesh that it actually hits
+        let hit = self
+            .objects
+            .iter()
+            .enumerate()
+            .filter_map(|(index, object)| {
+                let hit = Intersection::hit(self.unclipped(
+                    object.as_ref(),
+                    &ray,
+                    object.intersect(&ray),
+                ))?;
+                let comps = hit.prepare_computations(&ray);
+                let cap = self.cap_in_front(object.as_ref(), &ray, &comps);
+                Some((index, hit, comps, cap))
+            })
+            .min_by(|(_, a, _, cap_a), (_, b, _, cap_b)| {
+                let a = cap_a.map_or(a.t, |(t, _)| t);
+                let b = cap_b.map_or(b.t, |(t, _)| t);
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            });
+
+        if let Some((index, i, comps, cap)) = hit {
+            let material = comps.object.material();
+
+            if let Some(catcher) = material.shadow_catcher {
+                (black, self.shadow_catcher_opacity(&catcher, &comps), None)
+            } else if let Some((t, plane)) = cap {
+                self.shade_cap(index, &comps, material, &ray, t, &plane)
+            } else {
+                let mut material = material.surface_at(&comps);
+
+                if let Some(visualization) = &material.visualization {
+                    let color = visualization.color_at(self.objects[index].as_ref(), &comps);
+                    material.set_color(color);
+                }
+
+                let shadows = self.shadows(comps.over_point);
+
+                let surface = SurfaceSample {
+                    object: index,
+                    primitive: self.objects[index].primitive_index(&i),
+                    depth: comps.t,
+                    position: comps.point,
+                    normal: comps.normalv,
+                    edge_distance: i.object.edge_distance(&i),
+                    albedo: material.color,
+                    shadowed: shadows.iter().all(|&s| s),
+                };
+
+                (
+                    self.shade_hit(&comps, &material, &shadows),
+                    1.0,
+                    Some(surface),
+                )
+            }
+        } else {
+            (black, 0.0, None)
+        }
+    }
+
+    /// drops the intersections in the parts of `object` cut away by the
+    /// clipping planes
+    fn unclipped<'a>(
+        &self,
+        object: &dyn Shape,
+        ray: &Ray,
+        intersections: Vec<Intersection<'a>>,
+    ) -> Vec<Intersection<'a>> {
+        if self.clip_planes.is_empty() || !object.material().clippable {
+            return intersections;
+        }
+
+        intersections
+            .into_iter()
+            .filter(|i| {
+                let point = ray.position(i.t);
+                !self.clip_planes.iter().any(|plane| plane.clips(point))
+            })
+            .collect()
+    }
+
+    /// where a ray whose first hit on `object` is inside it last crossed a
+    /// clipping plane, which is where it entered the cut face
+    fn cap_in_front(
+        &self,
+        object: &dyn Shape,
+        ray: &Ray,
+        hit: &PreparedComputations,
+    ) -> Option<(f32, ClipPlane)> {
+        if self.clip_planes.is_empty() || !object.material().clippable || !hit.inside {
+            return None;
+        }
+
+        self.clip_planes
+            .iter()
+            .filter_map(|plane| plane.entry(ray).map(|entry| (entry, *plane)))
+            .filter(|(entry, _)| *entry > 0.0 && *entry < hit.t)
+            .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    /// shades the cut face where the ray entered a mesh through a clipping
+    /// plane, lit as if the plane were part of the hit object
+    fn shade_cap(
+        &self,
+        index: usize,
+        hit: &PreparedComputations,
+        material: &Material,
+        ray: &Ray,
+        t: f32,
+        plane: &ClipPlane,
+    ) -> (Vector3<f32>, f32, Option<SurfaceSample>) {
+        let point = ray.position(t);
+        let normalv = plane.normal;
+        let color = self.cap.color_at(point, plane);
+
+        let comps = PreparedComputations {
+            t,
+            object: hit.object,
+            point,
+            eyev: -ray.direction,
+            normalv,
+            inside: false,
+            outward_normalv: normalv,
+            over_point: point + normalv * 0.00001,
+            uv: None,
+        };
+
+        let mut material = Material {
+            pattern: None,
+            visualization: None,
+            ..material.clone()
+        };
+        material.set_color(color);
+
+        let shadows = self.shadows(comps.over_point);
+
+        let surface = SurfaceSample {
+            object: index,
+            primitive: None,
+            depth: t,
+            position: point,
+            normal: normalv,
+            edge_distance: None,
+            albedo: color,
+            shadowed: shadows.iter().all(|&s| s),
+        };
+
+        (
+            self.shade_hit(&comps, &material, &shadows),
+            1.0,
+            Some(surface),
+        )
+    }
+
+    fn shadow_catcher_opacity(&self, catcher: &ShadowCatcher, comps: &PreparedComputations) -> f32 {
+        let shadows = self.shadows(comps.over_point);
+        let shadow = if shadows.is_empty() {
+            0.0
+        } else {
+            shadows.iter().filter(|&&s| s).count() as f32 / shadows.len() as f32
+        };
+
+        let occlusion = self.ambient_occlusion(
             comps.over_point,
-            comps.eyev,
             comps.normalv,
-            self.is_shadowed(comps.over_point),
-        )
+            catcher.occlusion_distance,
+            catcher.occlusion_samples,
+        );
+
+        (1.0 - (1.0 - shadow) * (1.0 - occlusion)) * catcher.opacity
     }
 
-    pub fn color_at(&self, ray: Ray) -> Vector3<f32> {
-        let intersections = self.intersect(ray);
-        let intersection = Intersection::hit(intersections);
+    /// the fraction of cosine-weighted hemisphere rays that hit something
+    /// within `distance`
+    fn ambient_occlusion(
+        &self,
+        point: Point3<f32>,
+        normal: Vector3<f32>,
+        distance: f32,
+        samples: usize,
+    ) -> f32 {
+        if samples == 0 {
+            return 0.0;
+        }
 
-        if let Some(i) = intersection {
-            let comps = i.prepare_computations(&ray);
-            self.shade_hit(comps)
+        let helper = if normal.x.abs() > 0.9 {
+            Vector3::y()
         } else {
-            Vector3::new(0.0, 0.0, 0.0) // black
+            Vector3::x()
+        };
+        let tangent = normal.cross(&helper).normalize();
+        let bitangent = normal.cross(&tangent);
+
+        let occluded = stratified_samples(samples, point)
+            .into_iter()
+            .filter(|(_, u1, u2)| {
+                let r = u1.sqrt();
+                let phi = 2.0 * PI * u2;
+                let direction = tangent * (r * phi.cos())
+                    + bitangent * (r * phi.sin())
+                    + normal * (1.0 - u1).sqrt();
+
+                let hit = Intersection::hit(self.intersect(Ray::new(point, direction)));
+
+                matches!(hit, Some(h) if h.t < distance)
+            })
+            .count();
+
+        occluded as f32 / samples as f32
+    }
+
+    /// whether a ray from `eye` reaches `point` before hitting anything else
+    pub fn is_visible_from(&self, eye: Point3<f32>, point: Point3<f32>) -> bool {
+        let v = point - eye;
+        let distance = v.magnitude();
+        let ray = Ray::new(eye, v / distance);
+
+        // a point on a surface is hit by its own surface, give or take rounding
+        match Intersection::hit(self.intersect(ray)) {
+            Some(h) => h.t >= distance * 0.999,
+            None => true,
         }
     }
 
-    fn is_shadowed(&self, point: Point3<f32>) -> bool {
-        let v = self.light.position - point;
+    /// whether each light is blocked from `point`
+    fn shadows(&self, point: Point3<f32>) -> Vec<bool> {
+        self.lights
+            .iter()
+            .map(|light| self.is_shadowed_from(point, light.position))
+            .collect()
+    }
+
+    fn is_shadowed_from(&self, point: Point3<f32>, light_position: Point3<f32>) -> bool {
+        let v = light_position - point;
         let distance = v.magnitude();
         let direction = v.normalize();
         let ray = Ray::new(point, direction);
         let intersections = self.intersect(ray);
         let hit = Intersection::hit(intersections);
 
+        // a sample on an emitter must not be shadowed by the emitter itself
         if let Some(h) = hit {
-            h.t < distance
+            h.t < distance * 0.9999
         } else {
             false
         }
@@ -69,7 +435,6 @@ impl World {
 
 impl Default for World {
     fn default() -> Self {
-        let light = Light::point_light(Point3::new(16.0, 10.0, 25.0), Vector3::new(1.0, 1.0, 1.0));
         let m = Material {
             color: Vector3::new(0.1, 1.0, 0.1),
             diffuse: 0.7,
@@ -106,13 +471,215 @@ impl Default for World {
         // s3.transform = Matrix4::new_translation(&Vector3::new(3.5, 0.5, -0.5))
         //     * Matrix4::new_nonuniform_scaling(&Vector3::new(2.5, 2.5, 2.5));
 
-        let floor = Box::new(Plane::new());
+        // objects: vec![Arc::new(s1), Arc::new(s2), Arc::new(floor)],
+        World::with_floor(Plane::new())
+    }
+}
 
-        World {
-            // objects: vec![Arc::new(s1), Arc::new(s2), Arc::new(floor)],
-            // objects: vec![Arc::new(floor)],
-            objects: vec![floor],
-            light,
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clip::Hatch;
+    use crate::cube::Cube;
+    use crate::triangle::Triangle;
+
+    fn catcher_world(occlusion_samples: usize) -> World {
+        let mut floor = Plane::new();
+        floor.material.shadow_catcher = Some(ShadowCatcher {
+            occlusion_samples,
+            ..Default::default()
+        });
+        let mut world = World::with_floor(floor);
+        world.lights = vec![Light::point_light(
+            Point3::new(0.0, 10.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        )];
+        world.transparent_background = true;
+        world
+    }
+
+    fn blocker_above_origin() -> Triangle {
+        Triangle::new(
+            Point3::new(-1.0, 0.5, -1.0),
+            Point3::new(1.0, 0.5, -1.0),
+            Point3::new(0.0, 0.5, 1.0),
+        )
+    }
+
+    fn dark_world() -> World {
+        let mut world = World::with_floor(Plane::new());
+        world.lights = vec![Light::point_light(
+            Point3::new(0.0, 10.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+        )];
+        world
+    }
+
+    fn looking_down_at_the_floor() -> Ray {
+        Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0))
+    }
+
+    #[test]
+    fn an_emissive_surface_glows_without_any_light() {
+        let mut floor = Plane::new();
+        floor.material.emissive = Vector3::new(0.2, 0.4, 0.6);
+        floor.material.emission_strength = 0.5;
+        let mut world = World::with_floor(floor);
+        world.lights = vec![Light::point_light(
+            Point3::new(0.0, 10.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+        )];
+        let color = world.trace(looking_down_at_the_floor()).color;
+        assert!((color - Vector3::new(0.1, 0.2, 0.3)).norm() < 0.0001);
+    }
+
+    #[test]
+    fn an_emissive_triangle_lights_the_floor_below_it() {
+        let mut panel = Triangle::new(
+            Point3::new(-1.0, 2.0, -1.0),
+            Point3::new(1.0, 2.0, -1.0),
+            Point3::new(0.0, 2.0, 1.0),
+        );
+        panel.material.emissive = Vector3::new(1.0, 1.0, 1.0);
+        panel.material.emission_strength = 10.0;
+
+        let mut world = dark_world();
+        world.objects.push(Box::new(panel));
+
+        let unlit = world.trace(looking_down_at_the_floor()).color;
+        assert_eq!(unlit, Vector3::new(0.0, 0.0, 0.0));
+
+        world.collect_emitters();
+        let lit = world.trace(looking_down_at_the_floor()).color;
+        assert!(lit.x > 0.1);
+    }
+
+    #[test]
+    fn emitters_are_shadowed_by_other_objects() {
+        let mut panel = Triangle::new(
+            Point3::new(-1.0, 3.0, -1.0),
+            Point3::new(1.0, 3.0, -1.0),
+            Point3::new(0.0, 3.0, 1.0),
+        );
+        panel.material.emissive = Vector3::new(1.0, 1.0, 1.0);
+        panel.material.emission_strength = 10.0;
+
+        let blocker = Triangle::new(
+            Point3::new(-5.0, 2.0, -5.0),
+            Point3::new(5.0, 2.0, -5.0),
+            Point3::new(0.0, 2.0, 5.0),
+        );
+
+        let mut world = dark_world();
+        world.objects.push(Box::new(panel));
+        world.objects.push(Box::new(blocker));
+        world.collect_emitters();
+
+        let color = world.trace(looking_down_at_the_floor()).color;
+        assert_eq!(color, Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn an_unshadowed_shadow_catcher_is_transparent() {
+        let world = catcher_world(16);
+        let Trace { color, alpha, .. } = world.trace(looking_down_at_the_floor());
+        assert_eq!(color, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(alpha, 0.0);
+    }
+
+    #[test]
+    fn a_shadow_catcher_shows_shadows() {
+        let mut world = catcher_world(0);
+        world.objects.push(Box::new(blocker_above_origin()));
+        let ray = Ray::new(Point3::new(0.0, 0.2, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let Trace { color, alpha, .. } = world.trace(ray);
+        assert_eq!(color, Vector3::new(0.0, 0.0, 0.0));
+        assert!((alpha - 0.6).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_shadow_catcher_shows_ambient_occlusion() {
+        let mut world = catcher_world(16);
+        world.lights = vec![Light::point_light(
+            Point3::new(20.0, 10.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        )];
+        world.objects.push(Box::new(blocker_above_origin()));
+        let ray = Ray::new(Point3::new(0.0, 0.2, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let alpha = world.trace(ray).alpha;
+        assert!(alpha > 0.0 && alpha < 0.6);
+    }
+
+    #[test]
+    fn the_background_shows_through_a_shadow_catcher() {
+        let mut world = catcher_world(0);
+        world.transparent_background = false;
+        world.background = Vector3::new(1.0, 1.0, 1.0);
+        world.objects.push(Box::new(blocker_above_origin()));
+        let beside = Ray::new(Point3::new(5.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(world.trace(beside).color, Vector3::new(1.0, 1.0, 1.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.2, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let shadowed = world.trace(ray).color;
+        assert!((shadowed - Vector3::new(0.4, 0.4, 0.4)).norm() < 0.0001);
+    }
+
+    fn cut_cube() -> World {
+        let mut world = World::with_floor(Plane::new());
+        world.objects.push(Box::new(Cube::default()));
+        world.clip_planes = vec![ClipPlane::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        )];
+        world
+    }
+
+    #[test]
+    fn clipping_planes_remove_the_near_half_of_a_shape() {
+        let world = cut_cube();
+        let ray = Ray::new(Point3::new(0.0, 0.5, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let ts = world.intersect(ray).iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(ts, vec![6.0]);
+    }
+
+    #[test]
+    fn the_cut_face_is_capped() {
+        let world = cut_cube();
+        let ray = Ray::new(Point3::new(0.0, 0.5, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let surface = world.trace(ray).surface.unwrap();
+        assert_eq!(surface.depth, 5.0);
+        assert_eq!(surface.albedo, world.cap.color);
+        assert_eq!(surface.normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn caps_can_be_hatched() {
+        let mut world = cut_cube();
+        world.cap.hatch = Some(Hatch {
+            spacing: 0.5,
+            ..Default::default()
+        });
+
+        let albedos = (0..10)
+            .map(|i| {
+                let x = -0.9 + i as f32 * 0.1;
+                let ray = Ray::new(Point3::new(x, 0.5, 5.0), Vector3::new(0.0, 0.0, -1.0));
+                world.trace(ray).surface.unwrap().albedo
+            })
+            .collect::<Vec<_>>();
+
+        assert!(albedos.contains(&world.cap.color));
+        assert!(albedos.contains(&Hatch::default().color));
+    }
+
+    #[test]
+    fn the_floor_is_not_clipped() {
+        let mut world = cut_cube();
+        world.objects.truncate(1);
+        let ray = Ray::new(Point3::new(0.0, 5.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_eq!(world.intersect(ray).len(), 1);
     }
 }
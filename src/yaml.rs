@@ -0,0 +1,558 @@
+use crate::json::{Json, Value};
+
+/// Parses the YAML the book's scene files use into the same tree as JSON:
+/// block sequences and mappings by indentation, one-line flow `[ ... ]` and
+/// `{ ... }` collections, plain and quoted scalars, and `#` comments.
+/// Anchors, aliases, tags and multi-line strings are errors rather than
+/// being read as plain text; multiple documents aren't supported.
+pub(crate) fn parse(text: &str) -> Result<Json, String> {
+    let mut lines = vec![];
+
+    for (i, raw) in text.lines().enumerate() {
+        let number = i + 1;
+        let content = strip_comment(raw);
+
+        if content.trim().is_empty() || content.trim() == "---" {
+            continue;
+        }
+
+        let indent = content.len() - content.trim_start_matches(' ').len();
+
+        if content[indent..].starts_with('\t') {
+            return Err(format!("line {}: indent with spaces, not tabs", number));
+        }
+
+        lines.push(Line {
+            number,
+            indent,
+            content: content.trim().to_string(),
+        });
+    }
+
+    if lines.is_empty() {
+        return Ok(Json {
+            line: 1,
+            value: Value::Null,
+        });
+    }
+
+    let mut parser = Parser { lines, position: 0 };
+    let indent = parser.lines[0].indent;
+    let json = parser.block(indent)?;
+
+    match parser.lines.get(parser.position) {
+        Some(line) => Err(format!("line {}: unexpected indentation", line.number)),
+        None => Ok(json),
+    }
+}
+
+struct Line {
+    number: usize,
+    indent: usize,
+    content: String,
+}
+
+impl Line {
+    fn is_item(&self) -> bool {
+        self.content == "-" || self.content.starts_with("- ")
+    }
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    position: usize,
+}
+
+impl Parser {
+    fn block(&mut self, indent: usize) -> Result<Json, String> {
+        if self.lines[self.position].is_item() {
+            self.sequence(indent)
+        } else {
+            self.mapping(indent)
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Json, String> {
+        let line = self.lines[self.position].number;
+        let mut items = vec![];
+
+        while let Some(current) = self.lines.get(self.position) {
+            if current.indent != indent || !current.is_item() {
+                break;
+            }
+
+            let number = current.number;
+            let rest = current.content[1..].trim_start();
+            // the item's own content starts this many columns in
+            let offset = current.content.len() - rest.len();
+
+            if rest.is_empty() {
+                self.position += 1;
+                items.push(self.nested(indent, number)?);
+            } else if key_split(rest).is_some() {
+                // "- key: value" opens a mapping whose keys line up with `key`
+                let rest = rest.to_string();
+                let current = &mut self.lines[self.position];
+                current.indent = indent + offset;
+                current.content = rest;
+                items.push(self.mapping(indent + offset)?);
+            } else {
+                let value = flow(rest, number)?;
+                self.position += 1;
+                items.push(value);
+            }
+        }
+
+        Ok(Json {
+            line,
+            value: Value::Array(items),
+        })
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Json, String> {
+        let line = self.lines[self.position].number;
+        let mut members: Vec<(String, Json)> = vec![];
+
+        while let Some(current) = self.lines.get(self.position) {
+            if current.indent != indent || current.is_item() {
+                break;
+            }
+
+            let number = current.number;
+            let (key, rest) = key_split(&current.content)
+                .ok_or_else(|| format!("line {}: expected \"key: value\"", number))?;
+            let key = unquote(key, number)?;
+
+            if members.iter().any(|(existing, _)| *existing == key) {
+                return Err(format!("line {}: \"{}\" is given twice", number, key));
+            }
+
+            let value = if rest.is_empty() {
+                self.position += 1;
+
+                match self.lines.get(self.position) {
+                    // a sequence may sit at its key's own indentation
+                    Some(next) if next.indent == indent && next.is_item() => {
+                        self.sequence(indent)?
+                    }
+                    _ => self.nested(indent, number)?,
+                }
+            } else {
+                let value = flow(rest, number)?;
+                self.position += 1;
+                value
+            };
+
+            members.push((key, value));
+        }
+
+        Ok(Json {
+            line,
+            value: Value::Object(members),
+        })
+    }
+
+    /// the block indented under a key or dash on line `number`, or null
+    fn nested(&mut self, indent: usize, number: usize) -> Result<Json, String> {
+        match self.lines.get(self.position) {
+            Some(next) if next.indent > indent => {
+                let indent = next.indent;
+                self.block(indent)
+            }
+            _ => Ok(Json {
+                line: number,
+                value: Value::Null,
+            }),
+        }
+    }
+}
+
+/// `line` without its comment, leaving `#` inside quotes alone
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return &line[..i],
+            None => {}
+        }
+
+        previous = c;
+    }
+
+    line
+}
+
+/// splits `key: value` at the first colon outside brackets and quotes that
+/// ends the line or is followed by a space
+fn key_split(content: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let chars = content.char_indices().collect::<Vec<_>>();
+
+    for (n, &(i, c)) in chars.iter().enumerate() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                ':' if depth == 0 => {
+                    let followed_by_space = chars.get(n + 1).is_none_or(|(_, c)| *c == ' ');
+
+                    if followed_by_space && i > 0 {
+                        return Some((content[..i].trim(), content[i + 1..].trim()));
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    None
+}
+
+/// a scalar's text without its quotes, with double-quoted escapes and
+/// single-quoted `''` read
+fn unquote(s: &str, line: usize) -> Result<String, String> {
+    let quoted = |q| s.len() >= 2 && s.starts_with(q) && s.ends_with(q);
+
+    if quoted('\'') {
+        Ok(s[1..s.len() - 1].replace("''", "'"))
+    } else if quoted('"') {
+        unescape(&s[1..s.len() - 1]).ok_or_else(|| format!("line {}: bad escape in string", line))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        unescaped.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'u' => {
+                let hex = chars.by_ref().take(4).collect::<String>();
+
+                if hex.len() != 4 {
+                    return None;
+                }
+
+                std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+            }
+            c if c == '"' || c == '\\' || c == '/' || c == ' ' => c,
+            _ => return None,
+        });
+    }
+
+    Some(unescaped)
+}
+
+/// a scalar or a one-line flow collection
+fn flow(text: &str, line: usize) -> Result<Json, String> {
+    let mut flow = Flow {
+        chars: text.chars().collect(),
+        position: 0,
+        line,
+    };
+
+    let value = flow.value()?;
+    flow.skip_spaces();
+
+    if flow.position < flow.chars.len() {
+        return Err(flow.error("unexpected text after the value"));
+    }
+
+    Ok(value)
+}
+
+struct Flow {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl Flow {
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.position) == Some(&' ') {
+            self.position += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_spaces();
+
+        let value = match self.chars.get(self.position) {
+            Some('[') => self.collection(']')?,
+            Some('{') => self.collection('}')?,
+            Some('&') | Some('*') | Some('!') => {
+                return Err(self.error("anchors, aliases and tags aren't supported"))
+            }
+            Some('|') | Some('>') => return Err(self.error("multi-line strings aren't supported")),
+            Some(_) => scalar(&self.scalar()?, self.line)?,
+            None => Value::Null,
+        };
+
+        Ok(Json {
+            line: self.line,
+            value,
+        })
+    }
+
+    /// a `[...]` sequence or `{...}` mapping
+    fn collection(&mut self, close: char) -> Result<Value, String> {
+        self.position += 1;
+        let mut items = vec![];
+        let mut members: Vec<(String, Json)> = vec![];
+
+        loop {
+            self.skip_spaces();
+
+            match self.chars.get(self.position) {
+                Some(c) if *c == close => {
+                    self.position += 1;
+                    break;
+                }
+                None => return Err(self.error(&format!("missing '{}'", close))),
+                _ => {}
+            }
+
+            if close == '}' {
+                let key = self.scalar()?;
+
+                if self.chars.get(self.position) != Some(&':') {
+                    return Err(self.error("expected ':' after a key"));
+                }
+
+                self.position += 1;
+                members.push((unquote(&key, self.line)?, self.value()?));
+            } else {
+                items.push(self.value()?);
+            }
+
+            self.skip_spaces();
+
+            match self.chars.get(self.position) {
+                Some(',') => self.position += 1,
+                Some(c) if *c == close => {}
+                None => return Err(self.error(&format!("missing '{}'", close))),
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close))),
+            }
+        }
+
+        Ok(if close == '}' {
+            Value::Object(members)
+        } else {
+            Value::Array(items)
+        })
+    }
+
+    /// the text of a quoted or plain scalar, up to the next `,`, `:`, `]`
+    /// or `}` for plain ones
+    fn scalar(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        let start = self.position;
+
+        match self.chars.get(start) {
+            Some(&q) if q == '"' || q == '\'' => {
+                self.position += 1;
+
+                loop {
+                    match self.chars.get(self.position) {
+                        None => return Err(self.error("unterminated string")),
+                        Some('\\') if q == '"' => self.position += 2,
+                        // '' is a quote inside a single-quoted string
+                        Some(c)
+                            if *c == q
+                                && q == '\''
+                                && self.chars.get(self.position + 1) == Some(&q) =>
+                        {
+                            self.position += 2
+                        }
+                        Some(c) if *c == q => break,
+                        Some(_) => self.position += 1,
+                    }
+                }
+
+                self.position += 1;
+            }
+            _ => {
+                while self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| !",]}".contains(*c) && *c != ':')
+                {
+                    self.position += 1;
+                }
+            }
+        }
+
+        Ok(self.chars[start..self.position]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_string())
+    }
+}
+
+fn scalar(text: &str, line: usize) -> Result<Value, String> {
+    Ok(match text {
+        "" | "~" | "null" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if text.starts_with('"') || text.starts_with('\'') => Value::String(unquote(text, line)?),
+        _ => text
+            .parse()
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(text.to_string())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_a_book_scene() {
+        let yaml = parse(
+            "# a comment\n\
+             - add: camera\n\
+             \x20 width: 100\n\
+             \x20 from: [ 0, 1.5, -5 ] # the eye\n\
+             \n\
+             - define: standard-transform\n\
+             \x20 value:\n\
+             \x20   - [ translate, 1, -1, 1 ]\n\
+             \x20   - [ scale, 0.5, 0.5, 0.5 ]\n",
+        )
+        .unwrap();
+
+        let items = yaml.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+
+        let camera = items[0].as_object().unwrap();
+        assert_eq!(camera[0].0, "add");
+        assert_eq!(camera[0].1.as_str(), Ok("camera"));
+        assert_eq!(camera[1].1.as_f32(), Ok(100.0));
+        assert_eq!(camera[2].1.as_numbers(), Ok([0.0, 1.5, -5.0]));
+
+        let define = items[1].as_object().unwrap();
+        let steps = define[1].1.as_array().unwrap();
+        assert_eq!(steps[1].as_array().unwrap()[0].as_str(), Ok("scale"));
+        assert_eq!(steps[1].line, 9);
+    }
+
+    #[test]
+    fn sequences_may_sit_at_their_keys_indentation() {
+        let yaml = parse("children:\n- add: sphere\n- add: cube\nshadow: false").unwrap();
+        let members = yaml.as_object().unwrap();
+
+        assert_eq!(members[0].1.as_array().unwrap().len(), 2);
+        assert_eq!(members[1].1.as_bool(), Ok(false));
+    }
+
+    #[test]
+    fn flow_mappings_and_quotes() {
+        let yaml = parse("a: { color: [1, 0, 0], name: \"x # y\" }").unwrap();
+        let a = yaml.as_object().unwrap()[0].1.as_object().unwrap();
+
+        assert_eq!(a[0].1.as_numbers(), Ok([1.0, 0.0, 0.0]));
+        assert_eq!(a[1].1.as_str(), Ok("x # y"));
+    }
+
+    #[test]
+    fn nested_flow_collections_and_escapes() {
+        let yaml = parse(
+            "a: [ [1, [2, 3]], {}, [] ]\n\
+             b: { 'it''s': \"tab\\t \\\"q\\\" \\u00e9 é\", n: { m: ~ } }\n\
+             c: \"# not a comment\" # but this is\n",
+        )
+        .unwrap();
+        let members = yaml.as_object().unwrap();
+
+        let a = members[0].1.as_array().unwrap();
+        assert_eq!(a[0].as_array().unwrap()[1].as_numbers(), Ok([2.0, 3.0]));
+        assert_eq!(a[1].as_object(), Ok(&[][..]));
+        assert_eq!(a[2].as_array(), Ok(&[][..]));
+
+        let b = members[1].1.as_object().unwrap();
+        assert_eq!(b[0].0, "it's");
+        assert_eq!(b[0].1.as_str(), Ok("tab\t \"q\" é é"));
+        assert_eq!(b[1].1.as_object().unwrap()[0].1.value, Value::Null);
+
+        assert_eq!(members[2].1.as_str(), Ok("# not a comment"));
+    }
+
+    #[test]
+    fn unsupported_yaml_is_an_error_not_text() {
+        for (text, error) in &[
+            (
+                "a: &base [1, 2]",
+                "line 1: anchors, aliases and tags aren't supported",
+            ),
+            (
+                "- add: sphere\n  extend: *base",
+                "line 2: anchors, aliases and tags aren't supported",
+            ),
+            (
+                "a: !!str 1",
+                "line 1: anchors, aliases and tags aren't supported",
+            ),
+            (
+                "a: [ *base ]",
+                "line 1: anchors, aliases and tags aren't supported",
+            ),
+            (
+                "a: |\n  text",
+                "line 1: multi-line strings aren't supported",
+            ),
+            ("a: \"bad \\q\"", "line 1: bad escape in string"),
+            ("a: 'open", "line 1: unterminated string"),
+            ("a: [1, 2", "line 1: missing ']'"),
+            ("a: [[1] 2]", "line 1: expected ',' or ']'"),
+            ("a: { b 1 }", "line 1: expected ':' after a key"),
+            ("a: 1\na: 2", "line 2: \"a\" is given twice"),
+            ("a: 1\n\tb: 2", "line 2: indent with spaces, not tabs"),
+            ("a", "line 1: expected \"key: value\""),
+        ] {
+            assert_eq!(parse(text), Err(error.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn yaml_errors_name_the_line() {
+        assert_eq!(
+            parse("- add: sphere\n  transform: [ [scale, 1, 1, 1 ]"),
+            Err("line 2: missing ']'".to_string())
+        );
+        assert_eq!(
+            parse("a: 1\n  b: 2"),
+            Err("line 2: unexpected indentation".to_string())
+        );
+    }
+}
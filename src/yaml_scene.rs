@@ -0,0 +1,820 @@
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::cone::Cone;
+use crate::csg::{Csg, CsgOperation};
+use crate::cube::Cube;
+use crate::cylinder::Cylinder;
+use crate::disk::Disk;
+use crate::dyn_group::DynGroup;
+use crate::instance::Instance;
+use crate::json::{Json, Value};
+use crate::light::Light;
+use crate::material::Material;
+use crate::pattern::Pattern;
+use crate::plane::Plane;
+use crate::rectangle::Rectangle;
+use crate::shape::Shape;
+use crate::sphere::Sphere;
+use crate::torus::Torus;
+use crate::world::World;
+use crate::yaml;
+use nalgebra::{Matrix4, Point3, Vector3};
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Builds the world and camera a scene file in The Ray Tracer Challenge's
+/// YAML format describes: `add` for the camera, lights and shapes
+/// (`sphere`, `plane`, `cube`, `cylinder`, `cone`, `torus`, `disk`,
+/// `rectangle`, `group`, `obj` and `csg`), and `define` for named materials
+/// and transforms, which other definitions can `extend`.
+///
+/// Reflection and refraction settings are read but have no effect, as the
+/// renderer has neither. OBJ paths are relative to `base`.
+pub(crate) fn load(text: &str, base: &Path) -> Result<(World, Camera), String> {
+    let yaml = yaml::parse(text)?;
+    let mut scene = Scene {
+        defines: vec![],
+        base,
+        camera: None,
+        lights: vec![],
+        objects: vec![],
+        meshes: vec![],
+    };
+
+    for item in yaml.as_array()? {
+        let members = item.as_object()?;
+
+        if let Some(define) = member(members, "define") {
+            scene.define(item, define.as_str()?)?;
+        } else if let Some(add) = member(members, "add") {
+            scene.add(item, add.as_str()?)?;
+        } else {
+            return Err(item.error("expected \"add\" or \"define\""));
+        }
+    }
+
+    let camera = scene
+        .camera
+        .ok_or_else(|| "the scene needs a camera".to_string())?;
+    let mut world = World::new(Light::default());
+
+    if !scene.lights.is_empty() {
+        world.lights = scene.lights;
+    }
+
+    world.objects = scene.objects;
+    world.collect_emitters();
+
+    Ok((world, camera))
+}
+
+struct Scene<'a> {
+    /// definitions by name, with any `extend` already merged in
+    defines: Vec<(String, Json)>,
+    base: &'a Path,
+    camera: Option<Camera>,
+    lights: Vec<Light>,
+    objects: Vec<Box<dyn Shape>>,
+    /// obj files by path, loaded once however often they're added
+    meshes: Vec<(String, Arc<Bvh>)>,
+}
+
+impl<'a> Scene<'a> {
+    fn define(&mut self, item: &Json, name: &str) -> Result<(), String> {
+        let members = checked(item, &["define", "extend", "value"])?;
+        let value = member(members, "value").ok_or_else(|| item.error("missing \"value\""))?;
+
+        let value = match member(members, "extend") {
+            Some(extend) => {
+                let base = self.lookup(extend)?;
+
+                match (&base.value, &value.value) {
+                    // the extension's settings win over the ones it extends
+                    (Value::Object(base), Value::Object(extension)) => {
+                        let mut merged = base
+                            .iter()
+                            .filter(|(key, _)| member(extension, key).is_none())
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        merged.extend(extension.iter().cloned());
+
+                        Json {
+                            line: value.line,
+                            value: Value::Object(merged),
+                        }
+                    }
+                    (Value::Array(base), Value::Array(extension)) => Json {
+                        line: value.line,
+                        value: Value::Array(base.iter().chain(extension).cloned().collect()),
+                    },
+                    _ => return Err(extend.error("can only extend a definition of the same kind")),
+                }
+            }
+            None => value.clone(),
+        };
+        let value = self.expanded(value)?;
+
+        self.defines.retain(|(existing, _)| existing != name);
+        self.defines.push((name.to_string(), value));
+
+        Ok(())
+    }
+
+    /// a defined transform with the names in it replaced by their steps, so
+    /// that stored definitions hold no names and one can build on its own
+    /// earlier value without expanding forever
+    fn expanded(&self, value: Json) -> Result<Json, String> {
+        let steps = match value.value {
+            Value::Array(steps) => steps,
+            _ => return Ok(value),
+        };
+        let mut expanded = vec![];
+
+        for step in steps {
+            match &step.value {
+                Value::String(_) => {
+                    expanded.extend(self.lookup(&step)?.as_array()?.iter().cloned())
+                }
+                _ => expanded.push(step),
+            }
+        }
+
+        Ok(Json {
+            line: value.line,
+            value: Value::Array(expanded),
+        })
+    }
+
+    fn lookup(&self, name: &Json) -> Result<&Json, String> {
+        let key = name.as_str()?;
+
+        self.defines
+            .iter()
+            .find(|(defined, _)| defined == key)
+            .map(|(_, value)| value)
+            .ok_or_else(|| name.error(&format!("nothing is defined as \"{}\"", key)))
+    }
+
+    fn add(&mut self, item: &Json, kind: &str) -> Result<(), String> {
+        match kind {
+            "camera" => {
+                let members = checked(
+                    item,
+                    &[
+                        "add",
+                        "width",
+                        "height",
+                        "field-of-view",
+                        "from",
+                        "to",
+                        "up",
+                    ],
+                )?;
+                let get = |key: &str| {
+                    member(members, key)
+                        .ok_or_else(|| item.error(&format!("the camera needs \"{}\"", key)))
+                };
+
+                let mut camera = Camera::new(
+                    get("width")?.as_usize()?,
+                    get("height")?.as_usize()?,
+                    get("field-of-view")?.as_f32()?,
+                );
+                camera.transform = Camera::view_transforms(
+                    Point3::from(get("from")?.as_numbers()?),
+                    Point3::from(get("to")?.as_numbers()?),
+                    Vector3::from(get("up")?.as_numbers()?),
+                );
+
+                self.camera = Some(camera);
+            }
+            "light" => {
+                let members = checked(item, &["add", "at", "intensity"])?;
+                let mut light = Light::default();
+
+                if let Some(at) = member(members, "at") {
+                    light.position = Point3::from(at.as_numbers()?);
+                }
+
+                if let Some(intensity) = member(members, "intensity") {
+                    light.intensity = Vector3::from(intensity.as_numbers()?);
+                }
+
+                self.lights.push(light);
+            }
+            _ => {
+                let shape = self.shape(item)?;
+                self.objects.push(shape);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the shape `item` describes; a group's children take on its transform
+    fn shape(&mut self, item: &Json) -> Result<Box<dyn Shape>, String> {
+        let members = checked(
+            item,
+            &[
+                "add",
+                "material",
+                "transform",
+                "children",
+                "file",
+                "min",
+                "max",
+                "closed",
+                "major-radius",
+                "minor-radius",
+                "inner-radius",
+                "operation",
+                "left",
+                "right",
+                "shadow",
+            ],
+        )?;
+        let kind = member(members, "add").ok_or_else(|| item.error("expected \"add\""))?;
+
+        let transform = match member(members, "transform") {
+            Some(steps) => self.transform(steps)?,
+            None => Matrix4::identity(),
+        };
+
+        let material = match member(members, "material") {
+            Some(material) => self.material(material)?,
+            None => Material::default(),
+        };
+
+        let only_for = |key: &str, shapes: &[&str]| match member(members, key) {
+            Some(value) if !shapes.iter().any(|shape| kind.as_str() == Ok(shape)) => Err(value
+                .error(&format!(
+                    "\"{}\" only applies to a {}",
+                    key,
+                    shapes.join(" or ")
+                ))),
+            _ => Ok(()),
+        };
+        only_for("children", &["group"])?;
+        only_for("file", &["obj"])?;
+        only_for("min", &["cylinder", "cone"])?;
+        only_for("max", &["cylinder", "cone"])?;
+        only_for("closed", &["cylinder", "cone"])?;
+        only_for("major-radius", &["torus"])?;
+        only_for("minor-radius", &["torus"])?;
+        only_for("inner-radius", &["disk"])?;
+        only_for("operation", &["csg"])?;
+        only_for("left", &["csg"])?;
+        only_for("right", &["csg"])?;
+
+        let number = |key: &str, default: f32| match member(members, key) {
+            Some(value) => value.as_f32(),
+            None => Ok(default),
+        };
+        let minimum = number("min", -f32::INFINITY)?;
+        let maximum = number("max", f32::INFINITY)?;
+        let closed = match member(members, "closed") {
+            Some(value) => value.as_bool()?,
+            None => false,
+        };
+
+        Ok(match kind.as_str()? {
+            "sphere" => Box::new(Sphere {
+                transform,
+                material,
+                ..Sphere::new()
+            }),
+            "plane" => {
+                let mut plane = Plane::new();
+                plane.transform = transform;
+                plane.material = material;
+                Box::new(plane)
+            }
+            "cube" => Box::new(Cube {
+                transform,
+                material,
+                ..Cube::default()
+            }),
+            "cylinder" => Box::new(Cylinder {
+                minimum,
+                maximum,
+                closed,
+                transform,
+                material,
+            }),
+            "cone" => Box::new(Cone {
+                minimum,
+                maximum,
+                closed,
+                transform,
+                material,
+            }),
+            "torus" => {
+                let defaults = Torus::default();
+
+                Box::new(Torus {
+                    major_radius: number("major-radius", defaults.major_radius)?,
+                    minor_radius: number("minor-radius", defaults.minor_radius)?,
+                    transform,
+                    material,
+                })
+            }
+            "disk" => {
+                let inner_radius = number("inner-radius", 0.0)?;
+
+                // a hole as wide as the disk would leave v dividing by zero
+                if !(0.0..1.0).contains(&inner_radius) {
+                    return Err(member(members, "inner-radius")
+                        .map_or(item, |value| value)
+                        .error("\"inner-radius\" must be at least 0 and less than 1"));
+                }
+
+                Box::new(Disk {
+                    inner_radius,
+                    transform,
+                    material,
+                })
+            }
+            "rectangle" => Box::new(Rectangle {
+                transform,
+                material,
+            }),
+            "group" => {
+                let children = match member(members, "children") {
+                    Some(children) => children
+                        .as_array()?
+                        .iter()
+                        .map(|child| self.shape(child))
+                        .collect::<Result<_, _>>()?,
+                    None => vec![],
+                };
+
+                Box::new(DynGroup::new(children).with_transform(transform))
+            }
+            "obj" => {
+                let file = member(members, "file").ok_or_else(|| item.error("missing \"file\""))?;
+                let path = file.as_str()?;
+                let mesh = match self.meshes.iter().find(|(loaded, _)| loaded == path) {
+                    Some((_, mesh)) => mesh.clone(),
+                    None => {
+                        let reader = std::fs::File::open(self.base.join(path))
+                            .map_err(|e| file.error(&format!("{}: {}", path, e)))?;
+                        let triangles = crate::obj::parse_obj(BufReader::new(reader))
+                            .map_err(|e| file.error(&format!("{}: {}", path, e)))?;
+                        let mesh = Arc::new(Bvh::new(&triangles));
+                        self.meshes.push((path.to_string(), mesh.clone()));
+                        mesh
+                    }
+                };
+
+                Box::new(
+                    Instance::new(mesh)
+                        .with_material(material)
+                        .with_transform(transform),
+                )
+            }
+            "csg" => {
+                let side = |key: &str| {
+                    member(members, key).ok_or_else(|| item.error(&format!("missing \"{}\"", key)))
+                };
+                let operation = side("operation")?;
+                let operation = match operation.as_str()? {
+                    "union" => CsgOperation::Union,
+                    "intersection" => CsgOperation::Intersection,
+                    "difference" => CsgOperation::Difference,
+                    other => {
+                        return Err(operation.error(&format!("unknown operation \"{}\"", other)))
+                    }
+                };
+                let (left, right) = (side("left")?, side("right")?);
+
+                Box::new(
+                    Csg::new(operation, self.shape(left)?, self.shape(right)?)
+                        .with_transform(transform),
+                )
+            }
+            other => return Err(kind.error(&format!("unknown shape \"{}\"", other))),
+        })
+    }
+
+    /// a list of steps and defined names, the first step applied first
+    fn transform(&self, steps: &Json) -> Result<Matrix4<f32>, String> {
+        let mut transform = Matrix4::identity();
+
+        for step in steps.as_array()? {
+            let matrix = match &step.value {
+                Value::String(_) => self.transform(self.lookup(step)?)?,
+                _ => transform_step(step)?,
+            };
+
+            transform = matrix * transform;
+        }
+
+        Ok(transform)
+    }
+
+    fn material(&self, json: &Json) -> Result<Material, String> {
+        let json = match json.value {
+            Value::String(_) => self.lookup(json)?,
+            _ => json,
+        };
+
+        let members = checked(
+            json,
+            &[
+                "color",
+                "ambient",
+                "diffuse",
+                "specular",
+                "shininess",
+                "pattern",
+                "reflective",
+                "transparency",
+                "refractive-index",
+            ],
+        )?;
+        let mut material = Material::default();
+
+        for (key, value) in members {
+            match key.as_str() {
+                "color" => material.color = Vector3::from(value.as_numbers()?),
+                "ambient" => material.ambient = value.as_f32()?,
+                "diffuse" => material.diffuse = value.as_f32()?,
+                "specular" => material.specular = value.as_f32()?,
+                "shininess" => material.shininess = value.as_f32()?,
+                "pattern" => material.pattern = Some(std::sync::Arc::new(self.pattern(value)?)),
+                _ => {
+                    value.as_f32()?;
+                }
+            }
+        }
+
+        Ok(material)
+    }
+
+    fn pattern(&self, json: &Json) -> Result<Pattern, String> {
+        let members = checked(json, &["type", "colors", "transform"])?;
+        let kind = member(members, "type").ok_or_else(|| json.error("missing \"type\""))?;
+        let colors = member(members, "colors").ok_or_else(|| json.error("missing \"colors\""))?;
+
+        let colors = match colors.as_array()? {
+            [a, b] => (
+                Pattern::solid(Vector3::from(a.as_numbers()?)),
+                Pattern::solid(Vector3::from(b.as_numbers()?)),
+            ),
+            _ => return Err(colors.error("a pattern needs 2 colors")),
+        };
+
+        let pattern = match kind.as_str()? {
+            "stripes" => Pattern::stripe(colors.0, colors.1),
+            "gradient" => Pattern::gradient(colors.0, colors.1),
+            "rings" => Pattern::ring(colors.0, colors.1),
+            "checkers" => Pattern::checker(colors.0, colors.1),
+            other => return Err(kind.error(&format!("unknown pattern \"{}\"", other))),
+        };
+
+        Ok(match member(members, "transform") {
+            Some(steps) => pattern.with_transform(self.transform(steps)?),
+            None => pattern,
+        })
+    }
+}
+
+/// one `[ name, arguments... ]` transform step
+fn transform_step(step: &Json) -> Result<Matrix4<f32>, String> {
+    let items = step.as_array()?;
+    let name = items
+        .first()
+        .ok_or_else(|| step.error("empty transform step"))?;
+    let args = items[1..]
+        .iter()
+        .map(Json::as_f32)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let expect = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(step.error(&format!(
+                "{} takes {} numbers",
+                name.as_str().unwrap_or(""),
+                count
+            )))
+        }
+    };
+
+    match name.as_str()? {
+        "translate" => {
+            expect(3)?;
+            Ok(Matrix4::new_translation(&Vector3::new(
+                args[0], args[1], args[2],
+            )))
+        }
+        "scale" => {
+            expect(3)?;
+            Ok(Matrix4::new_nonuniform_scaling(&Vector3::new(
+                args[0], args[1], args[2],
+            )))
+        }
+        "rotate-x" => {
+            expect(1)?;
+            Ok(Matrix4::from_euler_angles(args[0], 0.0, 0.0))
+        }
+        "rotate-y" => {
+            expect(1)?;
+            Ok(Matrix4::from_euler_angles(0.0, args[0], 0.0))
+        }
+        "rotate-z" => {
+            expect(1)?;
+            Ok(Matrix4::from_euler_angles(0.0, 0.0, args[0]))
+        }
+        "shearing" => {
+            expect(6)?;
+            let (xy, xz, yx, yz, zx, zy) = (args[0], args[1], args[2], args[3], args[4], args[5]);
+
+            #[rustfmt::skip]
+            let shearing = Matrix4::new(
+                1.0, xy, xz, 0.0,
+                yx, 1.0, yz, 0.0,
+                zx, zy, 1.0, 0.0,
+                0.0, 0.0, 0.0, 1.0,
+            );
+
+            Ok(shearing)
+        }
+        other => Err(name.error(&format!("unknown transform \"{}\"", other))),
+    }
+}
+
+/// `json`'s members, refusing any not in `known` so typos don't pass silently
+fn checked<'a>(json: &'a Json, known: &[&str]) -> Result<&'a [(String, Json)], String> {
+    let members = json.as_object()?;
+
+    for (key, value) in members {
+        if !known.contains(&key.as_str()) {
+            return Err(value.error(&format!("unknown setting \"{}\"", key)));
+        }
+    }
+
+    Ok(members)
+}
+
+fn member<'a>(members: &'a [(String, Json)], key: &str) -> Option<&'a Json> {
+    members
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    const SCENE: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [ 0, 1.5, -5 ]
+  to: [ 0, 1, 0 ]
+  up: [ 0, 1, 0 ]
+
+- add: light
+  at: [ -10, 10, -10 ]
+  intensity: [ 1, 1, 1 ]
+
+- define: white-material
+  value:
+    color: [ 1, 1, 1 ]
+    diffuse: 0.7
+    reflective: 0.1
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [ 0.537, 0.831, 0.914 ]
+
+- define: standard-transform
+  value:
+    - [ translate, 1, -1, 1 ]
+    - [ scale, 0.5, 0.5, 0.5 ]
+
+- add: plane
+  material:
+    pattern:
+      type: checkers
+      colors:
+        - [ 0.35, 0.35, 0.35 ]
+        - [ 0.65, 0.65, 0.65 ]
+
+- add: cube
+  material: blue-material
+  transform:
+    - standard-transform
+    - [ translate, 4, 0, 0 ]
+
+- add: group
+  transform:
+    - [ rotate-y, 1.5707963 ]
+  children:
+    - add: sphere
+      transform:
+        - [ translate, 1, 0, 0 ]
+";
+
+    #[test]
+    fn loading_a_book_scene() {
+        let (world, camera) = load(SCENE, Path::new(".")).unwrap();
+
+        assert_eq!((camera.hsize, camera.vsize), (100, 50));
+        assert!((camera.eye() - Point3::new(0.0, 1.5, -5.0)).norm() < 1e-5);
+        assert_eq!(world.lights[0].position, Point3::new(-10.0, 10.0, -10.0));
+        assert_eq!(world.objects.len(), 3);
+        assert!(world.objects[0].material().pattern.is_some());
+    }
+
+    #[test]
+    fn extended_definitions_keep_what_they_dont_override() {
+        let (world, _) = load(SCENE, Path::new(".")).unwrap();
+        let cube = world.objects[1].material();
+
+        assert_eq!(cube.color, Vector3::new(0.537, 0.831, 0.914));
+        assert_eq!(cube.diffuse, 0.7);
+    }
+
+    #[test]
+    fn defined_transforms_expand_in_place() {
+        let (world, _) = load(SCENE, Path::new(".")).unwrap();
+        let corner = world.objects[1]
+            .transform()
+            .transform_point(&Point3::new(1.0, 1.0, 1.0));
+
+        // translated by (1, -1, 1), halved, then moved 4 along x
+        assert!((corner - Point3::new(5.0, 0.0, 1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn groups_pass_their_transform_to_children() {
+        let (world, _) = load(SCENE, Path::new(".")).unwrap();
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = world.objects[2].intersect(&ray);
+        let center = hits[0]
+            .object
+            .transform()
+            .transform_point(&Point3::new(0.0, 0.0, 0.0));
+
+        // the sphere's offset, turned a quarter about y
+        assert!((center - Point3::new(0.0, 0.0, -1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn csg_shapes_combine_their_sides() {
+        let (world, _) = load(
+            "- add: camera\n\
+             \x20 width: 10\n\
+             \x20 height: 10\n\
+             \x20 field-of-view: 1\n\
+             \x20 from: [ 0, 0, -5 ]\n\
+             \x20 to: [ 0, 0, 0 ]\n\
+             \x20 up: [ 0, 1, 0 ]\n\
+             - add: csg\n\
+             \x20 operation: intersection\n\
+             \x20 left:\n\
+             \x20   add: sphere\n\
+             \x20   transform: [ [ translate, 0, 0, -0.5 ] ]\n\
+             \x20 right:\n\
+             \x20   add: sphere\n\
+             \x20   transform: [ [ translate, 0, 0, 0.5 ] ]\n",
+            Path::new("."),
+        )
+        .unwrap();
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let ts = world.objects[0]
+            .intersect(&ray)
+            .iter()
+            .map(|hit| hit.t)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ts.len(), 2);
+        assert!((ts[0] - 4.5).abs() < 1e-4 && (ts[1] - 5.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn tori_disks_and_rectangles_take_their_radii() {
+        let (world, _) = load(
+            "- add: camera\n\
+             \x20 width: 10\n\
+             \x20 height: 10\n\
+             \x20 field-of-view: 1\n\
+             \x20 from: [ 0, 0, -5 ]\n\
+             \x20 to: [ 0, 0, 0 ]\n\
+             \x20 up: [ 0, 1, 0 ]\n\
+             - add: torus\n\
+             \x20 major-radius: 2\n\
+             \x20 minor-radius: 0.5\n\
+             - add: disk\n\
+             \x20 inner-radius: 0.5\n\
+             - add: rectangle\n",
+            Path::new("."),
+        )
+        .unwrap();
+
+        // straight down through the torus's tube, the washer's hole and
+        // the rectangle
+        let ray = Ray::new(Point3::new(0.0, 5.0, 2.0), Vector3::new(0.0, -1.0, 0.0));
+        let ts = world.objects[0]
+            .intersect(&ray)
+            .iter()
+            .map(|hit| hit.t)
+            .collect::<Vec<_>>();
+        assert_eq!(ts.len(), 2);
+        assert!((ts[0] - 4.5).abs() < 1e-4 && (ts[1] - 5.5).abs() < 1e-4);
+
+        let ray = Ray::new(Point3::new(0.25, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(world.objects[1].intersect(&ray).is_empty());
+        assert_eq!(world.objects[2].intersect(&ray).len(), 1);
+
+        assert_eq!(
+            load("- add: sphere\n  inner-radius: 0.5", Path::new("."))
+                .err()
+                .unwrap(),
+            "line 2: \"inner-radius\" only applies to a disk"
+        );
+    }
+
+    #[test]
+    fn book_errors_name_the_line() {
+        let error = |text: &str| load(text, Path::new(".")).err().unwrap();
+
+        assert_eq!(
+            error("- add: sphere\n  material: red-material"),
+            "line 2: nothing is defined as \"red-material\""
+        );
+        assert_eq!(
+            error("- add: sphere\n  transform:\n    - [ twist, 1 ]"),
+            "line 3: unknown transform \"twist\""
+        );
+        assert_eq!(
+            error("- add: sphere\n  colour: [1, 0, 0]"),
+            "line 2: unknown setting \"colour\""
+        );
+        assert_eq!(
+            error("- add: disk\n  inner-radius: 1"),
+            "line 2: \"inner-radius\" must be at least 0 and less than 1"
+        );
+        assert_eq!(error("- add: sphere"), "the scene needs a camera");
+    }
+
+    #[test]
+    fn a_definition_can_build_on_its_own_earlier_value() {
+        let (world, _) = load(
+            "- add: camera\n\
+             \x20 width: 10\n\
+             \x20 height: 10\n\
+             \x20 field-of-view: 1\n\
+             \x20 from: [ 0, 0, -5 ]\n\
+             \x20 to: [ 0, 0, 0 ]\n\
+             \x20 up: [ 0, 1, 0 ]\n\
+             - define: t\n\
+             \x20 value: [ [ translate, 1, 2, 3 ] ]\n\
+             - define: t\n\
+             \x20 value: [ t, [ scale, 2, 2, 2 ] ]\n\
+             - add: sphere\n\
+             \x20 transform: [ t ]\n",
+            Path::new("."),
+        )
+        .unwrap();
+        let origin = world.objects[0]
+            .transform()
+            .transform_point(&Point3::new(0.0, 0.0, 0.0));
+
+        // translated by (1, 2, 3), then doubled
+        assert!((origin - Point3::new(2.0, 4.0, 6.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn every_light_is_added() {
+        let (world, _) = load(
+            "- add: camera\n\
+             \x20 width: 10\n\
+             \x20 height: 10\n\
+             \x20 field-of-view: 1\n\
+             \x20 from: [ 0, 0, -5 ]\n\
+             \x20 to: [ 0, 0, 0 ]\n\
+             \x20 up: [ 0, 1, 0 ]\n\
+             - add: light\n\
+             \x20 at: [ -10, 10, -10 ]\n\
+             - add: light\n\
+             \x20 at: [ 10, 10, -10 ]\n\
+             \x20 intensity: [ 0.5, 0.5, 0.5 ]\n",
+            Path::new("."),
+        )
+        .unwrap();
+
+        assert_eq!(world.lights.len(), 2);
+        assert_eq!(world.lights[1].position, Point3::new(10.0, 10.0, -10.0));
+        assert_eq!(world.lights[1].intensity, Vector3::new(0.5, 0.5, 0.5));
+    }
+}